@@ -0,0 +1,69 @@
+//! A crude comparison of the log call overhead of the proxies.
+//!
+//! Run with `cargo bench`.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use log_reroute::{Reroute, TypedReroute};
+
+const ROUNDS: u32 = 10_000_000;
+
+struct Noop;
+
+impl Log for Noop {
+    fn enabled(&self, _: &Metadata) -> bool {
+        true
+    }
+    fn log(&self, record: &Record) {
+        black_box(record);
+    }
+    fn flush(&self) {}
+}
+
+/// Like [`Noop`], but accepts only the `Info` level and more severe.
+struct InfoOnly;
+
+impl Log for InfoOnly {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Info
+    }
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            black_box(record);
+        }
+    }
+    fn flush(&self) {}
+}
+
+fn measure(name: &str, log: &dyn Log) {
+    measure_level(name, Level::Info, log);
+}
+
+fn measure_level(name: &str, level: Level, log: &dyn Log) {
+    let args = format_args!("Hello");
+    let record = Record::builder()
+        .args(args)
+        .level(level)
+        .target("bench")
+        .build();
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        log.log(black_box(&record));
+    }
+    let elapsed = start.elapsed();
+    println!("{:>12}: {:?} per call", name, elapsed / ROUNDS);
+}
+
+fn main() {
+    measure("direct", &Noop);
+    measure("Reroute", &Reroute::with(Noop));
+    measure("TypedReroute", &TypedReroute::with(Noop));
+
+    // Records filtered out, by the slave and by the gate
+    measure_level("slave filter", Level::Debug, &Reroute::with(InfoOnly));
+    let gated = Reroute::with(Noop);
+    gated.set_gate(LevelFilter::Info);
+    measure_level("gate", Level::Debug, &gated);
+}
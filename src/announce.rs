@@ -0,0 +1,42 @@
+//! Records announcing the swaps.
+//!
+//! See [`Reroute::announce_swaps`][crate::Reroute::announce_swaps].
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{Level, Record};
+
+use crate::Slave;
+
+/// The target of the announcing records.
+pub const SWAP_TARGET: &str = "log_reroute::swap";
+
+/// An announcement waiting to be sent once the state is unlocked.
+pub(crate) struct Announcement {
+    pub(crate) old: &'static str,
+    pub(crate) new: &'static str,
+    pub(crate) at: SystemTime,
+    /// Where to send it.
+    pub(crate) through: Slave,
+}
+
+impl Announcement {
+    pub(crate) fn emit(self) {
+        // Before the epoch can happen only with a really broken clock, don't fail on that.
+        let at = self.at.duration_since(UNIX_EPOCH).unwrap_or_default();
+        self.through.log(
+            &Record::builder()
+                .level(Level::Info)
+                .target(SWAP_TARGET)
+                .module_path_static(Some(module_path!()))
+                .args(format_args!(
+                    "Logging rerouted from {} to {} at {}.{:03}",
+                    self.old,
+                    self.new,
+                    at.as_secs(),
+                    at.subsec_millis(),
+                ))
+                .build(),
+        );
+    }
+}
@@ -0,0 +1,250 @@
+//! Logging in a background thread.
+
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::Arc;
+
+use log::{Log, Metadata, Record};
+
+use crate::record::OwnedRecord;
+use crate::worker::Worker;
+
+/// How many records an [`Async`] queues by default.
+pub const DEFAULT_ASYNC_CAPACITY: usize = 1024;
+
+enum Message {
+    Record(OwnedRecord),
+    /// Flush and confirm it's done.
+    Flush(SyncSender<()>),
+    Stop,
+}
+
+fn handle<L: Log>(inner: &L, message: Message) {
+    match message {
+        Message::Record(record) => record.emit_to(inner),
+        Message::Flush(done) => {
+            inner.flush();
+            let _ = done.send(());
+        }
+        Message::Stop => (),
+    }
+}
+
+/// Handles one message, returns if there'll be more.
+fn step<L: Log>(inner: &L, messages: &Receiver<Message>, stopping: &AtomicBool) -> bool {
+    if !stopping.load(Ordering::Acquire) {
+        match messages.recv() {
+            Ok(Message::Stop) | Err(_) => (),
+            Ok(message) => {
+                handle(inner, message);
+                return true;
+            }
+        }
+    }
+    // Deliver what's left before ending.
+    for message in messages.try_iter() {
+        handle(inner, message);
+    }
+    inner.flush();
+    false
+}
+
+/// Keeps the background thread of an [`Async`] logger (or of a
+/// [`NonBlockingFileLog`][crate::NonBlockingFileLog]) running.
+///
+/// Dropping it delivers all the queued records, flushes the logger and waits for the thread to
+/// terminate (at most for the [shutdown timeout][crate::set_shutdown_timeout]). It is meant to be
+/// kept in `main` (or wherever the application shuts down), so no records are lost on exit. After
+/// that, the [`Async`] logs directly in the calling threads.
+#[derive(Debug)]
+pub struct WorkerGuard {
+    worker: Option<Worker>,
+}
+
+impl WorkerGuard {
+    pub(crate) fn new(worker: Option<Worker>) -> Self {
+        Self { worker }
+    }
+
+    /// The background thread, if it could be started.
+    pub fn worker(&self) -> Option<&Worker> {
+        self.worker.as_ref()
+    }
+}
+
+impl Drop for WorkerGuard {
+    fn drop(&mut self) {
+        if let Some(worker) = self.worker.take() {
+            worker.stop();
+        }
+    }
+}
+
+/// A wrapper passing the records to the inner logger in a background thread.
+///
+/// Each record is copied (with its message formatted, see [`OwnedRecord`]) and queued, the
+/// logging thread doesn't wait for the inner logger. The queue is bounded (by
+/// [`DEFAULT_ASYNC_CAPACITY`] or by [`with_capacity`][Async::with_capacity]); if the logger can't
+/// keep up and the queue gets full, the logging threads wait for room. A [flush][Log::flush]
+/// waits until everything queued before it is delivered and the inner logger is flushed.
+///
+/// The background thread runs as long as the returned [`WorkerGuard`] is kept. Once it's dropped
+/// (or if the thread couldn't be started), the records are passed to the inner logger directly.
+/// The records logged from inside the background thread (eg. by the inner logger itself) are
+/// passed directly too. The thread is a [`Worker`], a panic of the inner logger loses only the
+/// record that caused it.
+///
+/// ```rust
+/// use std::sync::{Arc, Mutex};
+///
+/// use log::{Level, Log, Record};
+/// use log_reroute::{Async, FnLog};
+///
+/// let messages = Arc::new(Mutex::new(Vec::new()));
+/// let collect = Arc::clone(&messages);
+/// let (log, guard) = Async::new(FnLog::new(move |record: &Record| {
+///     collect.lock().unwrap().push(record.args().to_string());
+/// }));
+///
+/// for i in 0..3 {
+///     log.log(
+///         &Record::builder()
+///             .level(Level::Info)
+///             .args(format_args!("Message {}", i))
+///             .build(),
+///     );
+/// }
+/// log.flush();
+/// assert_eq!(3, messages.lock().unwrap().len());
+///
+/// log.log(
+///     &Record::builder()
+///         .level(Level::Info)
+///         .args(format_args!("Last one"))
+///         .build(),
+/// );
+/// // Delivers everything before returning
+/// drop(guard);
+/// assert_eq!("Last one", messages.lock().unwrap()[3]);
+/// ```
+///
+/// Surviving a panic of the inner logger:
+///
+/// ```rust
+/// use std::sync::{Arc, Mutex};
+///
+/// use log::{Level, Log, Record};
+/// use log_reroute::{Async, FnLog};
+///
+/// fn info(log: &dyn Log, msg: &str) {
+///     log.log(
+///         &Record::builder()
+///             .level(Level::Info)
+///             .args(format_args!("{}", msg))
+///             .build(),
+///     );
+/// }
+///
+/// let messages = Arc::new(Mutex::new(Vec::new()));
+/// let collect = Arc::clone(&messages);
+/// let (log, guard) = Async::new(FnLog::new(move |record: &Record| {
+///     let msg = record.args().to_string();
+///     if msg == "poison" {
+///         panic!("Can't log the poison");
+///     }
+///     collect.lock().unwrap().push(msg);
+/// }));
+///
+/// info(&log, "before");
+/// info(&log, "poison");
+/// info(&log, "after");
+/// log.flush();
+/// assert_eq!(vec!["before", "after"], *messages.lock().unwrap());
+/// let worker = guard.worker().unwrap();
+/// assert!(worker.is_healthy());
+/// assert_eq!(1, worker.panics());
+/// ```
+pub struct Async<L> {
+    inner: Arc<L>,
+    sender: SyncSender<Message>,
+    worker: Option<Worker>,
+}
+
+impl<L: Log + 'static> Async<L> {
+    /// Starts the background thread, with a queue of the default capacity.
+    pub fn new(inner: L) -> (Self, WorkerGuard) {
+        Self::with_capacity(inner, DEFAULT_ASYNC_CAPACITY)
+    }
+
+    /// Starts the background thread, with a queue of up to `capacity` records.
+    pub fn with_capacity(inner: L, capacity: usize) -> (Self, WorkerGuard) {
+        let inner = Arc::new(inner);
+        let (sender, receiver) = mpsc::sync_channel(capacity);
+        let stopping = Arc::new(AtomicBool::new(false));
+        let worker_inner = Arc::clone(&inner);
+        let worker_stopping = Arc::clone(&stopping);
+        let wake = sender.clone();
+        // If it fails, the receiver is dropped and everything is logged directly.
+        let worker = Worker::spawn(
+            move || step(&*worker_inner, &receiver, &worker_stopping),
+            move || {
+                stopping.store(true, Ordering::Release);
+                // If the queue is full, the worker sees the flag after the next record.
+                let _ = wake.try_send(Message::Stop);
+            },
+        )
+        .ok();
+        let log = Self {
+            inner,
+            sender,
+            worker: worker.clone(),
+        };
+        (log, WorkerGuard { worker })
+    }
+}
+
+impl<L> Async<L> {
+    /// The wrapped logger.
+    pub fn inner(&self) -> &L {
+        &self.inner
+    }
+
+    fn in_worker(&self) -> bool {
+        self.worker.as_ref().is_some_and(Worker::is_current)
+    }
+}
+
+impl<L> Debug for Async<L> {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_struct("Async")
+            .field("worker", &self.worker)
+            .finish()
+    }
+}
+
+impl<L: Log> Log for Async<L> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+    fn log(&self, record: &Record) {
+        if self.in_worker() {
+            // Queueing would wait for ourselves if the queue is full.
+            return self.inner.log(record);
+        }
+        let owned = OwnedRecord::from_record(record);
+        if self.sender.send(Message::Record(owned)).is_err() {
+            // The worker is gone.
+            self.inner.log(record);
+        }
+    }
+    fn flush(&self) {
+        if self.in_worker() {
+            return self.inner.flush();
+        }
+        let (done, wait) = mpsc::sync_channel(1);
+        if self.sender.send(Message::Flush(done)).is_err() || wait.recv().is_err() {
+            self.inner.flush();
+        }
+    }
+}
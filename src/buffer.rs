@@ -0,0 +1,416 @@
+//! Keeping the early records until the real logger is known.
+
+use std::collections::VecDeque;
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::mem;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use log::{Level, Log, Metadata, Record};
+
+use crate::cycle;
+use crate::record::OwnedRecord;
+use crate::{typed, FlushOld, Reroute, Slave};
+
+/// How many records a [`Buffer`] keeps by default.
+pub const DEFAULT_BUFFER_RECORDS: usize = 1024;
+
+/// How many bytes of records a [`Buffer`] keeps by default.
+pub const DEFAULT_BUFFER_BYTES: usize = 1024 * 1024;
+
+/// What a full [`Buffer`] does with another record.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Overflow {
+    /// Drops the oldest records to make room for the new one.
+    #[default]
+    DropOldest,
+    /// Drops the new record.
+    DropNewest,
+}
+
+#[derive(Default)]
+struct Collected {
+    records: VecDeque<OwnedRecord>,
+    bytes: usize,
+    dropped: u64,
+}
+
+enum Content {
+    Collecting(Collected),
+    /// Already replayed, the late records go directly to the new slave.
+    Forwarding(Slave),
+}
+
+/// The size of the record, for the limits.
+fn size(record: &OwnedRecord) -> usize {
+    record.message().len() + record.target().len()
+}
+
+/// A logger storing all the records, to be replayed into the real logger later on.
+///
+/// This is meant to be the initial slave of a [`Reroute`] (see
+/// [`RerouteBuilder::buffer_early`][crate::RerouteBuilder::buffer_early]), so no records are
+/// lost before the real logger is set up. The real logger is then installed by
+/// [`Reroute::reroute_and_replay`], which sends it all the stored records first.
+///
+/// The records are stored as owned copies (with the message formatted). The buffer is limited
+/// both by the number of records and their total size, what happens with more records is
+/// decided by the [`Overflow`] policy. The dropped records are counted and, when replayed, a
+/// warning about them comes first.
+///
+/// ```rust
+/// use log::{Level, Log, Record};
+/// use log_reroute::{Buffer, Overflow};
+///
+/// fn info(log: &dyn Log, msg: &str) {
+///     log.log(
+///         &Record::builder()
+///             .level(Level::Info)
+///             .args(format_args!("{}", msg))
+///             .build(),
+///     );
+/// }
+///
+/// let buffer = Buffer::with_limits(2, usize::MAX, Overflow::DropOldest);
+/// info(&buffer, "1");
+/// info(&buffer, "2");
+/// assert_eq!((2, 0), (buffer.len(), buffer.dropped()));
+/// info(&buffer, "3");
+/// assert_eq!((2, 1), (buffer.len(), buffer.dropped()));
+///
+/// // "12345" and "678" are exactly 8 bytes
+/// let buffer = Buffer::with_limits(usize::MAX, 8, Overflow::DropNewest);
+/// info(&buffer, "12345");
+/// info(&buffer, "678");
+/// assert_eq!((2, 0), (buffer.len(), buffer.dropped()));
+/// info(&buffer, "9");
+/// assert_eq!((2, 1), (buffer.len(), buffer.dropped()));
+/// ```
+pub struct Buffer {
+    max_records: usize,
+    max_bytes: usize,
+    overflow: Overflow,
+    content: Mutex<Content>,
+}
+
+impl Buffer {
+    /// Creates an empty buffer with the default limits.
+    ///
+    /// It keeps up to [`DEFAULT_BUFFER_RECORDS`] and [`DEFAULT_BUFFER_BYTES`], dropping the
+    /// oldest records.
+    pub fn new() -> Self {
+        Self::with_limits(
+            DEFAULT_BUFFER_RECORDS,
+            DEFAULT_BUFFER_BYTES,
+            Overflow::default(),
+        )
+    }
+
+    /// Creates an empty buffer with custom limits.
+    ///
+    /// The size of a record is counted as the size of its message and target.
+    pub fn with_limits(max_records: usize, max_bytes: usize, overflow: Overflow) -> Self {
+        Self {
+            max_records,
+            max_bytes,
+            overflow,
+            content: Mutex::new(Content::Collecting(Collected::default())),
+        }
+    }
+
+    /// How many records are stored.
+    pub fn len(&self) -> usize {
+        match &*self.lock() {
+            Content::Collecting(collected) => collected.records.len(),
+            Content::Forwarding(_) => 0,
+        }
+    }
+
+    /// How many records were dropped because of the limits.
+    pub fn dropped(&self) -> u64 {
+        match &*self.lock() {
+            Content::Collecting(collected) => collected.dropped,
+            Content::Forwarding(_) => 0,
+        }
+    }
+
+    /// Is the buffer empty?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Content> {
+        self.content.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Takes the stored records out.
+    ///
+    /// The buffer keeps collecting the records logged meanwhile (by someone still holding it),
+    /// so they can be replayed after the ones taken now. Once there is nothing left, the later
+    /// records go directly to the target.
+    fn drain(&self, target: &Slave) -> Option<Collected> {
+        let mut content = self.lock();
+        match &mut *content {
+            Content::Collecting(collected)
+                if collected.records.is_empty() && collected.dropped == 0 =>
+            {
+                *content = Content::Forwarding(Arc::clone(target));
+                None
+            }
+            Content::Collecting(collected) => Some(mem::take(collected)),
+            Content::Forwarding(_) => None,
+        }
+    }
+
+    fn store(&self, collected: &mut Collected, record: OwnedRecord) {
+        let needed = size(&record);
+        let fits = |c: &Collected| {
+            c.records.len() < self.max_records && c.bytes + needed <= self.max_bytes
+        };
+        if self.overflow == Overflow::DropOldest {
+            while !fits(collected) {
+                match collected.records.pop_front() {
+                    Some(oldest) => {
+                        collected.bytes -= size(&oldest);
+                        collected.dropped += 1;
+                    }
+                    None => break,
+                }
+            }
+        }
+        if fits(collected) {
+            collected.bytes += needed;
+            collected.records.push_back(record);
+        } else {
+            collected.dropped += 1;
+        }
+    }
+}
+
+impl Default for Buffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for Buffer {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_struct("Buffer")
+            .field("max_records", &self.max_records)
+            .field("max_bytes", &self.max_bytes)
+            .field("overflow", &self.overflow)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl Log for Buffer {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        match &*self.lock() {
+            Content::Collecting(_) => true,
+            Content::Forwarding(target) => target.enabled(metadata),
+        }
+    }
+    fn log(&self, record: &Record) {
+        let owned = OwnedRecord::from_record(record);
+        let target = match &mut *self.lock() {
+            Content::Collecting(collected) => return self.store(collected, owned),
+            Content::Forwarding(target) => Arc::clone(target),
+        };
+        target.log(record);
+    }
+    fn flush(&self) {}
+}
+
+fn replay(target: &dyn Log, collected: Collected) {
+    if collected.dropped > 0 {
+        target.log(
+            &Record::builder()
+                .level(Level::Warn)
+                .target("log_reroute")
+                .args(format_args!(
+                    "{} early records were discarded due to buffer capacity",
+                    collected.dropped
+                ))
+                .build(),
+        );
+    }
+    for record in collected.records {
+        record.emit_to(target);
+    }
+}
+
+impl Reroute {
+    /// Sets a new slave, replaying the records stored by the previous one first.
+    ///
+    /// If the current slave is a (typed) [`Buffer`], all the records stored in it are sent to the
+    /// new slave, in the original order, before any live records. The records logged meanwhile
+    /// are held back (as by [`pause`][Reroute::pause]) and follow the stored ones. This includes
+    /// the records logged into the buffer itself by a thread still holding onto it, these are
+    /// sent to the new slave only after the stored ones. If the buffer had to drop some records,
+    /// a warning about it is sent first.
+    ///
+    /// If the current slave is something else, this is the same as
+    /// [`reroute`][Reroute::reroute].
+    ///
+    /// ```rust
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// use log::{Level, Log, Metadata, Record};
+    /// use log_reroute::{Buffer, Overflow, Reroute};
+    ///
+    /// #[derive(Clone, Default)]
+    /// struct Messages(Arc<Mutex<Vec<String>>>);
+    ///
+    /// impl Log for Messages {
+    ///     fn enabled(&self, _: &Metadata) -> bool {
+    ///         true
+    ///     }
+    ///     fn log(&self, record: &Record) {
+    ///         self.0.lock().unwrap().push(record.args().to_string());
+    ///     }
+    ///     fn flush(&self) {}
+    /// }
+    ///
+    /// fn info(reroute: &Reroute, msg: &str) {
+    ///     reroute.log(
+    ///         &Record::builder()
+    ///             .level(Level::Info)
+    ///             .args(format_args!("{}", msg))
+    ///             .build(),
+    ///     );
+    /// }
+    ///
+    /// let reroute = Reroute::builder().buffer_early().build();
+    /// info(&reroute, "Early");
+    /// info(&reroute, "Also early");
+    ///
+    /// let messages = Messages::default();
+    /// reroute.reroute_and_replay(messages.clone());
+    /// info(&reroute, "Live");
+    /// assert_eq!(vec!["Early", "Also early", "Live"], *messages.0.lock().unwrap());
+    ///
+    /// // With a limited buffer
+    /// let reroute = Reroute::builder()
+    ///     .initial(Buffer::with_limits(1, usize::MAX, Overflow::DropOldest))
+    ///     .build();
+    /// info(&reroute, "Lost");
+    /// info(&reroute, "Kept");
+    /// let messages = Messages::default();
+    /// reroute.reroute_and_replay(messages.clone());
+    /// assert_eq!(
+    ///     vec!["1 early records were discarded due to buffer capacity", "Kept"],
+    ///     *messages.0.lock().unwrap(),
+    /// );
+    /// ```
+    ///
+    /// With the `kv` feature, the key-values survive the buffering:
+    ///
+    /// ```rust
+    /// # #![cfg_attr(not(feature = "kv"), allow(unused))]
+    /// # #[cfg(feature = "kv")]
+    /// # fn main() {
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// use log::kv::Key;
+    /// use log::{LevelFilter, Log, Metadata, Record};
+    /// use log_reroute::Reroute;
+    ///
+    /// #[derive(Clone, Default)]
+    /// struct Keys(Arc<Mutex<Vec<String>>>);
+    ///
+    /// impl Log for Keys {
+    ///     fn enabled(&self, _: &Metadata) -> bool {
+    ///         true
+    ///     }
+    ///     fn log(&self, record: &Record) {
+    ///         let key = record.key_values().get(Key::from_str("key")).unwrap();
+    ///         assert_eq!(Some(42), key.to_u64());
+    ///         self.0.lock().unwrap().push(record.args().to_string());
+    ///     }
+    ///     fn flush(&self) {}
+    /// }
+    ///
+    /// log::set_max_level(LevelFilter::Info);
+    /// let reroute = Reroute::builder().buffer_early().build();
+    /// log::info!(logger: &reroute, key = 42; "msg");
+    /// let keys = Keys::default();
+    /// reroute.reroute_and_replay(keys.clone());
+    /// assert_eq!(vec!["msg"], *keys.0.lock().unwrap());
+    /// # }
+    /// # #[cfg(not(feature = "kv"))]
+    /// # fn main() {}
+    /// ```
+    ///
+    /// The stored records come first even when another thread is logging into the buffer at the
+    /// same time:
+    ///
+    /// ```rust
+    /// # use std::sync::{Arc, Mutex};
+    /// # use log::{Level, Log, Metadata, Record};
+    /// # use log_reroute::Reroute;
+    /// # #[derive(Clone, Default)]
+    /// # struct Messages(Arc<Mutex<Vec<String>>>);
+    /// # impl Log for Messages {
+    /// #     fn enabled(&self, _: &Metadata) -> bool {
+    /// #         true
+    /// #     }
+    /// #     fn log(&self, record: &Record) {
+    /// #         self.0.lock().unwrap().push(record.args().to_string());
+    /// #     }
+    /// #     fn flush(&self) {}
+    /// # }
+    /// fn info(log: &dyn Log, msg: String) {
+    ///     log.log(
+    ///         &Record::builder()
+    ///             .level(Level::Info)
+    ///             .args(format_args!("{}", msg))
+    ///             .build(),
+    ///     );
+    /// }
+    ///
+    /// let reroute = Reroute::builder().buffer_early().build();
+    /// for i in 0..100 {
+    ///     info(&reroute, format!("Early {}", i));
+    /// }
+    /// // Someone still logging into the buffer directly
+    /// let buffer = reroute.get();
+    /// let late = std::thread::spawn(move || {
+    ///     for i in 0..100 {
+    ///         info(&**buffer, format!("Late {}", i));
+    ///     }
+    /// });
+    ///
+    /// let messages = Messages::default();
+    /// reroute.reroute_and_replay(messages.clone());
+    /// late.join().unwrap();
+    ///
+    /// let messages = messages.0.lock().unwrap();
+    /// let expected = (0..100)
+    ///     .map(|i| format!("Early {}", i))
+    ///     .chain((0..100).map(|i| format!("Late {}", i)))
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(expected, *messages);
+    /// ```
+    pub fn reroute_and_replay<L: Log + 'static>(&self, log: L) {
+        let (log, info) = typed(log);
+        let _pause = self.pause();
+        let displaced = self.replace_unconditionally(Arc::clone(&log), info);
+        let buffer = displaced
+            .info
+            .any
+            .clone()
+            .and_then(|any| any.downcast::<Buffer>().ok());
+        if let Some(buffer) = buffer {
+            // Straight into the new slave (through the layers), not to the overrides this thread
+            // might have.
+            cycle::nest(self, (), || {
+                self.through_layers(&**log, |target| {
+                    while let Some(collected) = buffer.drain(&log) {
+                        replay(target, collected);
+                    }
+                })
+            });
+        }
+        FlushOld::default().dispose(displaced.slave);
+    }
+}
@@ -0,0 +1,209 @@
+//! Configuring a [`Reroute`] up front.
+
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, PoisonError};
+
+use log::{Log, SetLoggerError};
+
+use crate::hook::Hooks;
+use crate::pause::DEFAULT_PAUSE_CAPACITY;
+use crate::{
+    typed, Buffer, CountingDummy, Reentry, Reroute, Slave, SlaveInfo, SwapEvent,
+    DEFAULT_HISTORY_CAPACITY,
+};
+
+/// A builder of a configured [`Reroute`].
+///
+/// Created by [`Reroute::builder`]. Unlike setting the options on an existing instance, the
+/// reroute is configured before anything can log into it.
+///
+/// ```rust
+/// use log_reroute::{Dummy, Reroute};
+///
+/// let reroute = Reroute::builder()
+///     .initial(Dummy)
+///     .announce_swaps(true)
+///     .buffer_capacity(16)
+///     .history_capacity(4)
+///     .build();
+/// assert!(!reroute.is_active());
+/// ```
+pub struct RerouteBuilder {
+    initial: Option<(Slave, SlaveInfo)>,
+    rest: Option<(Slave, SlaveInfo)>,
+    announce: bool,
+    reentry: Reentry,
+    counting: bool,
+    buffer_capacity: usize,
+    history_capacity: usize,
+    hooks: Hooks,
+}
+
+impl RerouteBuilder {
+    /// Creates a builder with the default options.
+    ///
+    /// The result would be the same as [`Reroute::new`].
+    pub const fn new() -> Self {
+        Self {
+            initial: None,
+            rest: None,
+            announce: false,
+            reentry: Reentry::Stderr,
+            counting: false,
+            buffer_capacity: DEFAULT_PAUSE_CAPACITY,
+            history_capacity: DEFAULT_HISTORY_CAPACITY,
+            hooks: Vec::new(),
+        }
+    }
+
+    /// Sets the slave the reroute starts with.
+    ///
+    /// Without it, it starts with the [rest state][RerouteBuilder::rest_state].
+    pub fn initial<L: Log + 'static>(mut self, log: L) -> Self {
+        self.initial = Some(typed(log));
+        self
+    }
+
+    /// Sets an already boxed slave to start with.
+    pub fn initial_boxed(mut self, log: Box<dyn Log>) -> Self {
+        self.initial = Some((Arc::new(log), SlaveInfo::boxed()));
+        self
+    }
+
+    /// Starts with a [`Buffer`], keeping the records until the real logger is installed by
+    /// [`Reroute::reroute_and_replay`].
+    pub fn buffer_early(self) -> Self {
+        self.initial(Buffer::new())
+    }
+
+    /// Starts with a [`CountingDummy`] that sends a summary of the discarded records to the first
+    /// real slave.
+    pub fn count_discarded(self) -> Self {
+        self.initial(CountingDummy::with_summary())
+    }
+
+    /// Sets the [rest state][Reroute::set_rest_state].
+    pub fn rest_state<L: Log + 'static>(mut self, log: L) -> Self {
+        self.rest = Some(typed(log));
+        self
+    }
+
+    /// Turns on or off the [announcing of swaps][Reroute::announce_swaps].
+    pub const fn announce_swaps(mut self, announce: bool) -> Self {
+        self.announce = announce;
+        self
+    }
+
+    /// Sets what happens with the [records logged from inside the slave][Reroute::set_reentry].
+    pub const fn reentry(mut self, reentry: Reentry) -> Self {
+        self.reentry = reentry;
+        self
+    }
+
+    /// Turns on or off [counting of the records][Reroute::set_counting].
+    pub const fn counting(mut self, counting: bool) -> Self {
+        self.counting = counting;
+        self
+    }
+
+    /// Sets how many records a [pause][Reroute::pause] holds.
+    pub const fn buffer_capacity(mut self, capacity: usize) -> Self {
+        self.buffer_capacity = capacity;
+        self
+    }
+
+    /// Sets how many swaps are kept in the [history][Reroute::history].
+    pub const fn history_capacity(mut self, capacity: usize) -> Self {
+        self.history_capacity = capacity;
+        self
+    }
+
+    /// Adds a [hook][Reroute::on_swap].
+    ///
+    /// The hooks are not called for the initial slave.
+    pub fn on_swap<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&SwapEvent) + Send + Sync + 'static,
+    {
+        let id = self.hooks.len() as u64;
+        self.hooks.push((id, Arc::new(hook)));
+        self
+    }
+
+    /// Creates the configured reroute.
+    pub fn build(self) -> Reroute {
+        let rest = self.rest;
+        let (slave, info) = match self.initial {
+            Some(initial) => initial,
+            None => match &rest {
+                Some((slave, info)) => {
+                    let mut info = info.clone();
+                    info.rest = true;
+                    (Arc::clone(slave), info)
+                }
+                None => typed(crate::Dummy),
+            },
+        };
+        let mut reroute = Reroute::with_info(slave, info);
+        let state = reroute
+            .state
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        state.rest = rest;
+        state.history.set_capacity(self.history_capacity);
+        state.next_id = state.next_id.max(self.hooks.len() as u64);
+        state.hooks = self.hooks;
+        reroute
+            .buffer
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .default_capacity = self.buffer_capacity;
+        reroute.announce.store(self.announce, Ordering::Relaxed);
+        reroute.set_reentry(self.reentry);
+        reroute.set_counting(self.counting);
+        reroute
+    }
+
+    /// Creates the configured reroute and installs it as the global logger.
+    ///
+    /// This is an alternative to the global instance, for a reroute configured up front.
+    /// The reroute lives until the end of the program.
+    ///
+    /// Fails if another logger was already installed (in which case the reroute is thrown away).
+    pub fn install_global(self) -> Result<&'static Reroute, SetLoggerError> {
+        let reroute = Box::leak(Box::new(self.build()));
+        log::set_logger(reroute)?;
+        Ok(reroute)
+    }
+}
+
+impl Default for RerouteBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for RerouteBuilder {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_struct("RerouteBuilder")
+            .field("initial", &self.initial.as_ref().map(|(_, i)| i.type_name))
+            .field("rest", &self.rest.as_ref().map(|(_, i)| i.type_name))
+            .field("announce", &self.announce)
+            .field("reentry", &self.reentry)
+            .field("counting", &self.counting)
+            .field("buffer_capacity", &self.buffer_capacity)
+            .field("history_capacity", &self.history_capacity)
+            .field("hooks", &self.hooks.len())
+            .finish()
+    }
+}
+
+impl Reroute {
+    /// Starts configuring a new [`Reroute`].
+    ///
+    /// See [`RerouteBuilder`].
+    pub const fn builder() -> RerouteBuilder {
+        RerouteBuilder::new()
+    }
+}
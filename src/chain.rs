@@ -0,0 +1,117 @@
+//! Falling back to the next logger.
+
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+
+use log::{Log, Metadata, Record};
+
+/// A logger giving each record to the first of its links that accepts it.
+///
+/// The links are asked in order by their [`enabled`][Log::enabled] and the record goes to the
+/// first one that says yes, only. That is, the later links are fallbacks for the records the
+/// earlier ones don't care about (unlike the [`Fanout`][crate::Fanout], which gives the record to
+/// all of them). Flushing flushes all the links.
+///
+/// ```rust
+/// use std::sync::{Arc, Mutex};
+///
+/// use log::{Level, Log, Metadata, Record};
+/// use log_reroute::{Chain, Reroute};
+///
+/// #[derive(Clone, Default)]
+/// struct Messages(Arc<Mutex<Vec<String>>>, Option<&'static str>);
+///
+/// impl Log for Messages {
+///     fn enabled(&self, metadata: &Metadata) -> bool {
+///         self.1.map_or(true, |target| metadata.target() == target)
+///     }
+///     fn log(&self, record: &Record) {
+///         self.0.lock().unwrap().push(record.args().to_string());
+///     }
+///     fn flush(&self) {}
+/// }
+///
+/// fn info(reroute: &Reroute, target: &str, msg: &str) {
+///     reroute.log(
+///         &Record::builder()
+///             .level(Level::Info)
+///             .target(target)
+///             .args(format_args!("{}", msg))
+///             .build(),
+///     );
+/// }
+///
+/// // Accepts only the "pipeline" target
+/// let structured = Messages(Default::default(), Some("pipeline"));
+/// // Accepts everything
+/// let stderr = Messages::default();
+/// let reroute = Reroute::new();
+/// reroute.reroute(Chain::new(vec![
+///     Box::new(structured.clone()),
+///     Box::new(stderr.clone()),
+/// ]));
+/// info(&reroute, "pipeline", "Both accept, goes to the first");
+/// info(&reroute, "other", "Only the second accepts");
+///
+/// assert_eq!(vec!["Both accept, goes to the first"], *structured.0.lock().unwrap());
+/// assert_eq!(vec!["Only the second accepts"], *stderr.0.lock().unwrap());
+/// ```
+#[derive(Default)]
+pub struct Chain {
+    links: Vec<Box<dyn Log>>,
+}
+
+impl Chain {
+    /// Creates a chain of the links, in the order in which they are asked.
+    pub fn new(links: Vec<Box<dyn Log>>) -> Self {
+        Self { links }
+    }
+
+    /// How many links there are.
+    pub fn len(&self) -> usize {
+        self.links.len()
+    }
+
+    /// Is the chain empty?
+    pub fn is_empty(&self) -> bool {
+        self.links.is_empty()
+    }
+
+    /// Takes the links out.
+    pub fn into_inner(self) -> Vec<Box<dyn Log>> {
+        self.links
+    }
+}
+
+impl From<Vec<Box<dyn Log>>> for Chain {
+    fn from(links: Vec<Box<dyn Log>>) -> Self {
+        Self::new(links)
+    }
+}
+
+impl Debug for Chain {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_struct("Chain")
+            .field("links", &self.links.len())
+            .finish()
+    }
+}
+
+impl Log for Chain {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.links.iter().any(|link| link.enabled(metadata))
+    }
+    fn log(&self, record: &Record) {
+        if let Some(link) = self
+            .links
+            .iter()
+            .find(|link| link.enabled(record.metadata()))
+        {
+            link.log(record);
+        }
+    }
+    fn flush(&self) {
+        for link in &self.links {
+            link.flush();
+        }
+    }
+}
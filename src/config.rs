@@ -0,0 +1,184 @@
+//! Declarative configuration, loadable by serde.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use log::{LevelFilter, Log};
+use serde::Deserialize;
+
+use crate::filter::{FilterSpec, DEFAULT_LEVEL};
+use crate::stderr::StderrLog;
+use crate::{Dummy, FileLog, FileOptions, Reroute};
+
+/// Where the records go.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum Destination {
+    /// Lines to the standard error output.
+    #[default]
+    Stderr,
+    /// Lines appended to a file.
+    File {
+        /// The path of the file, created if it doesn't exist.
+        path: PathBuf,
+    },
+    /// Nowhere, the records are thrown away.
+    Null,
+}
+
+/// The logging configuration.
+///
+/// This is meant to be a section of a bigger configuration of an application, deserialized by
+/// serde from whatever format is used. All the fields are optional.
+///
+/// ```rust
+/// use log::LevelFilter;
+/// use log_reroute::{Config, Destination, Reroute};
+///
+/// let config: Config = serde_json::from_str(r#"{
+///     "level": "info",
+///     "targets": { "hyper": "warn" },
+///     "destination": { "kind": "null" },
+///     "announce": true
+/// }"#).unwrap();
+/// assert_eq!(LevelFilter::Info, config.level);
+/// assert_eq!(Destination::Null, config.destination);
+///
+/// let reroute = Reroute::new();
+/// config.apply(&reroute).unwrap();
+///
+/// let error = serde_json::from_str::<Config>(r#"{ "destination": { "kind": "syslog" } }"#)
+///     .unwrap_err();
+/// assert!(error.to_string().contains("unknown variant `syslog`"));
+/// ```
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct Config {
+    /// The level of the targets not listed in [`targets`][Config::targets].
+    pub level: LevelFilter,
+    /// The levels of the listed targets and their submodules.
+    pub targets: BTreeMap<String, LevelFilter>,
+    /// Where the records go.
+    pub destination: Destination,
+    /// Should the reroutes be [announced][Reroute::announce_swaps]?
+    pub announce: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            level: DEFAULT_LEVEL,
+            targets: BTreeMap::new(),
+            destination: Destination::default(),
+            announce: false,
+        }
+    }
+}
+
+impl Config {
+    /// The filter spec corresponding to the levels.
+    pub fn filter_spec(&self) -> FilterSpec {
+        FilterSpec::from_levels(
+            self.level,
+            self.targets
+                .iter()
+                .map(|(target, level)| (target.clone(), *level)),
+        )
+    }
+
+    /// Sets up the reroute by the configuration.
+    ///
+    /// This [sets the filter][Reroute::set_filter] (and the [`log::max_level`]) by the levels and
+    /// replaces the slave by the destination, replacing whatever the previous call installed.
+    /// The previous slave is flushed.
+    ///
+    /// The destination is opened first and nothing changes if that fails. The change then
+    /// happens while the reroute is [paused][Reroute::pause], so no record sees the new filter
+    /// with the old destination (or the other way around).
+    ///
+    /// ```rust
+    /// use std::fs;
+    ///
+    /// use log::{Level, Log, Record};
+    /// use log_reroute::{Config, Destination, Reroute};
+    ///
+    /// let dir = tempfile::tempdir().unwrap();
+    /// let path = dir.path().join("log");
+    /// let reroute = Reroute::new();
+    ///
+    /// let mut config = Config::default();
+    /// config.destination = Destination::File { path: path.clone() };
+    /// config.apply(&reroute).unwrap();
+    /// reroute.log(
+    ///     &Record::builder()
+    ///         .level(Level::Error)
+    ///         .target("app")
+    ///         .args(format_args!("Hello"))
+    ///         .build(),
+    /// );
+    ///
+    /// // Re-applying flushes the previous file
+    /// config.destination = Destination::Null;
+    /// config.apply(&reroute).unwrap();
+    /// assert_eq!("ERROR app: Hello\n", fs::read_to_string(&path).unwrap());
+    ///
+    /// config.destination = Destination::File { path: dir.path().join("missing/log") };
+    /// assert!(config.apply(&reroute).is_err());
+    /// ```
+    pub fn apply(&self, reroute: &Reroute) -> Result<(), ApplyError> {
+        let slave: Box<dyn Log> = match &self.destination {
+            Destination::Stderr => Box::new(StderrLog),
+            Destination::File { path } => Box::new(open(path)?),
+            Destination::Null => Box::new(Dummy),
+        };
+        let _pause = reroute.pause();
+        reroute.announce_swaps(self.announce);
+        reroute.set_filter(self.filter_spec());
+        reroute.reroute_boxed(slave);
+        Ok(())
+    }
+}
+
+/// An error from [`Config::apply`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ApplyError {
+    /// The file destination couldn't be opened.
+    File {
+        /// The path of the file.
+        path: PathBuf,
+        /// The reason.
+        error: io::Error,
+    },
+}
+
+impl Display for ApplyError {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        match self {
+            ApplyError::File { path, error } => {
+                write!(fmt, "Can't open log file {}: {}", path.display(), error)
+            }
+        }
+    }
+}
+
+impl Error for ApplyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ApplyError::File { error, .. } => Some(error),
+        }
+    }
+}
+
+/// Opens the file destination.
+fn open(path: &Path) -> Result<FileLog, ApplyError> {
+    FileLog::open(path, FileOptions::new()).map_err(|error| ApplyError::File {
+        path: path.to_owned(),
+        error,
+    })
+}
@@ -0,0 +1,140 @@
+//! A dummy counting what it swallowed.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use log::{Level, Log, Metadata, Record};
+
+use crate::displace::OnDisplace;
+
+/// A logger that doesn't log, like [`Dummy`][crate::Dummy], but counts the records.
+///
+/// This tells how much was lost before logging was set up. Optionally (if created by
+/// [`with_summary`][CountingDummy::with_summary]), it sends a summary of the lost records into
+/// the slave that replaces it in a [`Reroute`][crate::Reroute] (only once, and only if it has
+/// counted anything).
+///
+/// It reports itself as [enabled][Log::enabled] for all the records, so they get to it (and are
+/// counted) also when it sits inside a [`Fanout`][crate::Fanout] or a [`Chain`][crate::Chain].
+///
+/// ```rust
+/// use std::sync::{Arc, Mutex};
+///
+/// use log::{Level, Log, Metadata, Record};
+/// use log_reroute::{CountingDummy, Fanout, Reroute};
+///
+/// #[derive(Clone, Default)]
+/// struct Messages(Arc<Mutex<Vec<String>>>);
+///
+/// impl Log for Messages {
+///     fn enabled(&self, _: &Metadata) -> bool {
+///         true
+///     }
+///     fn log(&self, record: &Record) {
+///         self.0.lock().unwrap().push(record.args().to_string());
+///     }
+///     fn flush(&self) {}
+/// }
+///
+/// fn log(reroute: &Reroute, level: Level) {
+///     reroute.log(&Record::builder().level(level).args(format_args!("Hi")).build());
+/// }
+///
+/// let reroute = Reroute::builder().count_discarded().build();
+/// log(&reroute, Level::Error);
+/// log(&reroute, Level::Debug);
+/// log(&reroute, Level::Debug);
+/// assert_eq!([1, 0, 0, 2, 0], reroute.get_downcast::<CountingDummy>().unwrap().counts());
+///
+/// let messages = Messages::default();
+/// reroute.reroute(messages.clone());
+/// assert_eq!(
+///     vec![
+///         "Discarded 3 records (1 error, 0 warn, 0 info, 2 debug, 0 trace) before logging was \
+///          configured"
+///     ],
+///     *messages.0.lock().unwrap()
+/// );
+///
+/// // Counts inside a fanout too
+/// let counting = Arc::new(CountingDummy::new());
+/// let reroute = Reroute::with(Fanout::new(vec![Box::new(Arc::clone(&counting))]));
+/// log(&reroute, Level::Warn);
+/// assert_eq!(1, counting.total());
+/// ```
+#[derive(Debug, Default)]
+pub struct CountingDummy {
+    counts: [AtomicU64; 5],
+    summary: bool,
+    summarized: AtomicBool,
+}
+
+impl CountingDummy {
+    /// Creates the counting dummy, without the summary.
+    pub const fn new() -> Self {
+        Self::create(false)
+    }
+
+    /// Creates the counting dummy that sends a summary to its replacement.
+    pub const fn with_summary() -> Self {
+        Self::create(true)
+    }
+
+    const fn create(summary: bool) -> Self {
+        Self {
+            counts: [const { AtomicU64::new(0) }; 5],
+            summary,
+            summarized: AtomicBool::new(false),
+        }
+    }
+
+    /// The numbers of swallowed records, by level (from `Error` to `Trace`).
+    pub fn counts(&self) -> [u64; 5] {
+        let mut counts = [0; 5];
+        for (count, counter) in counts.iter_mut().zip(&self.counts) {
+            *count = counter.load(Ordering::Relaxed);
+        }
+        counts
+    }
+
+    /// The number of all the swallowed records.
+    pub fn total(&self) -> u64 {
+        self.counts().iter().sum()
+    }
+}
+
+impl Log for CountingDummy {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        // Wants to see everything, to count it (eg. inside a Fanout that asks first).
+        true
+    }
+    fn log(&self, record: &Record) {
+        self.counts[record.level() as usize - 1].fetch_add(1, Ordering::Relaxed);
+    }
+    fn flush(&self) {}
+}
+
+impl OnDisplace for CountingDummy {
+    fn on_displace(&self, next: &dyn Log) {
+        if !self.summary || self.total() == 0 || self.summarized.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        let [error, warn, info, debug, trace] = self.counts();
+        next.log(
+            &Record::builder()
+                .level(Level::Info)
+                .target("log_reroute")
+                .module_path_static(Some(module_path!()))
+                .args(format_args!(
+                    "Discarded {} records ({} error, {} warn, {} info, {} debug, {} trace) before \
+                     logging was configured",
+                    self.total(),
+                    error,
+                    warn,
+                    info,
+                    debug,
+                    trace
+                ))
+                .build(),
+        );
+    }
+}
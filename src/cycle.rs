@@ -0,0 +1,148 @@
+//! Protection against reroutes forwarding (directly or indirectly) into themselves.
+
+use std::any::Any;
+use std::cell::Cell;
+use std::ptr;
+use std::sync::Arc;
+
+use crate::{Reroute, RerouteHandle, SlaveInfo};
+
+/// How deep the reroutes may be nested in each other.
+///
+/// Calls nested deeper are dropped, protecting against cycles the checks on installation can't
+/// see.
+pub(crate) const MAX_DEPTH: usize = 16;
+
+thread_local! {
+    /// How many calls into the reroutes are on the stack of this thread.
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+    /// The addresses of the reroutes on the stack, the first `DEPTH` are valid.
+    static ACTIVE: [Cell<usize>; MAX_DEPTH] = const { [const { Cell::new(0) }; MAX_DEPTH] };
+}
+
+/// What a [`Reroute`] does with a record logged from inside its own slave.
+///
+/// Such a record comes from a slave logging (eg. its own errors) through the same reroute while
+/// handling a record (typically through the global logger). Passing it to the slave again is
+/// likely to recurse, so by default it goes to stderr instead. See
+/// [`Reroute::set_reentry`][crate::Reroute::set_reentry].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Reentry {
+    /// The record is written to stderr.
+    #[default]
+    Stderr,
+    /// The record is dropped.
+    Drop,
+    /// The record goes to the slave as any other.
+    ///
+    /// This is for slaves that nest on purpose and know when to stop; only the limit of nesting
+    /// of the reroutes protects against infinite recursion.
+    Allow,
+}
+
+impl Reentry {
+    pub(crate) const fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Reentry::Drop,
+            2 => Reentry::Allow,
+            _ => Reentry::Stderr,
+        }
+    }
+
+    pub(crate) const fn to_u8(self) -> u8 {
+        match self {
+            Reentry::Stderr => 0,
+            Reentry::Drop => 1,
+            Reentry::Allow => 2,
+        }
+    }
+}
+
+/// Leaves one level of nesting on drop (even by a panic).
+struct Leave;
+
+impl Drop for Leave {
+    fn drop(&mut self) {
+        let _ = DEPTH.try_with(|d| d.set(d.get().saturating_sub(1)));
+    }
+}
+
+/// Runs the closure one level deeper inside the reroute, unless it's too deep already.
+#[inline]
+pub(crate) fn nest<R, F: FnOnce() -> R>(reroute: &Reroute, too_deep: R, f: F) -> R {
+    let entered = DEPTH.try_with(|d| {
+        let depth = d.get();
+        if depth < MAX_DEPTH {
+            ACTIVE.with(|active| active[depth].set(address(reroute)));
+            d.set(depth + 1);
+            true
+        } else {
+            false
+        }
+    });
+    match entered {
+        Ok(true) => {
+            let _leave = Leave;
+            f()
+        }
+        Ok(false) => too_deep,
+        // During thread destruction, don't protect anything.
+        Err(_) => f(),
+    }
+}
+
+/// Is this thread inside a call to a reroute (therefore, its slave)?
+pub(crate) fn inside() -> bool {
+    DEPTH.try_with(|d| d.get() > 0).unwrap_or(false)
+}
+
+fn address(reroute: &Reroute) -> usize {
+    reroute as *const Reroute as usize
+}
+
+/// Is this thread inside a call to this particular reroute?
+#[inline]
+pub(crate) fn inside_of(reroute: &Reroute) -> bool {
+    let depth = match DEPTH.try_with(Cell::get) {
+        Ok(0) | Err(_) => return false,
+        Ok(depth) => depth,
+    };
+    let address = address(reroute);
+    ACTIVE
+        .try_with(|active| active[..depth].iter().any(|a| a.get() == address))
+        .unwrap_or(false)
+}
+
+/// Finds the reroute the slave forwards to, if it's one of ours.
+fn forwards_to(any: &(dyn Any + Send + Sync)) -> Option<&Reroute> {
+    if let Some(reroute) = any.downcast_ref::<Reroute>() {
+        Some(reroute)
+    } else if let Some(reroute) = any.downcast_ref::<Arc<Reroute>>() {
+        Some(reroute)
+    } else if let Some(handle) = any.downcast_ref::<RerouteHandle>() {
+        Some(handle.target())
+    } else {
+        None
+    }
+}
+
+/// Would installing the slave into the target make it forward into itself?
+///
+/// Only the slaves of known types are followed.
+pub(crate) fn leads_to(target: &Reroute, info: &SlaveInfo) -> bool {
+    let mut any = info.any.clone();
+    for _ in 0..MAX_DEPTH {
+        let next = match any.as_deref().and_then(forwards_to) {
+            Some(next) => next,
+            None => return false,
+        };
+        if ptr::eq(next, target) {
+            return true;
+        }
+        let next_any = next.lock_state().info.any.clone();
+        any = next_any;
+    }
+    // Too deep to tell, leave it to the runtime protection.
+    false
+}
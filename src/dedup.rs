@@ -0,0 +1,229 @@
+//! Collapsing the repeated records.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::{Debug, Formatter, Result as FmtResult, Write};
+use std::hash::Hasher;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use log::{Level, Log, Metadata, Record};
+
+/// Hashes the formatted message without storing it.
+pub(crate) struct HashWriter<'a>(pub(crate) &'a mut DefaultHasher);
+
+impl Write for HashWriter<'_> {
+    fn write_str(&mut self, s: &str) -> FmtResult {
+        self.0.write(s.as_bytes());
+        Ok(())
+    }
+}
+
+#[derive(Eq, PartialEq)]
+struct Key {
+    level: Level,
+    target: String,
+    message: u64,
+}
+
+fn message_hash(record: &Record) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    let _ = HashWriter(&mut hasher).write_fmt(*record.args());
+    hasher.finish()
+}
+
+impl Key {
+    fn new(record: &Record, message: u64) -> Self {
+        Self {
+            level: record.level(),
+            target: record.target().to_owned(),
+            message,
+        }
+    }
+
+    fn matches(&self, record: &Record, message: u64) -> bool {
+        self.level == record.level() && self.target == record.target() && self.message == message
+    }
+}
+
+struct Last {
+    key: Key,
+    repeated: u64,
+    since: Instant,
+}
+
+/// A summary of the repeats to send.
+struct Repeated {
+    level: Level,
+    target: String,
+    repeated: u64,
+}
+
+impl Repeated {
+    fn take(last: &mut Last, now: Instant) -> Option<Self> {
+        if last.repeated == 0 {
+            return None;
+        }
+        let repeated = Repeated {
+            level: last.key.level,
+            target: last.key.target.clone(),
+            repeated: last.repeated,
+        };
+        last.repeated = 0;
+        last.since = now;
+        Some(repeated)
+    }
+
+    fn emit_to(&self, log: &dyn Log) {
+        log.log(
+            &Record::builder()
+                .level(self.level)
+                .target(&self.target)
+                .args(format_args!(
+                    "last message repeated {} times",
+                    self.repeated
+                ))
+                .build(),
+        );
+    }
+}
+
+/// A wrapper collapsing the identical consecutive records.
+///
+/// A record with the same level, target and message as the previous one is suppressed and
+/// counted. When a different record comes (or on [flush][Log::flush]), a `last message repeated
+/// N times` record is sent first. With [`with_max_hold`][Dedup::with_max_hold], the count is also
+/// sent when a repeat comes after holding it for so long.
+///
+/// The messages are compared by their hash, so a long message is not stored. There's a single
+/// previous record shared by all the threads; the records of different threads interleaving
+/// therefore are not repeats of each other.
+///
+/// ```rust
+/// use std::sync::{Arc, Mutex};
+///
+/// use log::{Level, Log, Metadata, Record};
+/// use log_reroute::Dedup;
+///
+/// #[derive(Clone, Default)]
+/// struct Messages(Arc<Mutex<Vec<String>>>);
+///
+/// impl Log for Messages {
+///     fn enabled(&self, _: &Metadata) -> bool {
+///         true
+///     }
+///     fn log(&self, record: &Record) {
+///         self.0.lock().unwrap().push(record.args().to_string());
+///     }
+///     fn flush(&self) {}
+/// }
+///
+/// let messages = Messages::default();
+/// let dedup = Dedup::new(messages.clone());
+/// for msg in ["Link down", "Link down", "Link down", "Link up", "Link up"] {
+///     dedup.log(
+///         &Record::builder()
+///             .level(Level::Warn)
+///             .args(format_args!("{}", msg))
+///             .build(),
+///     );
+/// }
+/// dedup.flush();
+/// assert_eq!(
+///     vec![
+///         "Link down",
+///         "last message repeated 2 times",
+///         "Link up",
+///         "last message repeated 1 times",
+///     ],
+///     *messages.0.lock().unwrap(),
+/// );
+/// ```
+pub struct Dedup<L> {
+    inner: L,
+    max_hold: Option<Duration>,
+    last: Mutex<Option<Last>>,
+}
+
+impl<L: Log> Dedup<L> {
+    /// Wraps the logger.
+    pub fn new(inner: L) -> Self {
+        Self {
+            inner,
+            max_hold: None,
+            last: Mutex::new(None),
+        }
+    }
+
+    /// Sends the count of the repeats when they are being held for longer than this.
+    ///
+    /// This is checked when another repeat comes.
+    pub fn with_max_hold(mut self, max_hold: Duration) -> Self {
+        self.max_hold = Some(max_hold);
+        self
+    }
+
+    /// The wrapped logger.
+    pub fn inner(&self) -> &L {
+        &self.inner
+    }
+}
+
+impl<L> Debug for Dedup<L> {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_struct("Dedup")
+            .field("max_hold", &self.max_hold)
+            .finish()
+    }
+}
+
+impl<L: Log> Log for Dedup<L> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+    fn log(&self, record: &Record) {
+        // Hashing the message runs its Display impls, which may log through us again.
+        let message = message_hash(record);
+        let now = Instant::now();
+        let (repeated, pass) = {
+            let mut last = self.last.lock().unwrap_or_else(PoisonError::into_inner);
+            match &mut *last {
+                Some(last) if last.key.matches(record, message) => {
+                    last.repeated += 1;
+                    let held = now.duration_since(last.since);
+                    let repeated = match self.max_hold {
+                        Some(max_hold) if held >= max_hold => Repeated::take(last, now),
+                        _ => None,
+                    };
+                    (repeated, false)
+                }
+                last => {
+                    let repeated = last.as_mut().and_then(|last| Repeated::take(last, now));
+                    *last = Some(Last {
+                        key: Key::new(record, message),
+                        repeated: 0,
+                        since: now,
+                    });
+                    (repeated, true)
+                }
+            }
+        };
+        if let Some(repeated) = repeated {
+            repeated.emit_to(&self.inner);
+        }
+        if pass {
+            self.inner.log(record);
+        }
+    }
+    fn flush(&self) {
+        let repeated = self
+            .last
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_mut()
+            .and_then(|last| Repeated::take(last, Instant::now()));
+        if let Some(repeated) = repeated {
+            repeated.emit_to(&self.inner);
+        }
+        self.inner.flush();
+    }
+}
@@ -0,0 +1,25 @@
+//! Giving the crate-provided slaves a last word when they are replaced.
+
+use std::any::Any;
+use std::sync::Arc;
+
+use log::Log;
+
+use crate::CountingDummy;
+
+/// A slave that wants to know when it gets replaced.
+pub(crate) trait OnDisplace: Send + Sync {
+    /// Called after the slave was replaced, with the state of the reroute unlocked.
+    fn on_displace(&self, next: &dyn Log);
+}
+
+/// A displaced slave's call, waiting for the state to be unlocked.
+pub(crate) type Handoff = Box<dyn FnOnce() + Send>;
+
+/// Recognizes the slaves implementing the [`OnDisplace`].
+pub(crate) fn find(any: &Arc<dyn Any + Send + Sync>) -> Option<Arc<dyn OnDisplace>> {
+    if let Ok(counting) = Arc::clone(any).downcast::<CountingDummy>() {
+        return Some(counting);
+    }
+    None
+}
@@ -0,0 +1,177 @@
+//! Disposal of old slaves in a background thread.
+//!
+//! See [`FlushOld::Background`][crate::FlushOld::Background].
+
+use std::collections::VecDeque;
+use std::io;
+use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+
+use crate::worker::Worker;
+use crate::Slave;
+
+/// The worker doing the disposals.
+struct Disposer {
+    worker: Worker,
+    /// Set when it's asked to stop (it still finishes the pending slaves).
+    stopping: Arc<AtomicBool>,
+}
+
+impl Disposer {
+    fn is_alive(&self) -> bool {
+        self.worker.is_healthy() && !self.stopping.load(Ordering::Acquire)
+    }
+}
+
+struct Queue {
+    pending: Vec<Slave>,
+    disposer: Option<Disposer>,
+}
+
+static QUEUE: Mutex<Queue> = Mutex::new(Queue {
+    pending: Vec::new(),
+    disposer: None,
+});
+
+static WAKEUP: Condvar = Condvar::new();
+
+fn lock() -> MutexGuard<'static, Queue> {
+    // Nothing panics while holding the lock (the flushing happens outside of it).
+    QUEUE.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn spawn() -> io::Result<Disposer> {
+    let stopping = Arc::new(AtomicBool::new(false));
+    let stop = Arc::clone(&stopping);
+    let check = Arc::clone(&stopping);
+    // All the slaves accumulated since the last round are taken in one go. The batch lives in
+    // the body, so the rest of it survives if one of the slaves panics in its flush.
+    let mut batch = VecDeque::new();
+    let body = move || {
+        if batch.is_empty() {
+            let mut queue = lock();
+            loop {
+                if !queue.pending.is_empty() {
+                    batch.extend(queue.pending.drain(..));
+                    break;
+                }
+                if check.load(Ordering::Acquire) {
+                    return false;
+                }
+                queue = WAKEUP.wait(queue).unwrap_or_else(PoisonError::into_inner);
+            }
+        }
+        if let Some(slave) = batch.pop_front() {
+            slave.flush();
+        }
+        true
+    };
+    let worker = Worker::spawn(body, move || {
+        stop.store(true, Ordering::Release);
+        // Under the lock, so the worker doesn't miss the wakeup between checking and waiting.
+        let _queue = lock();
+        WAKEUP.notify_all();
+    })?;
+    Ok(Disposer { worker, stopping })
+}
+
+fn flush_here(pending: Vec<Slave>) {
+    for slave in pending {
+        slave.flush();
+    }
+}
+
+/// Hands the slave over to the background worker, spawning it if needed.
+pub(crate) fn background(slave: Slave) {
+    let mut queue = lock();
+    queue.pending.push(slave);
+    // A worker that gave up after panicking too much (or is being stopped) gets replaced.
+    if !queue.disposer.as_ref().is_some_and(Disposer::is_alive) {
+        match spawn() {
+            Ok(disposer) => queue.disposer = Some(disposer),
+            Err(_) => {
+                // Can't get a thread, so at least do it here.
+                let pending = mem::take(&mut queue.pending);
+                drop(queue);
+                return flush_here(pending);
+            }
+        }
+    }
+    WAKEUP.notify_all();
+}
+
+/// Waits for all the background disposals to finish.
+///
+/// Slaves replaced with [`FlushOld::Background`][crate::FlushOld::Background] are flushed and
+/// dropped in a background [`Worker`]. If the process exits soon after such reroute, the data
+/// still buffered in the old slave could be lost. This function finishes all the pending
+/// disposals and stops the worker, so it should be called before exiting ([`shutdown`] does that
+/// too, together with the other workers).
+///
+/// It waits at most for the [configured timeout][crate::set_shutdown_timeout] and returns if the
+/// disposals finished in time. It is possible to use the background disposal again after this
+/// call, a new worker is started as needed.
+///
+/// A slave panicking in its flush doesn't stop the disposal of the others:
+///
+/// ```rust
+/// use std::sync::atomic::{AtomicBool, Ordering};
+/// use std::sync::Arc;
+///
+/// use log::{Log, Metadata, Record};
+/// use log_reroute::{Dummy, FlushOld, Reroute};
+///
+/// struct Flushed(Arc<AtomicBool>);
+///
+/// impl Log for Flushed {
+///     fn enabled(&self, _: &Metadata) -> bool {
+///         true
+///     }
+///     fn log(&self, _: &Record) {}
+///     fn flush(&self) {
+///         self.0.store(true, Ordering::Relaxed);
+///     }
+/// }
+///
+/// struct Broken;
+///
+/// impl Log for Broken {
+///     fn enabled(&self, _: &Metadata) -> bool {
+///         true
+///     }
+///     fn log(&self, _: &Record) {}
+///     fn flush(&self) {
+///         panic!("Can't flush");
+///     }
+/// }
+///
+/// let flushed = Arc::new(AtomicBool::new(false));
+/// let reroute = Reroute::new();
+/// reroute.reroute(Broken);
+/// reroute.reroute_boxed_with(Box::new(Flushed(Arc::clone(&flushed))), FlushOld::Background);
+/// reroute.reroute_boxed_with(Box::new(Dummy), FlushOld::Background);
+/// // At the end of main
+/// assert!(log_reroute::join_disposal());
+/// assert!(flushed.load(Ordering::Relaxed));
+/// ```
+///
+/// [`shutdown`]: crate::shutdown
+pub fn join_disposal() -> bool {
+    // Taking it out, so anything disposed of from now on gets a new worker.
+    let disposer = lock().disposer.take();
+    let finished = disposer.is_none_or(|disposer| disposer.worker.stop());
+    if finished {
+        // A worker that gave up might have left something behind.
+        let leftover = {
+            let mut queue = lock();
+            if queue.disposer.is_none() {
+                mem::take(&mut queue.pending)
+            } else {
+                Vec::new()
+            }
+        };
+        flush_here(leftover);
+    }
+    finished
+}
@@ -0,0 +1,172 @@
+//! A fanout with children changing at runtime.
+
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+use log::{Log, Metadata, Record};
+
+use crate::{fanout, wait_unique, Slave};
+
+/// Identifies a child of a [`DynFanout`].
+///
+/// Returned by [`DynFanout::add`], passed to [`DynFanout::remove`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct SinkId(u64);
+
+type Children = Vec<(SinkId, Slave)>;
+
+struct Inner {
+    children: ArcSwap<Children>,
+    next_id: AtomicU64,
+}
+
+/// A [`Fanout`][crate::Fanout] whose children can be added and removed while it is in use.
+///
+/// This is a handle; the clones share the same children. So one clone can be installed into a
+/// [`Reroute`][crate::Reroute] while another is kept by the code attaching and detaching the
+/// children (eg. a temporary debug tap), without rerouting each time.
+///
+/// Logging doesn't lock, the children are kept in an immutable list that is replaced on each
+/// change.
+///
+/// ```rust
+/// use std::sync::{Arc, Mutex};
+///
+/// use log::{Level, Log, Metadata, Record};
+/// use log_reroute::{DynFanout, Reroute};
+///
+/// #[derive(Clone, Default)]
+/// struct Messages(Arc<Mutex<Vec<String>>>);
+///
+/// impl Log for Messages {
+///     fn enabled(&self, _: &Metadata) -> bool {
+///         true
+///     }
+///     fn log(&self, record: &Record) {
+///         self.0.lock().unwrap().push(record.args().to_string());
+///     }
+///     fn flush(&self) {}
+/// }
+///
+/// fn info(reroute: &Reroute, msg: &str) {
+///     reroute.log(
+///         &Record::builder()
+///             .level(Level::Info)
+///             .args(format_args!("{}", msg))
+///             .build(),
+///     );
+/// }
+///
+/// let fanout = DynFanout::new();
+/// let reroute = Reroute::new();
+/// reroute.reroute(fanout.clone());
+///
+/// let main = Messages::default();
+/// fanout.add(Box::new(main.clone()));
+/// info(&reroute, "Before");
+///
+/// let tap = Messages::default();
+/// let id = fanout.add(Box::new(tap.clone()));
+/// info(&reroute, "Tapped");
+/// assert!(fanout.remove(id).is_some());
+/// assert!(fanout.remove(id).is_none());
+/// info(&reroute, "After");
+///
+/// assert_eq!(vec!["Before", "Tapped", "After"], *main.0.lock().unwrap());
+/// assert_eq!(vec!["Tapped"], *tap.0.lock().unwrap());
+/// ```
+#[derive(Clone)]
+pub struct DynFanout {
+    inner: Arc<Inner>,
+}
+
+impl DynFanout {
+    /// Creates a fanout without any children.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                children: ArcSwap::from_pointee(Vec::new()),
+                next_id: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Adds another child.
+    pub fn add(&self, child: Box<dyn Log>) -> SinkId {
+        let id = SinkId(self.inner.next_id.fetch_add(1, Ordering::Relaxed));
+        let child: Slave = Arc::new(child);
+        self.inner.children.rcu(|children| {
+            let mut children = Children::clone(children);
+            children.push((id, Arc::clone(&child)));
+            children
+        });
+        id
+    }
+
+    /// Removes a child, flushes it and hands it back.
+    ///
+    /// Returns `None` if there's no such child (eg. it was already removed).
+    ///
+    /// This waits for the threads currently logging into the child to finish, therefore it must
+    /// not be called from within a child of this fanout.
+    pub fn remove(&self, id: SinkId) -> Option<Box<dyn Log>> {
+        let previous = self.inner.children.rcu(|children| {
+            children
+                .iter()
+                .filter(|(child_id, _)| *child_id != id)
+                .cloned()
+                .collect::<Children>()
+        });
+        let removed = previous
+            .iter()
+            .find(|(child_id, _)| *child_id == id)
+            .map(|(_, child)| Arc::clone(child));
+        drop(previous);
+        // Without a deadline, this doesn't fail.
+        let removed = wait_unique(removed?, None).ok()?;
+        removed.flush();
+        Some(removed)
+    }
+
+    /// How many children there are.
+    pub fn len(&self) -> usize {
+        self.inner.children.load().len()
+    }
+
+    /// Is the fanout empty?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for DynFanout {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for DynFanout {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_struct("DynFanout")
+            .field("children", &self.len())
+            .finish()
+    }
+}
+
+fn children(children: &Children) -> impl Iterator<Item = &dyn Log> {
+    children.iter().map(|(_, child)| &***child)
+}
+
+impl Log for DynFanout {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        fanout::enabled(children(&self.inner.children.load()), metadata)
+    }
+    fn log(&self, record: &Record) {
+        fanout::log(children(&self.inner.children.load()), record)
+    }
+    fn flush(&self) {
+        fanout::flush(children(&self.inner.children.load()))
+    }
+}
@@ -0,0 +1,153 @@
+//! Adding fixed key-values to the records.
+
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+
+use log::kv::{Error, Key, Source, ToValue, VisitSource};
+use log::{Log, Metadata, Record};
+
+use crate::kv::KeyValues;
+use crate::transform::rebuild;
+
+/// The added pairs whose keys the record doesn't have already.
+struct Missing<'a> {
+    added: &'a KeyValues,
+    original: &'a dyn Source,
+}
+
+impl Source for Missing<'_> {
+    fn visit<'kvs>(&'kvs self, visitor: &mut dyn VisitSource<'kvs>) -> Result<(), Error> {
+        for (key, value) in self.added.iter() {
+            let key = Key::from_str(key);
+            if self.original.get(key.clone()).is_none() {
+                visitor.visit_pair(key, value.to_value())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A wrapper adding fixed key-values to each record.
+///
+/// The pairs (eg. the name and version of the application) are set when the wrapper is built.
+/// They are appended after the record's own key-values; if the record already has the key, its
+/// own value wins and the added pair is left out.
+///
+/// For the loggers not looking at the key-values (like the plain text ones), the pairs can be
+/// put [into the message][Enrich::in_message] as `key=value` text instead.
+///
+/// Requires the `kv` feature.
+///
+/// ```rust
+/// use std::sync::{Arc, Mutex};
+///
+/// use log::kv::Key;
+/// use log::LevelFilter;
+/// use log_reroute::{Enrich, FnLog};
+///
+/// let seen = Arc::new(Mutex::new(Vec::new()));
+/// let collect = Arc::clone(&seen);
+/// let enrich = Enrich::new(FnLog::new(move |record| {
+///     let kvs = record.key_values();
+///     let get = |key| kvs.get(Key::from_str(key)).map(|v| v.to_string());
+///     let line = format!("{:?} {:?} {}", get("app"), get("version"), record.args());
+///     collect.lock().unwrap().push(line);
+/// }))
+/// .with("app", "billing")
+/// .with("version", "1.4.2");
+///
+/// log::set_max_level(LevelFilter::Info);
+/// log::info!(logger: &enrich, "Started");
+/// log::info!(logger: &enrich, app = "override"; "Own value");
+///
+/// let text = Enrich::new(FnLog::new(|record| {
+///     assert_eq!("app=billing Started", record.args().to_string());
+/// }))
+/// .with("app", "billing")
+/// .in_message();
+/// log::info!(logger: &text, "Started");
+///
+/// assert_eq!(
+///     vec![
+///         r#"Some("billing") Some("1.4.2") Started"#,
+///         r#"Some("override") Some("1.4.2") Own value"#,
+///     ],
+///     *seen.lock().unwrap(),
+/// );
+/// ```
+pub struct Enrich<L> {
+    inner: L,
+    pairs: KeyValues,
+    in_message: bool,
+    text: String,
+}
+
+impl<L: Log> Enrich<L> {
+    /// Wraps the logger, without any pairs yet.
+    pub fn new(inner: L) -> Self {
+        Self {
+            inner,
+            pairs: KeyValues::default(),
+            in_message: false,
+            text: String::new(),
+        }
+    }
+
+    /// Adds a pair.
+    pub fn with<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: ToValue,
+    {
+        let key = key.into();
+        let value = value.to_value();
+        self.text.push_str(&format!("{}={} ", key, value));
+        self.pairs.push(key, &value);
+        self
+    }
+
+    /// Puts the pairs into the message (as `key=value` text before it) instead of the
+    /// key-values.
+    pub fn in_message(mut self) -> Self {
+        self.in_message = true;
+        self
+    }
+
+    /// The wrapped logger.
+    pub fn inner(&self) -> &L {
+        &self.inner
+    }
+}
+
+impl<L> Debug for Enrich<L> {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_struct("Enrich")
+            .field("pairs", &self.text.trim_end())
+            .field("in_message", &self.in_message)
+            .finish()
+    }
+}
+
+impl<L: Log> Log for Enrich<L> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+    fn log(&self, record: &Record) {
+        if self.in_message {
+            return self.inner.log(
+                &rebuild(record)
+                    .args(format_args!("{}{}", self.text, record.args()))
+                    .build(),
+            );
+        }
+        let missing = Missing {
+            added: &self.pairs,
+            original: record.key_values(),
+        };
+        let key_values: [&dyn Source; 2] = [record.key_values(), &missing];
+        self.inner
+            .log(&rebuild(record).key_values(&key_values).build());
+    }
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
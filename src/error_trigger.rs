@@ -0,0 +1,184 @@
+//! Noticing that an error was logged.
+
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use log::{Level, Log, Metadata, Record};
+
+type Callback = Box<dyn Fn(&Record) + Send + Sync>;
+
+#[derive(Default)]
+struct Seen {
+    seen: AtomicBool,
+    count: AtomicU64,
+}
+
+/// Shared access to the state of an [`ErrorTrigger`].
+///
+/// Returned by [`ErrorTrigger::handle`], so the state can be checked after the trigger is
+/// installed as (part of) the logger.
+#[derive(Clone)]
+pub struct ErrorTriggerHandle(Arc<Seen>);
+
+impl ErrorTriggerHandle {
+    /// Was an error logged since the creation or the last [`reset`][ErrorTriggerHandle::reset]?
+    pub fn error_seen(&self) -> bool {
+        self.0.seen.load(Ordering::Acquire)
+    }
+
+    /// How many errors were logged since the creation or the last reset.
+    pub fn error_count(&self) -> u64 {
+        self.0.count.load(Ordering::Relaxed)
+    }
+
+    /// Forgets the errors, so the callback is called again on the next one.
+    pub fn reset(&self) {
+        self.0.count.store(0, Ordering::Relaxed);
+        self.0.seen.store(false, Ordering::Release);
+    }
+}
+
+impl Debug for ErrorTriggerHandle {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_struct("ErrorTriggerHandle")
+            .field("error_seen", &self.error_seen())
+            .field("error_count", &self.error_count())
+            .finish()
+    }
+}
+
+/// A wrapper noticing the errors going through.
+///
+/// Each record at or above the level (by default [`Error`][Level::Error]) is counted. The first
+/// one (since the last [`reset`][ErrorTriggerHandle::reset]) also calls the callback set by
+/// [`on_error`][ErrorTrigger::on_error], after it is passed to the inner logger. The callback is
+/// called without holding any locks, other records may be logged meanwhile (the later errors don't
+/// call it again, even concurrently). The state can be checked at any time through the
+/// [`handle`][ErrorTrigger::handle].
+///
+/// The records are counted even if the inner logger isn't [enabled][Log::enabled] for them.
+///
+/// ```rust
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::sync::Arc;
+///
+/// use log::{Level, Log, Record};
+/// use log_reroute::{Dummy, ErrorTrigger, Reroute};
+///
+/// let calls = Arc::new(AtomicUsize::new(0));
+/// let counted = Arc::clone(&calls);
+/// let trigger = ErrorTrigger::new(Dummy).on_error(move |_| {
+///     counted.fetch_add(1, Ordering::Relaxed);
+/// });
+/// let handle = trigger.handle();
+/// let reroute = Reroute::with(trigger);
+///
+/// reroute.log(&Record::builder().level(Level::Warn).build());
+/// assert!(!handle.error_seen());
+/// reroute.log(&Record::builder().level(Level::Error).build());
+/// reroute.log(&Record::builder().level(Level::Error).build());
+/// assert!(handle.error_seen());
+/// assert_eq!(2, handle.error_count());
+/// assert_eq!(1, calls.load(Ordering::Relaxed));
+///
+/// handle.reset();
+/// assert!(!handle.error_seen());
+/// reroute.log(&Record::builder().level(Level::Error).build());
+/// assert_eq!(2, calls.load(Ordering::Relaxed));
+///
+/// // At the end of main
+/// let code = if handle.error_seen() { 1 } else { 0 };
+/// # assert_eq!(1, code);
+/// ```
+pub struct ErrorTrigger<L> {
+    inner: L,
+    level: Level,
+    callback: Option<Callback>,
+    seen: Arc<Seen>,
+}
+
+impl<L: Log> ErrorTrigger<L> {
+    /// Wraps the logger, noticing the errors.
+    pub fn new(inner: L) -> Self {
+        Self {
+            inner,
+            level: Level::Error,
+            callback: None,
+            seen: Arc::default(),
+        }
+    }
+
+    /// Notices the records of this level and more severe.
+    pub fn at(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Sets the callback called with the first error.
+    pub fn on_error<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&Record) + Send + Sync + 'static,
+    {
+        self.callback = Some(Box::new(callback));
+        self
+    }
+
+    /// A handle to check the state, even after the trigger is installed.
+    pub fn handle(&self) -> ErrorTriggerHandle {
+        ErrorTriggerHandle(Arc::clone(&self.seen))
+    }
+
+    /// Was an error logged since the creation or the last reset?
+    pub fn error_seen(&self) -> bool {
+        self.handle().error_seen()
+    }
+
+    /// How many errors were logged since the creation or the last reset.
+    pub fn error_count(&self) -> u64 {
+        self.handle().error_count()
+    }
+
+    /// Forgets the errors, so the callback is called again on the next one.
+    pub fn reset(&self) {
+        self.handle().reset()
+    }
+
+    /// The wrapped logger.
+    pub fn inner(&self) -> &L {
+        &self.inner
+    }
+}
+
+impl<L> Debug for ErrorTrigger<L> {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_struct("ErrorTrigger")
+            .field("level", &self.level)
+            .field("error_seen", &self.seen.seen.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+impl<L: Log> Log for ErrorTrigger<L> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        // Must see the errors even if the inner logger doesn't want them.
+        metadata.level() <= self.level || self.inner.enabled(metadata)
+    }
+    fn log(&self, record: &Record) {
+        if self.inner.enabled(record.metadata()) {
+            self.inner.log(record);
+        }
+        if record.level() > self.level {
+            return;
+        }
+        self.seen.count.fetch_add(1, Ordering::Relaxed);
+        if !self.seen.seen.swap(true, Ordering::AcqRel) {
+            if let Some(callback) = &self.callback {
+                callback(record);
+            }
+        }
+    }
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
@@ -0,0 +1,100 @@
+//! Copying the severe records to another logger.
+
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::iter;
+
+use log::{Level, Log, Metadata, Record};
+
+use crate::fanout;
+
+/// A logger sending all the records to the primary logger and a copy of the severe ones to the
+/// secondary.
+///
+/// The records at or above the level go to both loggers (each gets them only if it's
+/// [enabled][Log::enabled] for them), the others only to the primary one. Unlike with the
+/// [`LevelRouter`][crate::LevelRouter], which sends each record to one place, the severe records
+/// are duplicated; eg. the full log goes to a file and the warnings and errors also to a small
+/// incident log. A panic of one of the loggers doesn't prevent the other from getting the record.
+///
+/// ```rust
+/// use std::sync::{Arc, Mutex};
+///
+/// use log::{Level, Log, Record};
+/// use log_reroute::{Escalate, FnLog};
+///
+/// fn collect(into: &Arc<Mutex<Vec<String>>>) -> impl Log {
+///     let into = Arc::clone(into);
+///     FnLog::new(move |record: &Record| into.lock().unwrap().push(record.args().to_string()))
+/// }
+///
+/// let full = Arc::new(Mutex::new(Vec::new()));
+/// let incidents = Arc::new(Mutex::new(Vec::new()));
+/// let escalate = Escalate::new(collect(&full), collect(&incidents), Level::Warn);
+///
+/// for (level, msg) in [
+///     (Level::Info, "Started"),
+///     (Level::Warn, "Disk almost full"),
+///     (Level::Error, "Disk full"),
+/// ] {
+///     escalate.log(
+///         &Record::builder()
+///             .level(level)
+///             .args(format_args!("{}", msg))
+///             .build(),
+///     );
+/// }
+/// assert_eq!(vec!["Started", "Disk almost full", "Disk full"], *full.lock().unwrap());
+/// assert_eq!(vec!["Disk almost full", "Disk full"], *incidents.lock().unwrap());
+/// ```
+pub struct Escalate<P, S> {
+    primary: P,
+    secondary: S,
+    level: Level,
+}
+
+impl<P: Log, S: Log> Escalate<P, S> {
+    /// Creates the logger, copying the records at or above the level to the secondary logger.
+    pub fn new(primary: P, secondary: S, level: Level) -> Self {
+        Self {
+            primary,
+            secondary,
+            level,
+        }
+    }
+
+    /// The primary logger.
+    pub fn primary(&self) -> &P {
+        &self.primary
+    }
+
+    /// The secondary logger.
+    pub fn secondary(&self) -> &S {
+        &self.secondary
+    }
+
+    /// The loggers for a record of the level.
+    fn targets(&self, level: Level) -> impl Iterator<Item = &dyn Log> {
+        let secondary: &dyn Log = &self.secondary;
+        iter::once(&self.primary as &dyn Log).chain((level <= self.level).then_some(secondary))
+    }
+}
+
+impl<P, S> Debug for Escalate<P, S> {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_struct("Escalate")
+            .field("level", &self.level)
+            .finish()
+    }
+}
+
+impl<P: Log, S: Log> Log for Escalate<P, S> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        fanout::enabled(self.targets(metadata.level()), metadata)
+    }
+    fn log(&self, record: &Record) {
+        fanout::log(self.targets(record.level()), record)
+    }
+    fn flush(&self) {
+        fanout::flush([&self.primary as &dyn Log, &self.secondary])
+    }
+}
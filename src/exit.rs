@@ -0,0 +1,87 @@
+//! Flushing the global reroute when the main thread ends.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+use crate::{join_disposal, REROUTE};
+
+/// Flushes on drop.
+struct FlushOnExit;
+
+impl Drop for FlushOnExit {
+    fn drop(&mut self) {
+        // Nothing here spawns threads, the disposal worker is only waited for (at most for the
+        // shutdown timeout).
+        log::Log::flush(&REROUTE);
+        join_disposal();
+    }
+}
+
+thread_local! {
+    static FLUSH: FlushOnExit = const { FlushOnExit };
+}
+
+/// Tries to flush the global [`Reroute`][crate::Reroute] instance when the main thread ends.
+///
+/// Statics are not dropped at the end of the program, so a slave buffering the records would lose
+/// the last ones if nothing flushes it. This is a best-effort guard against that: it flushes the
+/// slave (and finishes the old slaves being [disposed][crate::FlushOld::Background] of) when the
+/// destructors of the main thread's thread-local variables run.
+///
+/// This is not an exit hook. The crate doesn't use `unsafe` code and therefore can't register one
+/// with `atexit`. Whether the thread-local destructors of the main thread run when `main` returns
+/// depends on the platform (they do on Linux with glibc, but it is not guaranteed everywhere).
+/// When the process ends any other way, like by [`std::process::exit`], [`std::process::abort`],
+/// a signal or a panic with `panic = "abort"`, the flush can't be relied on. Where it matters,
+/// flush explicitly before exiting.
+///
+/// The old slaves still being disposed of are waited for at most the [shutdown
+/// timeout][crate::set_shutdown_timeout]. The flush of the current slave is not limited, it is up
+/// to the slave not to block forever.
+///
+/// It must be called from the main thread (the one named `main`), a destructor of another thread
+/// would run when that thread ends. Called from elsewhere, it does nothing and returns `false`.
+/// Calling this multiple times is fine, the flush is registered only once.
+///
+/// ```rust
+/// use std::env;
+/// use std::io::{self, Write};
+/// use std::process::Command;
+/// use std::thread;
+///
+/// use log::Record;
+/// use log_reroute::FnLog;
+///
+/// if env::var_os("FLUSH_ON_MAIN_THREAD_EXIT_CHILD").is_some() {
+///     // Runs in a copy of the process, started below
+///     log_reroute::init().unwrap();
+///     log_reroute::reroute(FnLog::new(|_: &Record| ()).with_flush(|| {
+///         let _ = io::stdout().write_all(b"Flushed\n");
+///     }));
+///     assert!(!thread::spawn(log_reroute::flush_on_main_thread_exit).join().unwrap());
+///     assert!(log_reroute::flush_on_main_thread_exit());
+///     assert!(log_reroute::flush_on_main_thread_exit());
+///     return;
+/// }
+///
+/// let output = Command::new(env::current_exe().unwrap())
+///     .env("FLUSH_ON_MAIN_THREAD_EXIT_CHILD", "1")
+///     .output()
+///     .unwrap();
+/// assert!(output.status.success());
+/// # // Thread-local destructors at exit are platform dependent
+/// # if cfg!(all(target_os = "linux", target_env = "gnu")) {
+/// assert_eq!(b"Flushed\n", &output.stdout[..]);
+/// # }
+/// ```
+pub fn flush_on_main_thread_exit() -> bool {
+    static REGISTERED: AtomicBool = AtomicBool::new(false);
+    if thread::current().name() != Some("main") {
+        return false;
+    }
+    if !REGISTERED.swap(true, Ordering::Relaxed) {
+        // Touching it registers the destructor.
+        let _ = FLUSH.try_with(|_| ());
+    }
+    true
+}
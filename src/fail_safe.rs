@@ -0,0 +1,248 @@
+//! Surviving a panicking logger.
+
+use std::any::Any;
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use log::{Level, Log, Metadata, Record};
+
+use crate::fanout::isolated;
+use crate::panic::message;
+use crate::stderr::StderrLog;
+
+/// The last panic of the inner logger.
+struct Failure {
+    at: Instant,
+    message: String,
+}
+
+/// A wrapper that survives a panicking logger.
+///
+/// All the calls into the inner logger are done inside [`catch_unwind`][panic::catch_unwind].
+/// When it panics the first time, an error record describing the panic is sent to the fallback
+/// logger (by default a minimal stderr logger, or the one set by
+/// [`with_fallback`][FailSafe::with_fallback]) and all the records, including the one that caused
+/// the panic, go there from then on. With [`with_cool_down`][FailSafe::with_cool_down], the inner
+/// logger is tried again after some time.
+///
+/// The wrapper itself never panics, panics of the fallback are swallowed too. Note that the panic
+/// hook still runs for the caught panics (so the default one prints them to stderr).
+///
+/// ```rust
+/// use std::sync::{Arc, Mutex};
+///
+/// use log::{Level, Log, Record};
+/// use log_reroute::{FailSafe, FnLog};
+///
+/// fn info(log: &dyn Log, msg: &str) {
+///     log.log(
+///         &Record::builder()
+///             .level(Level::Info)
+///             .args(format_args!("{}", msg))
+///             .build(),
+///     );
+/// }
+///
+/// let messages = Arc::new(Mutex::new(Vec::new()));
+/// let inner = Arc::clone(&messages);
+/// let fallback = Arc::clone(&messages);
+/// let fail_safe = FailSafe::new(FnLog::new(move |record: &Record| {
+///     let msg = record.args().to_string();
+///     if msg == "boom" {
+///         panic!("the buggy logger broke");
+///     }
+///     inner.lock().unwrap().push(format!("inner: {}", msg));
+/// }))
+/// .with_fallback(FnLog::new(move |record: &Record| {
+///     fallback.lock().unwrap().push(format!("fallback: {}", record.args()));
+/// }));
+///
+/// info(&fail_safe, "Hello");
+/// assert!(fail_safe.panic_message().is_none());
+/// info(&fail_safe, "boom");
+/// info(&fail_safe, "World");
+/// assert_eq!(
+///     Some("the buggy logger broke".to_owned()),
+///     fail_safe.panic_message(),
+/// );
+/// assert_eq!(
+///     vec![
+///         "inner: Hello",
+///         "fallback: The logger panicked, using the fallback instead: the buggy logger broke",
+///         "fallback: boom",
+///         "fallback: World",
+///     ],
+///     *messages.lock().unwrap(),
+/// );
+/// ```
+pub struct FailSafe<L> {
+    inner: L,
+    fallback: Box<dyn Log>,
+    cool_down: Option<Duration>,
+    failed: AtomicBool,
+    failure: Mutex<Option<Failure>>,
+}
+
+impl<L: Log> FailSafe<L> {
+    /// Wraps the logger, falling back to stderr once it panics.
+    pub fn new(inner: L) -> Self {
+        Self {
+            inner,
+            fallback: Box::new(StderrLog),
+            cool_down: None,
+            failed: AtomicBool::new(false),
+            failure: Mutex::new(None),
+        }
+    }
+
+    /// Sets the logger used after the inner one panics.
+    pub fn with_fallback<F: Log + 'static>(mut self, fallback: F) -> Self {
+        self.fallback = Box::new(fallback);
+        self
+    }
+
+    /// Tries the inner logger again once this long has passed since its last panic.
+    ///
+    /// By default, a panicked logger is never used again.
+    ///
+    /// ```rust
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// use log::{Level, Log, Record};
+    /// use log_reroute::{Dummy, FailSafe, FnLog};
+    ///
+    /// let calls = Arc::new(AtomicUsize::new(0));
+    /// let counted = Arc::clone(&calls);
+    /// let fail_safe = FailSafe::new(FnLog::new(move |_: &Record| {
+    ///     // Panics every other time
+    ///     if counted.fetch_add(1, Ordering::Relaxed) % 2 == 0 {
+    ///         panic!("Odd call");
+    ///     }
+    /// }))
+    /// .with_fallback(Dummy)
+    /// .with_cool_down(Duration::ZERO);
+    ///
+    /// let record = Record::builder().level(Level::Info).build();
+    /// for _ in 0..4 {
+    ///     fail_safe.log(&record);
+    /// }
+    /// // Re-armed each time
+    /// assert_eq!(4, calls.load(Ordering::Relaxed));
+    /// ```
+    pub fn with_cool_down(mut self, cool_down: Duration) -> Self {
+        self.cool_down = Some(cool_down);
+        self
+    }
+
+    /// The message of the last panic of the inner logger, if it panicked.
+    pub fn panic_message(&self) -> Option<String> {
+        self.lock().as_ref().map(|failure| failure.message.clone())
+    }
+
+    /// The wrapped logger.
+    pub fn inner(&self) -> &L {
+        &self.inner
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<Failure>> {
+        self.failure.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Should the inner logger be used (re-arming it if the cool-down passed)?
+    fn armed(&self) -> bool {
+        if !self.failed.load(Ordering::Acquire) {
+            return true;
+        }
+        let cool_down = match self.cool_down {
+            Some(cool_down) => cool_down,
+            None => return false,
+        };
+        let failure = self.lock();
+        let cooled = failure
+            .as_ref()
+            .is_none_or(|failure| failure.at.elapsed() >= cool_down);
+        if cooled {
+            self.failed.store(false, Ordering::Release);
+        }
+        cooled
+    }
+
+    /// Runs the call into the inner logger, noting its panic.
+    fn guarded<R, F: FnOnce(&L) -> R>(&self, f: F) -> Option<R> {
+        if !self.armed() {
+            return None;
+        }
+        match panic::catch_unwind(AssertUnwindSafe(|| f(&self.inner))) {
+            Ok(result) => Some(result),
+            Err(payload) => {
+                self.fail(&*payload);
+                // Even dropping the payload may panic.
+                isolated(|| drop(payload));
+                None
+            }
+        }
+    }
+
+    fn fail(&self, payload: &(dyn Any + Send)) {
+        let message = message(payload).to_owned();
+        {
+            // Both under the lock, so whoever sees the flag also sees the time of this failure.
+            let mut failure = self.lock();
+            if self.failed.load(Ordering::Acquire) {
+                // Panicked concurrently in another thread, already reported.
+                return;
+            }
+            *failure = Some(Failure {
+                at: Instant::now(),
+                message: message.clone(),
+            });
+            self.failed.store(true, Ordering::Release);
+        }
+        // Outside of the lock, the fallback might be slow (or log through us).
+        isolated(|| {
+            self.fallback.log(
+                &Record::builder()
+                    .level(Level::Error)
+                    .target("log_reroute")
+                    .args(format_args!(
+                        "The logger panicked, using the fallback instead: {}",
+                        message
+                    ))
+                    .build(),
+            )
+        });
+    }
+}
+
+impl<L> Debug for FailSafe<L> {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_struct("FailSafe")
+            .field("cool_down", &self.cool_down)
+            .field("failed", &self.failed.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+impl<L: Log> Log for FailSafe<L> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.guarded(|inner| inner.enabled(metadata))
+            .unwrap_or_else(|| {
+                panic::catch_unwind(AssertUnwindSafe(|| self.fallback.enabled(metadata)))
+                    .unwrap_or(false)
+            })
+    }
+    fn log(&self, record: &Record) {
+        if self.guarded(|inner| inner.log(record)).is_none() {
+            isolated(|| self.fallback.log(record));
+        }
+    }
+    fn flush(&self) {
+        self.guarded(|inner| inner.flush());
+        isolated(|| self.fallback.flush());
+    }
+}
@@ -0,0 +1,70 @@
+//! Printing the important records before logging is set up.
+
+use std::io::{self, Write};
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+use crate::format::Plain;
+use crate::stderr::write_line;
+
+/// A minimal logger printing the severe records to stderr.
+///
+/// Losing trace messages before logging is set up is fine, losing a startup error is not. This is
+/// meant to be the initial slave (or the [rest state][crate::Reroute::set_rest_state]) that
+/// prints the records of the threshold level and more severe (by default `Warn`) as
+/// [`Plain`] `LEVEL target: message` lines, and ignores the rest. For the global instance, it can
+/// be installed by `log_reroute::init_with(StderrFallback::default())`.
+///
+/// There are no timestamps and no colors (so there's nothing to go wrong with consoles not
+/// understanding the ANSI sequences, eg. the older ones on Windows). Each record is written as a
+/// single line with stderr locked, so lines from different threads don't interleave. It is cheap
+/// enough to be left installed for the whole lifetime of small tools.
+///
+/// ```rust
+/// use log::{LevelFilter, Log, Metadata, Level};
+/// use log_reroute::{Reroute, StderrFallback};
+///
+/// let reroute = Reroute::with(StderrFallback::default());
+/// assert!(reroute.enabled(&Metadata::builder().level(Level::Error).build()));
+/// assert!(!reroute.enabled(&Metadata::builder().level(Level::Info).build()));
+///
+/// let verbose = StderrFallback::new(LevelFilter::Info);
+/// assert!(verbose.enabled(&Metadata::builder().level(Level::Info).build()));
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct StderrFallback {
+    threshold: LevelFilter,
+}
+
+impl StderrFallback {
+    /// Creates the logger printing the records of the given level and more severe.
+    pub const fn new(threshold: LevelFilter) -> Self {
+        Self { threshold }
+    }
+
+    /// The least severe level printed.
+    pub const fn threshold(&self) -> LevelFilter {
+        self.threshold
+    }
+}
+
+impl Default for StderrFallback {
+    fn default() -> Self {
+        Self::new(LevelFilter::Warn)
+    }
+}
+
+impl Log for StderrFallback {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.threshold
+    }
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        write_line(&Plain::new(), record, false);
+    }
+    fn flush(&self) {
+        let _ = io::stderr().flush();
+    }
+}
@@ -0,0 +1,187 @@
+//! Sending the records to several loggers.
+
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::panic::{self, AssertUnwindSafe};
+
+use log::{Log, Metadata, Record};
+
+/// Calls the closure, isolating the others from its panic.
+///
+/// The panic is still reported by the panic hook.
+pub(crate) fn isolated<F: FnOnce()>(f: F) {
+    let _ = panic::catch_unwind(AssertUnwindSafe(f));
+}
+
+/// Asks the child if it's enabled, a panicking one is taken as disabled.
+fn child_enabled(child: &dyn Log, metadata: &Metadata) -> bool {
+    panic::catch_unwind(AssertUnwindSafe(|| child.enabled(metadata))).unwrap_or(false)
+}
+
+/// A logger forwarding the records to several children.
+///
+/// Each record goes to every child that is [enabled][Log::enabled] for it, so eg. the same
+/// records can go both to a file and to stderr. A panic of one child doesn't prevent the others
+/// from getting the record (a child panicking in [`enabled`][Log::enabled] is taken as disabled
+/// for it). An empty fanout logs nowhere, like the [`Dummy`][crate::Dummy].
+///
+/// The set of the children is fixed; to change it, a new fanout is built and installed by
+/// [`Reroute::reroute`][crate::Reroute::reroute] (or a [`DynFanout`][crate::DynFanout] is used
+/// instead).
+///
+/// ```rust
+/// use std::sync::{Arc, Mutex};
+///
+/// use log::{Level, Log, Metadata, Record};
+/// use log_reroute::{Fanout, Reroute};
+///
+/// #[derive(Clone, Default)]
+/// struct Messages(Arc<Mutex<Vec<String>>>);
+///
+/// impl Log for Messages {
+///     fn enabled(&self, _: &Metadata) -> bool {
+///         true
+///     }
+///     fn log(&self, record: &Record) {
+///         self.0.lock().unwrap().push(record.args().to_string());
+///     }
+///     fn flush(&self) {}
+/// }
+///
+/// struct Broken;
+///
+/// impl Log for Broken {
+///     fn enabled(&self, _: &Metadata) -> bool {
+///         true
+///     }
+///     fn log(&self, _: &Record) {
+///         panic!("Broken logger");
+///     }
+///     fn flush(&self) {}
+/// }
+///
+/// let first = Messages::default();
+/// let second = Messages::default();
+/// let reroute = Reroute::new();
+/// reroute.reroute(Fanout::new(vec![
+///     Box::new(first.clone()),
+///     Box::new(Broken),
+///     Box::new(second.clone()),
+/// ]));
+/// reroute.log(
+///     &Record::builder()
+///         .level(Level::Info)
+///         .args(format_args!("Hello"))
+///         .build(),
+/// );
+/// assert_eq!(vec!["Hello"], *first.0.lock().unwrap());
+/// assert_eq!(vec!["Hello"], *second.0.lock().unwrap());
+///
+/// // Panicking in enabled doesn't get in the way either
+/// struct Moody;
+///
+/// impl Log for Moody {
+///     fn enabled(&self, _: &Metadata) -> bool {
+///         panic!("Not in the mood");
+///     }
+///     fn log(&self, _: &Record) {
+///         unreachable!("Never enabled");
+///     }
+///     fn flush(&self) {}
+/// }
+///
+/// let third = Messages::default();
+/// let fanout = Fanout::new(vec![Box::new(Moody), Box::new(third.clone())]);
+/// assert!(fanout.enabled(&Metadata::builder().level(Level::Info).build()));
+/// fanout.log(&Record::builder().args(format_args!("Still here")).build());
+/// assert_eq!(vec!["Still here"], *third.0.lock().unwrap());
+///
+/// let empty = Fanout::default();
+/// assert!(!empty.enabled(&Metadata::builder().level(Level::Error).build()));
+/// ```
+#[derive(Default)]
+pub struct Fanout {
+    children: Vec<Box<dyn Log>>,
+}
+
+impl Fanout {
+    /// Creates a fanout to the given children.
+    pub fn new(children: Vec<Box<dyn Log>>) -> Self {
+        Self { children }
+    }
+
+    /// How many children there are.
+    pub fn len(&self) -> usize {
+        self.children.len()
+    }
+
+    /// Is the fanout empty?
+    pub fn is_empty(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    /// Takes the children out.
+    pub fn into_inner(self) -> Vec<Box<dyn Log>> {
+        self.children
+    }
+}
+
+impl Fanout {
+    fn children(&self) -> impl Iterator<Item = &dyn Log> {
+        self.children.iter().map(|child| &**child)
+    }
+}
+
+impl From<Vec<Box<dyn Log>>> for Fanout {
+    fn from(children: Vec<Box<dyn Log>>) -> Self {
+        Self::new(children)
+    }
+}
+
+impl Debug for Fanout {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_struct("Fanout")
+            .field("children", &self.children.len())
+            .finish()
+    }
+}
+
+pub(crate) fn enabled<'a, I>(children: I, metadata: &Metadata) -> bool
+where
+    I: IntoIterator<Item = &'a dyn Log>,
+{
+    children
+        .into_iter()
+        .any(|child| child_enabled(child, metadata))
+}
+
+pub(crate) fn log<'a, I>(children: I, record: &Record)
+where
+    I: IntoIterator<Item = &'a dyn Log>,
+{
+    for child in children {
+        if child_enabled(child, record.metadata()) {
+            isolated(|| child.log(record));
+        }
+    }
+}
+
+pub(crate) fn flush<'a, I>(children: I)
+where
+    I: IntoIterator<Item = &'a dyn Log>,
+{
+    for child in children {
+        isolated(|| child.flush());
+    }
+}
+
+impl Log for Fanout {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        enabled(self.children(), metadata)
+    }
+    fn log(&self, record: &Record) {
+        log(self.children(), record)
+    }
+    fn flush(&self) {
+        flush(self.children())
+    }
+}
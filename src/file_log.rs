@@ -0,0 +1,406 @@
+//! A logger writing to a file.
+
+use std::ffi::OsString;
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+use crate::format::{Format, Plain};
+use crate::non_blocking::Backpressure;
+use crate::{WriteLog, DEFAULT_ASYNC_CAPACITY};
+
+/// How a [`FileLog`] opens its file.
+///
+/// ```rust
+/// use log::LevelFilter;
+/// use log_reroute::FileOptions;
+///
+/// let options = FileOptions::new().truncate(true).level(LevelFilter::Info);
+/// # drop(options);
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FileOptions {
+    truncate: bool,
+    level: LevelFilter,
+    rotation: Option<Rotation>,
+    pub(crate) capacity: usize,
+    pub(crate) backpressure: Backpressure,
+}
+
+impl FileOptions {
+    /// The default options.
+    ///
+    /// The file is appended to and all the records are written.
+    pub const fn new() -> Self {
+        Self {
+            truncate: false,
+            level: LevelFilter::Trace,
+            rotation: None,
+            capacity: DEFAULT_ASYNC_CAPACITY,
+            backpressure: Backpressure::Block,
+        }
+    }
+
+    /// Empties the file when opening it, instead of appending to it.
+    pub const fn truncate(mut self, truncate: bool) -> Self {
+        self.truncate = truncate;
+        self
+    }
+
+    /// Writes only the records of this level and more severe.
+    pub const fn level(mut self, level: LevelFilter) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Rotates the file once it would grow over `max_size` bytes.
+    ///
+    /// Before writing a line that wouldn't fit, the file is renamed to `<name>.1` (the older
+    /// `<name>.1` to `<name>.2`, etc.) and a new one is started. At most `keep` of the old files
+    /// are kept, the oldest one is removed; with `keep` 0, the file is only emptied. A single line
+    /// longer than the limit still goes to a fresh file whole.
+    pub const fn rotate(mut self, max_size: u64, keep: usize) -> Self {
+        self.rotation = Some(Rotation { max_size, keep });
+        self
+    }
+
+    /// How many lines a [`NonBlockingFileLog`][crate::NonBlockingFileLog] queues.
+    ///
+    /// The default is [`DEFAULT_ASYNC_CAPACITY`].
+    pub const fn queue_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// What a [`NonBlockingFileLog`][crate::NonBlockingFileLog] does when its queue is full.
+    pub const fn backpressure(mut self, backpressure: Backpressure) -> Self {
+        self.backpressure = backpressure;
+        self
+    }
+
+    /// The level set by [`level`][FileOptions::level].
+    pub(crate) fn max_level(&self) -> LevelFilter {
+        self.level
+    }
+
+    pub(crate) fn open(&self, path: &Path) -> io::Result<FileWriter> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(!self.truncate)
+            .write(true)
+            .truncate(self.truncate)
+            .open(path)?;
+        let size = file.metadata()?.len();
+        Ok(FileWriter {
+            path: path.to_owned(),
+            rotation: self.rotation,
+            file: BufWriter::new(file),
+            size,
+            rotate_at: self.rotation.map_or(u64::MAX, |r| r.max_size),
+            failure: None,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Rotation {
+    max_size: u64,
+    keep: usize,
+}
+
+/// The buffered file, rotating it when it grows too much.
+///
+/// Used with one whole line per write call (as by [`WriteLog`] from behind its lock), so the
+/// lines are never split between the files.
+pub(crate) struct FileWriter {
+    path: PathBuf,
+    rotation: Option<Rotation>,
+    file: BufWriter<File>,
+    /// Including what's still in the buffer.
+    size: u64,
+    /// Rotate before growing over this.
+    rotate_at: u64,
+    /// The last failed rotation, not yet reported.
+    failure: Option<io::Error>,
+}
+
+impl FileWriter {
+    /// The path of the old file with the index.
+    fn old(&self, index: usize) -> PathBuf {
+        let mut name = OsString::from(self.path.as_os_str());
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+
+    /// Takes out the failure of the last rotation.
+    ///
+    /// A failed rotation doesn't fail the write of the line (that went into the current file),
+    /// so it's picked up separately.
+    pub(crate) fn take_failure(&mut self) -> Option<io::Error> {
+        self.failure.take()
+    }
+
+    fn rotate(&mut self, keep: usize) -> io::Result<()> {
+        self.file.flush()?;
+        if keep > 0 {
+            for index in (1..keep).rev() {
+                match fs::rename(self.old(index), self.old(index + 1)) {
+                    Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+                    _ => (),
+                }
+            }
+            fs::rename(&self.path, self.old(1))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&self.path);
+        let file = match file {
+            Ok(file) => file,
+            Err(e) => {
+                if keep > 0 {
+                    // Put the current file back, the next attempt would shift it away otherwise.
+                    let _ = fs::rename(self.old(1), &self.path);
+                }
+                return Err(e);
+            }
+        };
+        // The old one is flushed already, nothing is lost by dropping it.
+        self.file = BufWriter::new(file);
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for FileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len() as u64;
+        if let Some(rotation) = self.rotation {
+            if self.size > 0 && self.size.saturating_add(len) > self.rotate_at {
+                match self.rotate(rotation.keep) {
+                    Ok(()) => self.rotate_at = rotation.max_size,
+                    Err(e) => {
+                        // Keep writing into the current file, try again after another max_size.
+                        self.rotate_at = self.size.saturating_add(rotation.max_size);
+                        self.failure = Some(e);
+                    }
+                }
+            }
+        }
+        self.file.write_all(buf)?;
+        self.size += len;
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Default for FileOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A logger writing the records into a file.
+///
+/// The file is created if it doesn't exist. The records are lines in the same format as the ones
+/// of [`Stderr`][crate::Stderr] (or in [another format][FileLog::with_format]), written through a
+/// buffer; [flushing][Log::flush] writes the buffer through to the OS. This is a [`WriteLog`]
+/// writing to the buffered file, so it reports the failed writes the same way.
+///
+/// The file can be [rotated][FileOptions::rotate] by its size. If the rotation fails (eg. the file
+/// can't be renamed), the records keep going into the current file and the failure is reported
+/// as an error (see [`with_error_handler`][FileLog::with_error_handler]); it is tried again once
+/// the file grows by another `max_size`.
+///
+/// ```rust
+/// use std::fs;
+///
+/// use log::{Level, Log, Record};
+/// use log_reroute::{FileLog, FileOptions};
+///
+/// let dir = tempfile::tempdir().unwrap();
+/// let path = dir.path().join("app.log");
+/// fs::write(&path, "Old content\n").unwrap();
+///
+/// let log = FileLog::open(&path, FileOptions::new()).unwrap();
+/// log.log(
+///     &Record::builder()
+///         .level(Level::Info)
+///         .target("app")
+///         .args(format_args!("Hello"))
+///         .build(),
+/// );
+/// log.flush();
+/// assert_eq!("Old content\nINFO app: Hello\n", fs::read_to_string(&path).unwrap());
+///
+/// let log = FileLog::open(&path, FileOptions::new().truncate(true)).unwrap();
+/// log.flush();
+/// assert_eq!("", fs::read_to_string(&path).unwrap());
+///
+/// assert!(FileLog::open(dir.path().join("missing/app.log"), FileOptions::new()).is_err());
+/// ```
+///
+/// Rotating the file:
+///
+/// ```rust
+/// use std::fs;
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// use log::{Level, Log, Record};
+/// use log_reroute::{FileLog, FileOptions};
+///
+/// let dir = tempfile::tempdir().unwrap();
+/// let path = dir.path().join("app.log");
+/// // Each line is 22 bytes, 5 of them fit into a file
+/// let log = Arc::new(FileLog::open(&path, FileOptions::new().rotate(110, 2)).unwrap());
+/// let threads = (0..4)
+///     .map(|t| {
+///         let log = Arc::clone(&log);
+///         thread::spawn(move || {
+///             for i in 0..5 {
+///                 log.log(
+///                     &Record::builder()
+///                         .level(Level::Info)
+///                         .target("app")
+///                         .args(format_args!("Message {}/{}", t, i))
+///                         .build(),
+///                 );
+///             }
+///         })
+///     })
+///     .collect::<Vec<_>>();
+/// for thread in threads {
+///     thread.join().unwrap();
+/// }
+/// log.flush();
+///
+/// // 20 lines, the oldest 5 didn't fit into the 2 kept old files
+/// let read = |name: &str| fs::read_to_string(dir.path().join(name)).unwrap();
+/// for name in ["app.log", "app.log.1", "app.log.2"] {
+///     let content = read(name);
+///     assert_eq!(5, content.lines().count());
+///     assert!(content.lines().all(|l| l.len() == 21 && l.starts_with("INFO app: ")));
+/// }
+/// assert!(!dir.path().join("app.log.3").exists());
+/// assert_eq!(0, log.errors());
+/// ```
+pub struct FileLog<F = Plain> {
+    path: PathBuf,
+    inner: WriteLog<FileWriter, F>,
+}
+
+impl FileLog {
+    /// Opens (or creates) the file.
+    pub fn open<P: AsRef<Path>>(path: P, options: FileOptions) -> io::Result<Self> {
+        let path = path.as_ref();
+        let file = options.open(path)?;
+        Ok(Self {
+            path: path.to_owned(),
+            inner: WriteLog::with_level(file, options.level),
+        })
+    }
+}
+
+impl<F: Format> FileLog<F> {
+    /// Sets the format of the lines.
+    pub fn with_format<G: Format>(self, format: G) -> FileLog<G> {
+        FileLog {
+            path: self.path,
+            inner: self.inner.with_format(format),
+        }
+    }
+
+    /// The path of the file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Calls the handler each time writing, flushing or rotating fails.
+    ///
+    /// See [`WriteLog::with_error_handler`].
+    ///
+    /// ```rust
+    /// use std::fs;
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// use log::{Level, Log, Record};
+    /// use log_reroute::{FileLog, FileOptions};
+    ///
+    /// let dir = tempfile::tempdir().unwrap();
+    /// let path = dir.path().join("app.log");
+    /// // The old file can't be renamed over a directory
+    /// fs::create_dir(dir.path().join("app.log.1")).unwrap();
+    /// fs::write(dir.path().join("app.log.1/blocker"), "").unwrap();
+    ///
+    /// let failures = Arc::new(AtomicUsize::new(0));
+    /// let counter = Arc::clone(&failures);
+    /// let log = FileLog::open(&path, FileOptions::new().rotate(10, 1))
+    ///     .unwrap()
+    ///     .with_error_handler(move |_| {
+    ///         counter.fetch_add(1, Ordering::Relaxed);
+    ///     });
+    /// for i in 0..2 {
+    ///     log.log(
+    ///         &Record::builder()
+    ///             .level(Level::Info)
+    ///             .target("app")
+    ///             .args(format_args!("Message {}", i))
+    ///             .build(),
+    ///     );
+    /// }
+    /// log.flush();
+    ///
+    /// // Both of them still made it into the file
+    /// assert_eq!(2, fs::read_to_string(&path).unwrap().lines().count());
+    /// assert_eq!(1, failures.load(Ordering::Relaxed));
+    /// assert_eq!(1, log.errors());
+    /// ```
+    pub fn with_error_handler<H>(self, handler: H) -> Self
+    where
+        H: Fn(&io::Error) + Send + Sync + 'static,
+    {
+        FileLog {
+            path: self.path,
+            inner: self.inner.with_error_handler(handler),
+        }
+    }
+
+    /// How many times writing, flushing or rotating failed.
+    pub fn errors(&self) -> u64 {
+        self.inner.errors()
+    }
+}
+
+impl<F> Debug for FileLog<F> {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_struct("FileLog")
+            .field("path", &self.path)
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<F: Format> Log for FileLog<F> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+    fn log(&self, record: &Record) {
+        self.inner.log(record);
+        // Not holding the writer locked while reporting.
+        let failure = self.inner.writer().take_failure();
+        if let Some(error) = failure {
+            self.inner.report(&error);
+        }
+    }
+    fn flush(&self) {
+        self.inner.flush()
+    }
+}
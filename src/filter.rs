@@ -0,0 +1,549 @@
+//! Filtering of the records in front of the slave.
+
+use std::cell::Cell;
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use log::{LevelFilter, Metadata};
+
+use crate::Reroute;
+
+/// The level used when the spec doesn't set one.
+pub(crate) const DEFAULT_LEVEL: LevelFilter = LevelFilter::Warn;
+
+/// An error in a filter spec.
+///
+/// Returned when parsing a [`FilterSpec`] and by [`Reroute::apply_env_filter`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FilterError {
+    fragment: String,
+    position: usize,
+}
+
+impl FilterError {
+    /// The part of the spec that was not understood.
+    pub fn fragment(&self) -> &str {
+        &self.fragment
+    }
+
+    /// The position of the fragment in the spec (in bytes).
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl Display for FilterError {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        write!(
+            fmt,
+            "Invalid filter directive '{}' at position {}",
+            self.fragment, self.position
+        )
+    }
+}
+
+impl Error for FilterError {}
+
+/// Is the target the prefix itself or its submodule?
+fn matches(prefix: &str, target: &str) -> bool {
+    target == prefix || (target.starts_with(prefix) && target[prefix.len()..].starts_with("::"))
+}
+
+/// Finds the value of the most specific (longest) prefix matching the target.
+pub(crate) fn lookup<'a, T: 'a, I>(entries: I, target: &str) -> Option<&'a T>
+where
+    I: IntoIterator<Item = &'a (String, T)>,
+{
+    entries
+        .into_iter()
+        .filter(|(prefix, _)| matches(prefix, target))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(_, value)| value)
+}
+
+/// Levels of targets, parsed from a spec like `info,my_crate::db=trace,hyper=off`.
+///
+/// The spec is a comma separated list of either levels (eg. `info`, setting the default level)
+/// or `target=level` pairs (setting the levels of the given targets and their submodules). The
+/// most specific (longest) matching target wins and the default level is `warn` if not set. This
+/// is the syntax of the `RUST_LOG` variable as known from `env_logger` (without the regular
+/// expressions).
+///
+/// It can be installed in front of the slave by [`Reroute::set_filter`].
+///
+/// ```rust
+/// use log::LevelFilter;
+/// use log_reroute::FilterSpec;
+///
+/// let spec: FilterSpec = "info,my_crate::db=trace,hyper=off".parse().unwrap();
+/// let cases = [
+///     ("my_crate", LevelFilter::Info),
+///     ("my_crate::db", LevelFilter::Trace),
+///     ("my_crate::db::pool", LevelFilter::Trace),
+///     ("my_crate::dbx", LevelFilter::Info),
+///     ("hyper", LevelFilter::Off),
+///     ("hyper::client", LevelFilter::Off),
+///     ("hyperactive", LevelFilter::Info),
+/// ];
+/// for (target, level) in cases {
+///     assert_eq!(level, spec.level(target), "{}", target);
+/// }
+///
+/// let spec: FilterSpec = "my_crate=debug".parse().unwrap();
+/// assert_eq!(LevelFilter::Warn, spec.default_level());
+/// assert_eq!(LevelFilter::Warn, spec.level("other"));
+/// assert_eq!(LevelFilter::Debug, spec.max_level());
+///
+/// let error = "info,=debug,hyper=loud".parse::<FilterSpec>().unwrap_err();
+/// assert_eq!("=debug", error.fragment());
+/// assert_eq!(5, error.position());
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FilterSpec {
+    default: LevelFilter,
+    /// The targets with their levels.
+    targets: Vec<(String, LevelFilter)>,
+}
+
+impl FilterSpec {
+    /// Parses the spec.
+    ///
+    /// Returns the fragments that were not understood as the second value.
+    pub(crate) fn parse_lenient(spec: &str) -> (Self, Vec<FilterError>) {
+        let mut filter = FilterSpec {
+            default: DEFAULT_LEVEL,
+            targets: Vec::new(),
+        };
+        let mut unknown = Vec::new();
+        let mut position = 0;
+        for raw in spec.split(',') {
+            let fragment = raw.trim();
+            let fragment_pos = position + (raw.len() - raw.trim_start().len());
+            position += raw.len() + 1;
+            if fragment.is_empty() {
+                continue;
+            }
+            let mut parts = fragment.splitn(2, '=');
+            let first = parts.next().unwrap_or_default().trim();
+            let parsed = match (parts.next().map(str::trim), first.parse::<LevelFilter>()) {
+                (None, Ok(level)) => {
+                    filter.default = level;
+                    true
+                }
+                (Some(level), _) if !first.is_empty() => match level.parse() {
+                    Ok(level) => {
+                        filter.targets.push((first.to_owned(), level));
+                        true
+                    }
+                    Err(_) => false,
+                },
+                _ => false,
+            };
+            if !parsed {
+                unknown.push(FilterError {
+                    fragment: fragment.to_owned(),
+                    position: fragment_pos,
+                });
+            }
+        }
+        (filter, unknown)
+    }
+
+    /// Creates the spec from the levels directly, without parsing.
+    pub fn from_levels<I>(default: LevelFilter, targets: I) -> Self
+    where
+        I: IntoIterator<Item = (String, LevelFilter)>,
+    {
+        FilterSpec {
+            default,
+            targets: targets.into_iter().collect(),
+        }
+    }
+
+    /// The level used for the targets not listed in the spec.
+    pub fn default_level(&self) -> LevelFilter {
+        self.default
+    }
+
+    /// The level for the given target.
+    pub fn level(&self, target: &str) -> LevelFilter {
+        lookup(&self.targets, target)
+            .copied()
+            .unwrap_or(self.default)
+    }
+
+    /// The most verbose of the levels.
+    pub fn max_level(&self) -> LevelFilter {
+        self.targets
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, Ord::max)
+    }
+
+    /// Does the spec let the record with this metadata through?
+    pub fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level(metadata.target())
+    }
+}
+
+impl FromStr for FilterSpec {
+    type Err = FilterError;
+
+    /// Parses the spec, failing on the first fragment not understood.
+    fn from_str(spec: &str) -> Result<Self, FilterError> {
+        let (filter, mut unknown) = Self::parse_lenient(spec);
+        if unknown.is_empty() {
+            Ok(filter)
+        } else {
+            Err(unknown.swap_remove(0))
+        }
+    }
+}
+
+type FilterFn = Arc<dyn Fn(&Metadata) -> bool + Send + Sync>;
+
+thread_local! {
+    /// Is this thread running a filter closure right now?
+    static IN_FILTER_FN: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Resets the [`IN_FILTER_FN`] even if the closure panics.
+struct InFilterFn;
+
+impl Drop for InFilterFn {
+    fn drop(&mut self) {
+        IN_FILTER_FN.with(|f| f.set(false));
+    }
+}
+
+/// All the filters of a reroute.
+///
+/// Replaced as a whole whenever any of them changes.
+#[derive(Clone, Default)]
+pub(crate) struct Filters {
+    spec: Option<Arc<FilterSpec>>,
+    /// The per-target overrides, sorted by the target.
+    overrides: Vec<(String, LevelFilter)>,
+    func: Option<FilterFn>,
+}
+
+impl Filters {
+    fn is_empty(&self) -> bool {
+        self.spec.is_none() && self.overrides.is_empty() && self.func.is_none()
+    }
+
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let levels = match lookup(&self.overrides, metadata.target()).copied() {
+            Some(level) => metadata.level() <= level,
+            None => self.spec.as_ref().is_none_or(|spec| spec.enabled(metadata)),
+        };
+        levels
+            && self
+                .func
+                .as_ref()
+                .is_none_or(|func| Self::call(func, metadata))
+    }
+
+    fn call(func: &FilterFn, metadata: &Metadata) -> bool {
+        // The records logged from within the closure skip it, otherwise they'd recurse.
+        if IN_FILTER_FN.with(|f| f.replace(true)) {
+            return true;
+        }
+        let _reset = InFilterFn;
+        func(metadata)
+    }
+}
+
+/// Converts the numeric representation of the gate back.
+fn level_filter(level: usize) -> LevelFilter {
+    const LEVELS: [LevelFilter; 6] = [
+        LevelFilter::Off,
+        LevelFilter::Error,
+        LevelFilter::Warn,
+        LevelFilter::Info,
+        LevelFilter::Debug,
+        LevelFilter::Trace,
+    ];
+    LEVELS[level.min(LEVELS.len() - 1)]
+}
+
+impl Reroute {
+    /// Is the level let through by the [gate][Reroute::set_gate]?
+    #[inline]
+    pub(crate) fn gate_passes(&self, metadata: &Metadata) -> bool {
+        metadata.level() as usize <= self.gate.load(Ordering::Relaxed)
+    }
+
+    /// Sets the levels let through to the slave.
+    ///
+    /// This is a cheap gate in front of the slave (no matter which one is installed), checked
+    /// before anything else is done with a record. It is kept when the slave is replaced. The
+    /// default `Trace` lets everything through.
+    ///
+    /// Unlike the [`log::max_level`], this is specific to this reroute and is checked even for
+    /// the records not logged through the [`log`] macros.
+    ///
+    /// ```rust
+    /// use log::{Level, LevelFilter, Log, Metadata, Record};
+    /// use log_reroute::Reroute;
+    ///
+    /// struct All;
+    ///
+    /// impl Log for All {
+    ///     fn enabled(&self, _: &Metadata) -> bool {
+    ///         true
+    ///     }
+    ///     fn log(&self, _: &Record) {}
+    ///     fn flush(&self) {}
+    /// }
+    ///
+    /// let reroute = Reroute::with(All);
+    /// reroute.set_gate(LevelFilter::Info);
+    /// reroute.reroute(All);
+    /// assert_eq!(LevelFilter::Info, reroute.gate());
+    ///
+    /// let debug = Metadata::builder().level(Level::Debug).build();
+    /// assert!(!reroute.enabled(&debug));
+    /// let info = Metadata::builder().level(Level::Info).build();
+    /// assert!(reroute.enabled(&info));
+    /// ```
+    pub fn set_gate(&self, level: LevelFilter) {
+        self.gate.store(level as usize, Ordering::Relaxed);
+    }
+
+    /// Returns the current [gate][Reroute::set_gate].
+    pub fn gate(&self) -> LevelFilter {
+        level_filter(self.gate.load(Ordering::Relaxed))
+    }
+
+    /// Do the filters let the record through?
+    #[inline]
+    pub(crate) fn passes(&self, metadata: &Metadata) -> bool {
+        // Checking the flag is cheaper than loading the filters.
+        !self.has_filters.load(Ordering::Relaxed) || self.passes_slow(metadata)
+    }
+
+    #[cold]
+    fn passes_slow(&self, metadata: &Metadata) -> bool {
+        match &*self.filters.load() {
+            Some(filters) => filters.enabled(metadata),
+            None => true,
+        }
+    }
+
+    /// Changes the filters.
+    fn update_filters<F: FnOnce(&mut Filters)>(&self, f: F) {
+        // The lock keeps concurrent updates from losing each other.
+        let _state = self.lock_state();
+        let mut filters = self.filters.load().as_deref().cloned().unwrap_or_default();
+        f(&mut filters);
+        let empty = filters.is_empty();
+        self.filters
+            .store(if empty { None } else { Some(Arc::new(filters)) });
+        self.has_filters.store(!empty, Ordering::Relaxed);
+    }
+
+    /// Filters the records by the spec.
+    ///
+    /// The filter is applied in front of the slave, no matter which one is installed (now or in
+    /// the future). The previous spec is replaced (atomically, the logging threads see either
+    /// the old or the new one) and the [`log::max_level`] is set to the most verbose level of the
+    /// spec.
+    pub fn set_filter(&self, filter: FilterSpec) {
+        log::set_max_level(filter.max_level());
+        self.update_filters(|filters| filters.spec = Some(Arc::new(filter)));
+    }
+
+    /// Parses the spec in the format of the `RUST_LOG` variable and [sets][Reroute::set_filter]
+    /// it.
+    ///
+    /// See [`FilterSpec`] for the format. If the spec contains something not understood,
+    /// nothing is changed and the error describes the first such part.
+    ///
+    /// ```rust
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// use log::{Level, Log, Metadata, Record};
+    /// use log_reroute::Reroute;
+    ///
+    /// #[derive(Clone, Default)]
+    /// struct Targets(Arc<Mutex<Vec<String>>>);
+    ///
+    /// impl Log for Targets {
+    ///     fn enabled(&self, _: &Metadata) -> bool {
+    ///         true
+    ///     }
+    ///     fn log(&self, record: &Record) {
+    ///         self.0.lock().unwrap().push(record.target().to_owned());
+    ///     }
+    ///     fn flush(&self) {}
+    /// }
+    ///
+    /// fn debug(reroute: &Reroute, target: &str) {
+    ///     let record = Record::builder()
+    ///         .level(Level::Debug)
+    ///         .target(target)
+    ///         .args(format_args!("Hello"))
+    ///         .build();
+    ///     reroute.log(&record);
+    /// }
+    ///
+    /// let targets = Targets::default();
+    /// let reroute = Reroute::with(targets.clone());
+    /// reroute.apply_env_filter("debug,hyper=warn").unwrap();
+    ///
+    /// debug(&reroute, "hyper::client");
+    /// debug(&reroute, "my_crate");
+    /// assert_eq!(vec!["my_crate"], *targets.0.lock().unwrap());
+    ///
+    /// let error = reroute.apply_env_filter("info,hyper=loud").unwrap_err();
+    /// assert_eq!("hyper=loud", error.fragment());
+    /// assert_eq!(5, error.position());
+    /// // The previous filter stays
+    /// let meta = Metadata::builder().level(Level::Debug).target("my_crate").build();
+    /// assert!(reroute.enabled(&meta));
+    /// ```
+    pub fn apply_env_filter(&self, spec: &str) -> Result<(), FilterError> {
+        self.set_filter(spec.parse()?);
+        Ok(())
+    }
+
+    /// Overrides the level of a target and its submodules.
+    ///
+    /// This is useful to mute a noisy dependency at runtime. Like the [filter
+    /// spec][Reroute::set_filter], it applies in front of any slave and survives
+    /// replacing it. The overrides take precedence over the spec and the most specific
+    /// (longest) matching one wins. Setting the same target again replaces its level.
+    ///
+    /// This doesn't change the [`log::max_level`], so making a target more verbose than that has
+    /// no effect on the records logged through the macros.
+    ///
+    /// ```rust
+    /// use log::{Level, LevelFilter, Log, Metadata, Record};
+    /// use log_reroute::Reroute;
+    ///
+    /// struct All;
+    ///
+    /// impl Log for All {
+    ///     fn enabled(&self, _: &Metadata) -> bool {
+    ///         true
+    ///     }
+    ///     fn log(&self, _: &Record) {}
+    ///     fn flush(&self) {}
+    /// }
+    ///
+    /// let enabled = |reroute: &Reroute, target| {
+    ///     let meta = Metadata::builder().level(Level::Info).target(target).build();
+    ///     reroute.enabled(&meta)
+    /// };
+    ///
+    /// let reroute = Reroute::with(All);
+    /// reroute.set_target_level("hyper", LevelFilter::Warn);
+    /// reroute.set_target_level("hyper::server", LevelFilter::Info);
+    /// reroute.reroute(All);
+    ///
+    /// assert!(!enabled(&reroute, "hyper"));
+    /// assert!(!enabled(&reroute, "hyper::client"));
+    /// assert!(enabled(&reroute, "hyper::server::conn"));
+    /// assert!(enabled(&reroute, "hyperactive"));
+    ///
+    /// reroute.remove_target_level("hyper");
+    /// assert!(enabled(&reroute, "hyper::client"));
+    /// ```
+    pub fn set_target_level<T: Into<String>>(&self, target: T, level: LevelFilter) {
+        let target = target.into();
+        self.update_filters(|filters| {
+            match filters
+                .overrides
+                .binary_search_by(|(t, _)| t.as_str().cmp(&target))
+            {
+                Ok(pos) => filters.overrides[pos].1 = level,
+                Err(pos) => filters.overrides.insert(pos, (target, level)),
+            }
+        });
+    }
+
+    /// Removes an override set by [`set_target_level`][Reroute::set_target_level].
+    ///
+    /// Returns the level the target had, if any. Overrides of its submodules are kept.
+    pub fn remove_target_level(&self, target: &str) -> Option<LevelFilter> {
+        let mut removed = None;
+        self.update_filters(|filters| {
+            if let Ok(pos) = filters
+                .overrides
+                .binary_search_by(|(t, _)| t.as_str().cmp(target))
+            {
+                removed = Some(filters.overrides.remove(pos).1);
+            }
+        });
+        removed
+    }
+
+    /// Filters the records by a closure.
+    ///
+    /// This is for the rules that can't be expressed by the levels of targets. The closure is
+    /// consulted (after the [spec][Reroute::set_filter] and the [target
+    /// overrides][Reroute::set_target_level]) in front of any slave, both when logging and when
+    /// checking if a record is enabled. Only the records for which it returns `true` get
+    /// through. Setting another closure replaces the previous one.
+    ///
+    /// The closure may log itself. Such records skip the closure (but not the other filters),
+    /// instead of recursing into it.
+    ///
+    /// ```rust
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    ///
+    /// use log::{Level, Log, Metadata, Record};
+    /// use log_reroute::Reroute;
+    ///
+    /// struct All;
+    ///
+    /// impl Log for All {
+    ///     fn enabled(&self, _: &Metadata) -> bool {
+    ///         true
+    ///     }
+    ///     fn log(&self, _: &Record) {}
+    ///     fn flush(&self) {}
+    /// }
+    ///
+    /// static INCIDENT: AtomicBool = AtomicBool::new(false);
+    ///
+    /// let reroute = Reroute::with(All);
+    /// reroute.set_filter_fn(|meta| {
+    ///     !meta.target().starts_with("sqlx::query") || INCIDENT.load(Ordering::Relaxed)
+    /// });
+    ///
+    /// let query = Metadata::builder().level(Level::Info).target("sqlx::query").build();
+    /// assert!(!reroute.enabled(&query));
+    /// INCIDENT.store(true, Ordering::Relaxed);
+    /// assert!(reroute.enabled(&query));
+    ///
+    /// INCIDENT.store(false, Ordering::Relaxed);
+    /// reroute.clear_filter_fn();
+    /// assert!(reroute.enabled(&query));
+    /// ```
+    pub fn set_filter_fn<F>(&self, f: F)
+    where
+        F: Fn(&Metadata) -> bool + Send + Sync + 'static,
+    {
+        let f: FilterFn = Arc::new(f);
+        self.update_filters(|filters| filters.func = Some(f));
+    }
+
+    /// Removes the closure set by [`set_filter_fn`][Reroute::set_filter_fn].
+    pub fn clear_filter_fn(&self) {
+        self.update_filters(|filters| filters.func = None);
+    }
+
+    /// Lists the [overrides][Reroute::set_target_level] of target levels, sorted by the target.
+    pub fn target_levels(&self) -> Vec<(String, LevelFilter)> {
+        self.filters
+            .load()
+            .as_ref()
+            .map(|filters| filters.overrides.clone())
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,131 @@
+//! Flushing right after the severe records.
+
+use std::convert::TryFrom;
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use log::{Level, Log, Metadata, Record};
+
+/// A wrapper flushing the logger after each severe record.
+///
+/// The records at or above the level (by default [`Error`][Level::Error]) are forwarded and then
+/// the inner logger is [flushed][Log::flush], so the record doesn't stay in a buffer when the
+/// application crashes right after it. With [`with_debounce`][FlushOn::with_debounce], there's at
+/// most one such flush per the interval, so a burst of errors doesn't cause a burst of flushes.
+///
+/// ```rust
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// use log::{Level, Log, Record};
+/// use log_reroute::{FlushOn, FnLog};
+///
+/// let flushes = Arc::new(AtomicUsize::new(0));
+/// let counted = Arc::clone(&flushes);
+/// let log = |debounce| {
+///     let counted = Arc::clone(&counted);
+///     FlushOn::new(FnLog::new(|_: &Record| ()).with_flush(move || {
+///         counted.fetch_add(1, Ordering::Relaxed);
+///     }))
+///     .at(Level::Warn)
+///     .with_debounce(debounce)
+/// };
+/// let record = |level| Record::builder().level(level).build();
+///
+/// let flush_on = log(Duration::ZERO);
+/// flush_on.log(&record(Level::Info));
+/// assert_eq!(0, flushes.load(Ordering::Relaxed));
+/// flush_on.log(&record(Level::Warn));
+/// flush_on.log(&record(Level::Error));
+/// assert_eq!(2, flushes.load(Ordering::Relaxed));
+///
+/// // Only one flush per hour
+/// let flush_on = log(Duration::from_secs(3600));
+/// for _ in 0..3 {
+///     flush_on.log(&record(Level::Error));
+/// }
+/// assert_eq!(3, flushes.load(Ordering::Relaxed));
+/// ```
+pub struct FlushOn<L> {
+    inner: L,
+    level: Level,
+    debounce: Duration,
+    created: Instant,
+    /// Nanoseconds since `created` of the last forced flush, plus one (0 is never).
+    last: AtomicU64,
+}
+
+impl<L: Log> FlushOn<L> {
+    /// Wraps the logger, flushing it after each error.
+    pub fn new(inner: L) -> Self {
+        Self {
+            inner,
+            level: Level::Error,
+            debounce: Duration::ZERO,
+            created: Instant::now(),
+            last: AtomicU64::new(0),
+        }
+    }
+
+    /// Flushes after the records of this level and more severe.
+    pub fn at(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Flushes at most once per the interval.
+    pub fn with_debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// The wrapped logger.
+    pub fn inner(&self) -> &L {
+        &self.inner
+    }
+
+    /// Claims the next forced flush, unless there was one too recently.
+    fn due(&self) -> bool {
+        if self.debounce.is_zero() {
+            return true;
+        }
+        let nanos = |d: Duration| u64::try_from(d.as_nanos()).unwrap_or(u64::MAX);
+        let now = nanos(self.created.elapsed()).saturating_add(1);
+        let debounce = nanos(self.debounce);
+        self.last
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |last| {
+                if last == 0 || now.saturating_sub(last) >= debounce {
+                    Some(now)
+                } else {
+                    None
+                }
+            })
+            .is_ok()
+    }
+}
+
+impl<L> Debug for FlushOn<L> {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_struct("FlushOn")
+            .field("level", &self.level)
+            .field("debounce", &self.debounce)
+            .finish()
+    }
+}
+
+impl<L: Log> Log for FlushOn<L> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+    fn log(&self, record: &Record) {
+        self.inner.log(record);
+        if record.level() <= self.level && self.due() {
+            self.inner.flush();
+        }
+    }
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
@@ -0,0 +1,111 @@
+//! A logger made of closures.
+
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+
+use log::{Log, Metadata, Record};
+
+type Filter = Box<dyn Fn(&Metadata) -> bool + Send + Sync>;
+type Flush = Box<dyn Fn() + Send + Sync>;
+
+/// A logger calling a closure for each record.
+///
+/// Handy for experiments and tests, to avoid writing a struct implementing [`Log`]. By default
+/// it is enabled for everything and flushing does nothing; both can be customized by
+/// [`with_filter`][FnLog::with_filter] and [`with_flush`][FnLog::with_flush].
+///
+/// The closures are called from whatever threads log, possibly concurrently. They are called
+/// right in the logging path, so a blocking closure (eg. waiting for the network) blocks the
+/// code that logs.
+///
+/// ```rust
+/// use std::sync::{Arc, Mutex};
+///
+/// use log::{Level, Log, Record};
+/// use log_reroute::{FnLog, Reroute};
+///
+/// let reroute = Reroute::new();
+/// reroute.reroute(FnLog::new(|r| eprintln!("{}: {}", r.level(), r.args())));
+///
+/// let messages = Arc::new(Mutex::new(Vec::new()));
+/// let collect = Arc::clone(&messages);
+/// reroute.reroute(
+///     FnLog::new(move |r| collect.lock().unwrap().push(r.args().to_string()))
+///         .with_filter(|m| m.level() <= Level::Info),
+/// );
+/// for level in [Level::Info, Level::Debug] {
+///     reroute.log(
+///         &Record::builder()
+///             .level(level)
+///             .args(format_args!("{}", level))
+///             .build(),
+///     );
+/// }
+/// assert_eq!(vec!["INFO"], *messages.lock().unwrap());
+/// ```
+pub struct FnLog<F> {
+    log: F,
+    filter: Option<Filter>,
+    flush: Option<Flush>,
+}
+
+impl<F> FnLog<F>
+where
+    F: Fn(&Record) + Send + Sync,
+{
+    /// Creates the logger calling the closure for each record.
+    pub fn new(log: F) -> Self {
+        Self {
+            log,
+            filter: None,
+            flush: None,
+        }
+    }
+
+    /// Sets the closure deciding which records are [enabled][Log::enabled].
+    ///
+    /// The records it rejects are not passed to the logging closure.
+    pub fn with_filter<E>(mut self, filter: E) -> Self
+    where
+        E: Fn(&Metadata) -> bool + Send + Sync + 'static,
+    {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+
+    /// Sets the closure called on [flush][Log::flush].
+    pub fn with_flush<L>(mut self, flush: L) -> Self
+    where
+        L: Fn() + Send + Sync + 'static,
+    {
+        self.flush = Some(Box::new(flush));
+        self
+    }
+}
+
+impl<F> Debug for FnLog<F> {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_struct("FnLog")
+            .field("filter", &self.filter.is_some())
+            .field("flush", &self.flush.is_some())
+            .finish()
+    }
+}
+
+impl<F> Log for FnLog<F>
+where
+    F: Fn(&Record) + Send + Sync,
+{
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.filter.as_ref().is_none_or(|filter| filter(metadata))
+    }
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            (self.log)(record);
+        }
+    }
+    fn flush(&self) {
+        if let Some(flush) = &self.flush {
+            flush();
+        }
+    }
+}
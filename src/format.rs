@@ -0,0 +1,235 @@
+//! Formats of the lines written by the [`WriteLog`][crate::WriteLog].
+
+use std::env;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io::{self, Write};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{Level, Record};
+
+/// A format of the lines written by the [`WriteLog`][crate::WriteLog].
+///
+/// It writes a single record, including the trailing newline. The `time` is when the record is
+/// being written (the [`Record`] doesn't carry one).
+///
+/// The crate provides the [`Plain`], [`Json`][crate::Json] and [`Logfmt`][crate::Logfmt] formats
+/// and the configurable [`Pattern`][crate::Pattern].
+pub trait Format: Send + Sync {
+    /// Formats the record into the output.
+    fn format(&self, out: &mut dyn Write, record: &Record, time: SystemTime) -> io::Result<()>;
+
+    /// Formats the record for an output that is (or is not) a terminal.
+    ///
+    /// The formats that can use colors decide by this. The default ignores it.
+    fn format_for(
+        &self,
+        out: &mut dyn Write,
+        record: &Record,
+        time: SystemTime,
+        terminal: bool,
+    ) -> io::Result<()> {
+        let _ = terminal;
+        self.format(out, record, time)
+    }
+}
+
+/// When to use colors.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ColorChoice {
+    /// Always, whatever the output is.
+    Always,
+    /// Never.
+    #[default]
+    Never,
+    /// When writing into a terminal.
+    ///
+    /// The `NO_COLOR` environment variable (set to anything non-empty) turns the colors off,
+    /// `CLICOLOR_FORCE` (set to anything other than `0`) turns them on even when not writing to a
+    /// terminal; `NO_COLOR` wins if both are set. The variables are read once.
+    ///
+    /// On Windows, this never uses colors (the older consoles would print the escape sequences
+    /// as garbage).
+    Auto,
+}
+
+impl ColorChoice {
+    /// Decides if the colors are used for an output that is (or is not) a terminal.
+    pub fn use_color(&self, terminal: bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => auto_color(terminal),
+        }
+    }
+}
+
+fn auto_color(terminal: bool) -> bool {
+    static ENV: OnceLock<(bool, bool)> = OnceLock::new();
+    let (no_color, force) = *ENV.get_or_init(|| {
+        let no_color = env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        let force = env::var_os("CLICOLOR_FORCE").is_some_and(|v| !v.is_empty() && v != "0");
+        (no_color, force)
+    });
+    !no_color && (force || (terminal && !cfg!(windows)))
+}
+
+/// The plain `LEVEL target: message` lines.
+///
+/// Optionally, the level and the target can be colored (the level by its severity, the target
+/// dimmed); the message is always left as it is. With [`ColorChoice::Auto`], the same format
+/// colors the lines for a terminal and not for a file.
+///
+/// ```rust
+/// use std::time::SystemTime;
+///
+/// use log::{Level, Record};
+/// use log_reroute::{ColorChoice, Format, Plain};
+///
+/// let record = Record::builder()
+///     .level(Level::Error)
+///     .target("app")
+///     .args(format_args!("Failed"))
+///     .build();
+/// let render = |format: &Plain, terminal: bool| {
+///     let mut out = Vec::new();
+///     format.format_for(&mut out, &record, SystemTime::now(), terminal).unwrap();
+///     String::from_utf8(out).unwrap()
+/// };
+///
+/// let colored = Plain::new().with_color(ColorChoice::Always);
+/// assert_eq!("\x1b[31mERROR\x1b[0m \x1b[2mapp\x1b[0m: Failed\n", render(&colored, false));
+///
+/// let plain = Plain::new();
+/// assert_eq!("ERROR app: Failed\n", render(&plain, true));
+///
+/// let auto = Plain::new().with_color(ColorChoice::Auto);
+/// assert_eq!("ERROR app: Failed\n", render(&auto, false));
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Plain {
+    color: ColorChoice,
+}
+
+impl Plain {
+    /// Creates the format, without colors.
+    pub const fn new() -> Self {
+        Self {
+            color: ColorChoice::Never,
+        }
+    }
+
+    /// Sets when to use colors.
+    pub const fn with_color(self, color: ColorChoice) -> Self {
+        Self { color }
+    }
+
+    /// When the colors are used.
+    pub const fn color(&self) -> ColorChoice {
+        self.color
+    }
+}
+
+impl Format for Plain {
+    fn format(&self, out: &mut dyn Write, record: &Record, time: SystemTime) -> io::Result<()> {
+        self.format_for(out, record, time, false)
+    }
+
+    fn format_for(
+        &self,
+        out: &mut dyn Write,
+        record: &Record,
+        _time: SystemTime,
+        terminal: bool,
+    ) -> io::Result<()> {
+        if !self.color.use_color(terminal) {
+            return write_record(out, record);
+        }
+        let color = match record.level() {
+            Level::Error => "31",
+            Level::Warn => "33",
+            Level::Info => "32",
+            Level::Debug => "34",
+            Level::Trace => "35",
+        };
+        writeln!(
+            out,
+            "\x1b[{}m{}\x1b[0m \x1b[2m{}\x1b[0m: {}",
+            color,
+            record.level(),
+            record.target(),
+            record.args()
+        )
+    }
+}
+
+/// Writes the record as a single line.
+fn write_record<W: Write + ?Sized>(out: &mut W, record: &Record) -> io::Result<()> {
+    writeln!(
+        out,
+        "{} {}: {}",
+        record.level(),
+        record.target(),
+        record.args()
+    )
+}
+
+/// A point in time split into the calendar fields (in UTC).
+pub(crate) struct Civil {
+    pub(crate) year: i64,
+    pub(crate) month: u32,
+    pub(crate) day: u32,
+    pub(crate) hour: u32,
+    pub(crate) minute: u32,
+    pub(crate) second: u32,
+    pub(crate) nanos: u32,
+}
+
+impl Civil {
+    pub(crate) fn new(time: SystemTime) -> Self {
+        // Before the epoch can happen only with a really broken clock, don't fail on that.
+        let since = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let secs = since.as_secs();
+        let days = (secs / 86_400) as i64;
+        let of_day = (secs % 86_400) as u32;
+        // The days to the civil date algorithm by Howard Hinnant.
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era * 400 + i64::from(month <= 2);
+        Self {
+            year,
+            month,
+            day,
+            hour: of_day / 3600,
+            minute: of_day / 60 % 60,
+            second: of_day % 60,
+            nanos: since.subsec_nanos(),
+        }
+    }
+}
+
+/// Formats the time as RFC 3339 in UTC, with milliseconds (`2024-03-01T12:30:05.123Z`).
+pub(crate) struct Rfc3339(pub(crate) SystemTime);
+
+impl Display for Rfc3339 {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        let c = Civil::new(self.0);
+        write!(
+            fmt,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+            c.year,
+            c.month,
+            c.day,
+            c.hour,
+            c.minute,
+            c.second,
+            c.nanos / 1_000_000
+        )
+    }
+}
@@ -0,0 +1,325 @@
+//! The global instance and the functions manipulating it.
+
+use std::env;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use log::{Level, LevelFilter, Log, Record, SetLoggerError};
+
+use crate::filter::{FilterError, FilterSpec};
+use crate::stderr::{Stderr, StderrLog};
+#[cfg(doc)]
+use crate::Dummy;
+use crate::{FileLog, FileOptions};
+use crate::{InitResult, Reroute, RerouteGuard, SwapEvent};
+
+/// A global [`Reroute`](struct.Reroute.html) object.
+///
+/// This one is manipulated by the global functions:
+///
+/// * [`init`](fn.init.html)
+/// * [`init_at`](fn.init_at.html)
+/// * [`init_with`](fn.init_with.html)
+/// * [`try_init`](fn.try_init.html)
+/// * [`init_with_boxed`](fn.init_with_boxed.html)
+/// * [`init_with_level`](fn.init_with_level.html)
+/// * [`reroute`](fn.reroute.html)
+/// * [`reroute_boxed`](fn.reroute_boxed.html)
+/// * [`reroute_if_unset`](fn.reroute_if_unset.html)
+/// * [`swap`](fn.swap.html)
+/// * [`guarded`](fn.guarded.html)
+/// * [`set_rest_state`](fn.set_rest_state.html)
+/// * [`history`](fn.history.html)
+/// * [`is_installed`](fn.is_installed.html)
+pub static REROUTE: Reroute = Reroute::const_new();
+
+/// Installs the global [`Reroute`](struct.Reroute.html) instance into the
+/// [`log`](https://crates.io/crates/log) facade.
+///
+/// Note that the default slave is [`Dummy`](struct.Dummy.html) and you need to call
+/// [`reroute`](fn.reroute.html) or [`reroute_boxed`](fn.reroute_boxed.html).
+pub fn init() -> Result<(), SetLoggerError> {
+    log::set_logger(&REROUTE)
+}
+
+/// Installs the global [`Reroute`](struct.Reroute.html) instance and sets the max level.
+///
+/// This is the same as [`init`] followed by [`log::set_max_level`] (the level is set only if the
+/// installation succeeds). It prevents the common mistake of forgetting the latter, since the
+/// default is to log nothing.
+///
+/// Changing the slave later on doesn't change the level. See [`Reroute::install_at`].
+pub fn init_at(level: LevelFilter) -> Result<(), SetLoggerError> {
+    REROUTE.install_at(level)
+}
+
+/// Installs the global [`Reroute`](struct.Reroute.html) instance, unless already installed.
+///
+/// See [`Reroute::try_install`].
+///
+/// ```rust
+/// use log_reroute::InitResult;
+///
+/// assert_eq!(InitResult::Installed, log_reroute::try_init());
+/// assert_eq!(InitResult::AlreadyReroute, log_reroute::try_init());
+/// ```
+pub fn try_init() -> InitResult {
+    REROUTE.try_install()
+}
+
+/// Installs the global [`Reroute`](struct.Reroute.html) instance with a simple stderr logger
+/// configured by the `RUST_LOG` environment variable.
+///
+/// This allows using this crate as the only logging dependency in early stages of development.
+/// The variable is a comma separated list of either levels (eg. `info`, setting the default level)
+/// or `target=level` pairs (setting the levels of the given targets and their submodules). If it's
+/// not set, the level is `warn`. Anything else in the variable is ignored and reported by a
+/// warning.
+///
+/// The filter is [applied][Reroute::apply_env_filter] in front of the slave and the max level is
+/// set to what the variable asks for. Rerouting later on replaces the stderr logger, but keeps the
+/// filter and the level.
+///
+/// ```rust
+/// std::env::set_var("RUST_LOG", "info,my_crate::noisy=error");
+/// log_reroute::init_from_env().unwrap();
+/// assert_eq!(log::LevelFilter::Info, log::max_level());
+/// log::info!("Printed to stderr");
+/// ```
+pub fn init_from_env() -> Result<(), SetLoggerError> {
+    let spec = env::var("RUST_LOG").unwrap_or_default();
+    let (filter, unknown) = FilterSpec::parse_lenient(&spec);
+    init_with(StderrLog)?;
+    // Directly, the filter might not let them through (eg. with RUST_LOG=error,bogus).
+    for error in unknown {
+        StderrLog.log(
+            &Record::builder()
+                .level(Level::Warn)
+                .target("log_reroute")
+                .args(format_args!("Ignoring part of RUST_LOG: {}", error))
+                .build(),
+        );
+    }
+    REROUTE.set_filter(filter);
+    Ok(())
+}
+
+/// Reads the `RUST_LOG` environment variable again and applies it to the global
+/// [`Reroute`](struct.Reroute.html) instance.
+///
+/// This is useful to change the filter of a running program, as set up by [`init_from_env`]. If
+/// the variable is not set, the default `warn` level is used.
+///
+/// See [`Reroute::apply_env_filter`].
+pub fn reload_from_env() -> Result<(), FilterError> {
+    REROUTE.apply_env_filter(&env::var("RUST_LOG").unwrap_or_default())
+}
+
+/// Logs panics through the global [`Reroute`](struct.Reroute.html) instance.
+///
+/// See [`Reroute::install_panic_hook`].
+///
+/// ```rust
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// use log::{Log, Metadata, Record};
+///
+/// static PANICS: AtomicUsize = AtomicUsize::new(0);
+///
+/// struct CountPanics;
+///
+/// impl Log for CountPanics {
+///     fn enabled(&self, _: &Metadata) -> bool {
+///         true
+///     }
+///     fn log(&self, record: &Record) {
+///         if record.target() == "panic" {
+///             assert!(record.args().to_string().contains("Logged"));
+///             PANICS.fetch_add(1, Ordering::Relaxed);
+///         }
+///     }
+///     fn flush(&self) {}
+/// }
+///
+/// log_reroute::init_with_level(CountPanics, log::LevelFilter::Error).unwrap();
+/// log_reroute::install_panic_hook();
+/// let result = std::panic::catch_unwind(|| panic!("Logged"));
+/// assert!(result.is_err());
+/// assert_eq!(1, PANICS.load(Ordering::Relaxed));
+/// ```
+pub fn install_panic_hook() {
+    REROUTE.install_panic_hook()
+}
+
+/// Installs the global [`Reroute`](struct.Reroute.html) instance, already pointing to the slave.
+///
+/// Unlike [`init`] followed by [`reroute`], no records are lost to the [`Dummy`] in between.
+///
+/// The slave is set even if installing the reroute fails (because some other logger is installed
+/// already).
+///
+/// ```rust
+/// use log::Record;
+/// use log_reroute::FnLog;
+///
+/// log_reroute::init_with(FnLog::new(|record: &Record| eprintln!("{}", record.args()))).unwrap();
+/// ```
+pub fn init_with<L: Log + 'static>(log: L) -> Result<(), SetLoggerError> {
+    REROUTE.reroute(log);
+    init()
+}
+
+/// Installs the global [`Reroute`](struct.Reroute.html) instance, already pointing to the boxed
+/// slave.
+///
+/// See [`init_with`].
+pub fn init_with_boxed(log: Box<dyn Log>) -> Result<(), SetLoggerError> {
+    REROUTE.reroute_boxed(log);
+    init()
+}
+
+/// Installs the global [`Reroute`](struct.Reroute.html) instance pointing to the slave and sets
+/// the max level.
+///
+/// Like [`init_with`], but also sets the [`log::set_max_level`] (only if the installation
+/// succeeds).
+pub fn init_with_level<L: Log + 'static>(log: L, level: LevelFilter) -> Result<(), SetLoggerError> {
+    init_with(log)?;
+    log::set_max_level(level);
+    Ok(())
+}
+
+/// Is the global [`Reroute`](struct.Reroute.html) instance installed in the [`log`] facade?
+///
+/// ```rust
+/// assert!(!log_reroute::is_installed());
+/// log_reroute::init().unwrap();
+/// assert!(log_reroute::is_installed());
+/// ```
+pub fn is_installed() -> bool {
+    REROUTE.is_installed()
+}
+
+/// Warns (once) through the new slave that the global instance is not installed.
+///
+/// Otherwise, the records would silently never reach it.
+fn warn_not_installed() {
+    static WARNED: AtomicBool = AtomicBool::new(false);
+    if is_installed() || WARNED.swap(true, Ordering::Relaxed) {
+        return;
+    }
+    REROUTE.get().log(
+        &Record::builder()
+            .level(log::Level::Warn)
+            .target("log_reroute")
+            .module_path_static(Some(module_path!()))
+            .args(format_args!(
+                "log_reroute: reroute() called but init() was never called; records will not \
+                 reach this logger"
+            ))
+            .build(),
+    );
+}
+
+/// Sends the records of the level and more severe to stderr, through the global
+/// [`Reroute`](struct.Reroute.html) instance.
+///
+/// This is [`reroute`] with the [`Stderr`] logger. It doesn't change the [`log::max_level`], the
+/// global instance is expected to be [installed][init_at] with one already.
+///
+/// ```rust
+/// use log::LevelFilter;
+///
+/// log_reroute::init_at(LevelFilter::Info).unwrap();
+/// log_reroute::reroute_stderr(LevelFilter::Info);
+/// log::info!("Printed to stderr");
+/// log::debug!("Not printed");
+/// ```
+pub fn reroute_stderr(level: LevelFilter) {
+    reroute(Stderr::new(level));
+}
+
+/// Sends the records into the file, through the global [`Reroute`](struct.Reroute.html)
+/// instance.
+///
+/// This opens a [`FileLog`] and [`reroute`]s into it, so the previous slave is flushed. If the
+/// file can't be opened, the error is returned and the previous slave stays (the application can
+/// eg. fall back to [stderr][reroute_stderr]).
+///
+/// ```rust
+/// use log::LevelFilter;
+/// use log_reroute::FileOptions;
+///
+/// log_reroute::init_at(LevelFilter::Info).unwrap();
+/// let dir = tempfile::tempdir().unwrap();
+/// log_reroute::reroute_to_file(dir.path().join("app.log"), FileOptions::new()).unwrap();
+/// log::info!("Into the file");
+///
+/// let missing = dir.path().join("missing/app.log");
+/// if log_reroute::reroute_to_file(missing, FileOptions::new()).is_err() {
+///     log_reroute::reroute_stderr(LevelFilter::Info);
+/// }
+/// ```
+pub fn reroute_to_file<P: AsRef<Path>>(path: P, options: FileOptions) -> io::Result<()> {
+    reroute(FileLog::open(path, options)?);
+    Ok(())
+}
+
+/// Changes the slave of the global [`Reroute`](struct.Reroute.html) instance.
+///
+/// If you have a boxed logger, use [`reroute_boxed`](fn.reroute_boxed.html).
+///
+/// If the global instance was not [installed][init] yet, the records would not reach the slave.
+/// To make this mistake visible, a warning is sent to the slave the first time it happens.
+pub fn reroute<L: Log + 'static>(log: L) {
+    REROUTE.reroute(log);
+    warn_not_installed();
+}
+
+/// Changes the slave of the global [`Reroute`](struct.Reroute.html) instance.
+///
+/// Like [`reroute`], this warns if the global instance is not installed.
+pub fn reroute_boxed(log: Box<dyn Log>) {
+    REROUTE.reroute_boxed(log);
+    warn_not_installed();
+}
+
+/// Changes the slave of the global [`Reroute`](struct.Reroute.html) instance, unless it is already
+/// set.
+///
+/// See [`Reroute::reroute_if_unset`].
+pub fn reroute_if_unset<L: Log + 'static>(log: L) -> bool {
+    REROUTE.reroute_if_unset(log)
+}
+
+/// Changes the slave of the global [`Reroute`](struct.Reroute.html) instance until the returned
+/// guard is dropped.
+///
+/// See [`Reroute::guard`].
+pub fn guarded<L: Log + 'static>(log: L) -> RerouteGuard<'static> {
+    REROUTE.guard(log)
+}
+
+/// Sets the rest state of the global [`Reroute`](struct.Reroute.html) instance.
+///
+/// See [`Reroute::set_rest_state`].
+pub fn set_rest_state<L: Log + 'static>(log: L) {
+    REROUTE.set_rest_state(log)
+}
+
+/// Changes the slave of the global [`Reroute`](struct.Reroute.html) instance and returns the
+/// previous one.
+///
+/// See [`Reroute::swap`] for details (the previous logger is not flushed).
+pub fn swap(log: Box<dyn Log>) -> Arc<Box<dyn Log>> {
+    REROUTE.swap(log)
+}
+
+/// Returns the last few changes of the global [`Reroute`](struct.Reroute.html) instance.
+///
+/// See [`Reroute::history`].
+pub fn history() -> Vec<SwapEvent> {
+    REROUTE.history()
+}
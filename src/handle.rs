@@ -0,0 +1,108 @@
+//! Sharing a reroute without the global instance.
+
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::sync::Arc;
+
+use log::{Log, Metadata, Record};
+
+use crate::Reroute;
+
+/// A cheaply cloneable handle allowing to change the slave of a shared [`Reroute`].
+///
+/// This can be handed to other parts of the program (eg. another library) that should be able to
+/// change the logging destination, without giving them the whole reroute or requiring it to be
+/// `'static`. The handle also forwards the logging into the reroute.
+///
+/// Created by [`Reroute::handle`].
+///
+/// ```rust
+/// use std::sync::Arc;
+///
+/// use log_reroute::{Dummy, Reroute};
+///
+/// let reroute = Arc::new(Reroute::new());
+/// let handle = reroute.handle();
+/// handle.reroute(Dummy);
+/// assert_eq!(1, reroute.generation());
+/// ```
+#[derive(Clone)]
+pub struct RerouteHandle(Arc<Reroute>);
+
+impl RerouteHandle {
+    /// Sets a new slave of the reroute.
+    ///
+    /// See [`Reroute::reroute`].
+    pub fn reroute<L: Log + 'static>(&self, log: L) {
+        self.0.reroute(log)
+    }
+
+    /// Sets a new, already boxed slave of the reroute.
+    ///
+    /// See [`Reroute::reroute_boxed`].
+    pub fn reroute_boxed(&self, log: Box<dyn Log>) {
+        self.0.reroute_boxed(log)
+    }
+
+    /// Puts the reroute into its rest state.
+    ///
+    /// See [`Reroute::clear`].
+    pub fn clear(&self) {
+        self.0.clear()
+    }
+
+    /// Returns the current slave of the reroute.
+    ///
+    /// See [`Reroute::get`].
+    pub fn get(&self) -> Arc<Box<dyn Log>> {
+        self.0.get()
+    }
+}
+
+impl RerouteHandle {
+    pub(crate) fn target(&self) -> &Reroute {
+        &self.0
+    }
+}
+
+impl Log for RerouteHandle {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.0.enabled(metadata)
+    }
+    fn log(&self, record: &Record) {
+        self.0.log(record)
+    }
+    fn flush(&self) {
+        self.0.flush()
+    }
+}
+
+impl Debug for RerouteHandle {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_tuple("RerouteHandle").field(&self.0).finish()
+    }
+}
+
+impl From<Arc<Reroute>> for RerouteHandle {
+    fn from(reroute: Arc<Reroute>) -> Self {
+        Self(reroute)
+    }
+}
+
+impl Reroute {
+    /// Creates a [`RerouteHandle`] to this reroute.
+    ///
+    /// Note that an `Arc<Reroute>` is a logger too, so a shared reroute can also be installed by
+    /// [`log::set_boxed_logger`].
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use log_reroute::Reroute;
+    ///
+    /// let reroute = Arc::new(Reroute::new());
+    /// log::set_boxed_logger(Box::new(Arc::clone(&reroute))).unwrap();
+    /// ```
+    pub fn handle(self: &Arc<Self>) -> RerouteHandle {
+        RerouteHandle(Arc::clone(self))
+    }
+}
@@ -0,0 +1,62 @@
+//! Remembering the past swaps.
+//!
+//! See [`Reroute::history`][crate::Reroute::history].
+
+use std::collections::VecDeque;
+use std::time::SystemTime;
+
+/// How many swaps are remembered by default.
+pub const DEFAULT_HISTORY_CAPACITY: usize = 16;
+
+/// A record of a past change of the slave.
+///
+/// See [`Reroute::history`][crate::Reroute::history].
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct SwapEvent {
+    /// When the change happened.
+    pub at: SystemTime,
+
+    /// Type name of the slave installed by the change.
+    ///
+    /// See [`RerouteStatus::type_name`][crate::RerouteStatus::type_name].
+    pub type_name: &'static str,
+
+    /// The [generation][crate::Reroute::generation] of the installed slave.
+    pub generation: u64,
+}
+
+/// A ring of the last few swaps.
+pub(crate) struct History {
+    capacity: usize,
+    events: VecDeque<SwapEvent>,
+}
+
+impl History {
+    pub(crate) const fn new() -> Self {
+        Self {
+            capacity: DEFAULT_HISTORY_CAPACITY,
+            events: VecDeque::new(),
+        }
+    }
+
+    pub(crate) fn push(&mut self, event: SwapEvent) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.events.len() >= self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    pub(crate) fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        let excess = self.events.len().saturating_sub(capacity);
+        self.events.drain(..excess);
+    }
+
+    pub(crate) fn events(&self) -> Vec<SwapEvent> {
+        self.events.iter().cloned().collect()
+    }
+}
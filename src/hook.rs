@@ -0,0 +1,30 @@
+//! Observers of the swaps.
+//!
+//! See [`Reroute::on_swap`][crate::Reroute::on_swap].
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+
+use crate::SwapEvent;
+
+/// A handle to a hook, allowing its removal.
+///
+/// Returned by [`Reroute::on_swap`][crate::Reroute::on_swap], passed to
+/// [`Reroute::remove_hook`][crate::Reroute::remove_hook].
+#[derive(Debug, Eq, PartialEq)]
+pub struct HookHandle(pub(crate) u64);
+
+pub(crate) type Hook = Arc<dyn Fn(&SwapEvent) + Send + Sync>;
+
+pub(crate) type Hooks = Vec<(u64, Hook)>;
+
+/// Calls all the hooks with all the events.
+pub(crate) fn fire(hooks: &Hooks, events: &[SwapEvent]) {
+    for event in events {
+        for (_, hook) in hooks {
+            // A broken observer must not break the swapping (and the other observers). The
+            // default panic hook still reports it.
+            let _ = panic::catch_unwind(AssertUnwindSafe(|| hook(event)));
+        }
+    }
+}
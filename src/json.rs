@@ -0,0 +1,276 @@
+//! The JSON lines format.
+
+use std::fmt::{self, Arguments, Display, Result as FmtResult, Write as FmtWrite};
+use std::io::{self, Write};
+use std::time::SystemTime;
+
+use log::Record;
+
+use crate::format::{Format, Rfc3339};
+
+/// The names of the fields of each record.
+#[cfg(feature = "kv")]
+const FIELDS: &[&str] = &["ts", "level", "target", "msg", "module", "file", "line"];
+
+/// Writes the text escaped as the inside of a JSON string.
+fn escape(out: &mut dyn Write, text: &str) -> io::Result<()> {
+    let bytes = text.as_bytes();
+    let mut plain = 0;
+    // All the escaped characters are ASCII, so they can't be a part of a multi-byte char.
+    for (i, b) in bytes.iter().enumerate() {
+        let escaped: &[u8] = match b {
+            b'"' => b"\\\"",
+            b'\\' => b"\\\\",
+            b'\n' => b"\\n",
+            b'\r' => b"\\r",
+            b'\t' => b"\\t",
+            0..=0x1f => b"",
+            _ => continue,
+        };
+        out.write_all(&bytes[plain..i])?;
+        if escaped.is_empty() {
+            write!(out, "\\u{:04x}", b)?;
+        } else {
+            out.write_all(escaped)?;
+        }
+        plain = i + 1;
+    }
+    out.write_all(&bytes[plain..])
+}
+
+/// Escapes everything written into it into the output.
+struct Escape<'a> {
+    out: &'a mut dyn Write,
+    result: io::Result<()>,
+}
+
+impl FmtWrite for Escape<'_> {
+    fn write_str(&mut self, s: &str) -> FmtResult {
+        self.result = escape(self.out, s);
+        self.result.as_ref().map_err(|_| fmt::Error).copied()
+    }
+}
+
+/// Writes a JSON string (with the quotes).
+fn string(out: &mut dyn Write, args: Arguments) -> io::Result<()> {
+    out.write_all(b"\"")?;
+    let mut escape = Escape {
+        out: &mut *out,
+        result: Ok(()),
+    };
+    // A failing Display impl still leaves a valid (if shorter) string.
+    let _ = escape.write_fmt(args);
+    escape.result?;
+    out.write_all(b"\"")
+}
+
+fn optional<T: Display>(out: &mut dyn Write, value: Option<T>) -> io::Result<()> {
+    match value {
+        Some(value) => string(out, format_args!("{}", value)),
+        None => out.write_all(b"null"),
+    }
+}
+
+/// Records as JSON objects, one per line.
+///
+/// The fields are `ts` (the time of writing, in RFC 3339 in UTC), `level` (like `"WARN"`),
+/// `target`, `msg`, `module`, `file` and `line` (the last three can be `null`). The output is
+/// always valid JSON, whatever the message contains (the non-ASCII characters are written as
+/// they are, the control characters are escaped).
+///
+/// With the `kv` feature, the key-values of the record are additional fields of the object.
+/// The numbers, booleans and strings keep their types, anything else is formatted as a string.
+/// A key colliding with one of the fields above is prefixed by `kv.` (eg. `kv.msg`).
+///
+/// ```rust
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// use log::{Level, Record};
+/// use log_reroute::{Format, Json};
+///
+/// let mut out = Vec::new();
+/// let time = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+/// let record = Record::builder()
+///     .level(Level::Warn)
+///     .target("app")
+///     .line(Some(42))
+///     .args(format_args!("Said \"hi\"\nand left – bye"))
+///     .build();
+/// Json.format(&mut out, &record, time).unwrap();
+/// let line = String::from_utf8(out).unwrap();
+/// assert!(line.ends_with('\n'));
+/// assert_eq!(1, line.lines().count());
+///
+/// let parsed: serde_json::Value = serde_json::from_str(&line).unwrap();
+/// assert_eq!(
+///     serde_json::json!({
+///         "ts": "2023-11-14T22:13:20.123Z",
+///         "level": "WARN",
+///         "target": "app",
+///         "msg": "Said \"hi\"\nand left – bye",
+///         "module": null,
+///         "file": null,
+///         "line": 42,
+///     }),
+///     parsed,
+/// );
+/// ```
+///
+/// With the key-values:
+///
+/// ```rust
+/// # #![cfg_attr(not(feature = "kv"), allow(unused))]
+/// # #[cfg(feature = "kv")]
+/// # fn main() {
+/// use log::LevelFilter;
+/// use log_reroute::WriteLog;
+///
+/// let out = WriteLog::json(Vec::new());
+/// log::set_max_level(LevelFilter::Info);
+/// log::info!(logger: &out, tenant = "acme", count = 3, msg = "shadowed"; "Hello");
+///
+/// let line = String::from_utf8(out.into_inner()).unwrap();
+/// let parsed: serde_json::Value = serde_json::from_str(&line).unwrap();
+/// assert_eq!("Hello", parsed["msg"]);
+/// assert_eq!("acme", parsed["tenant"]);
+/// assert_eq!(3, parsed["count"]);
+/// assert_eq!("shadowed", parsed["kv.msg"]);
+/// # }
+/// # #[cfg(not(feature = "kv"))]
+/// # fn main() {}
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Json;
+
+impl Format for Json {
+    fn format(&self, out: &mut dyn Write, record: &Record, time: SystemTime) -> io::Result<()> {
+        out.write_all(b"{\"ts\":")?;
+        string(out, format_args!("{}", Rfc3339(time)))?;
+        out.write_all(b",\"level\":")?;
+        string(out, format_args!("{}", record.level()))?;
+        out.write_all(b",\"target\":")?;
+        string(out, format_args!("{}", record.target()))?;
+        out.write_all(b",\"msg\":")?;
+        string(out, *record.args())?;
+        out.write_all(b",\"module\":")?;
+        optional(out, record.module_path())?;
+        out.write_all(b",\"file\":")?;
+        optional(out, record.file())?;
+        out.write_all(b",\"line\":")?;
+        match record.line() {
+            Some(line) => write!(out, "{}", line)?,
+            None => out.write_all(b"null")?,
+        }
+        #[cfg(feature = "kv")]
+        kv::write(out, record)?;
+        out.write_all(b"}\n")
+    }
+}
+
+#[cfg(feature = "kv")]
+mod kv {
+    use std::io::{self, Write};
+
+    use log::kv::{Error, Key, Value, VisitSource, VisitValue};
+    use log::Record;
+
+    use super::{string, FIELDS};
+
+    struct Fields<'a> {
+        out: &'a mut dyn Write,
+        result: io::Result<()>,
+    }
+
+    impl Fields<'_> {
+        fn check(&mut self, result: io::Result<()>) -> Result<(), Error> {
+            self.result = result;
+            match &self.result {
+                Ok(()) => Ok(()),
+                Err(_) => Err(Error::msg("Failed to write")),
+            }
+        }
+    }
+
+    impl<'kvs> VisitSource<'kvs> for Fields<'_> {
+        fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), Error> {
+            let prefix = if FIELDS.contains(&key.as_str()) {
+                "kv."
+            } else {
+                ""
+            };
+            let result = self
+                .out
+                .write_all(b",")
+                .and_then(|()| string(self.out, format_args!("{}{}", prefix, key)))
+                .and_then(|()| self.out.write_all(b":"));
+            self.check(result)?;
+            let mut json = JsonValue {
+                out: &mut *self.out,
+                result: Ok(()),
+            };
+            let _ = value.visit(&mut json);
+            let result = json.result;
+            self.check(result)
+        }
+    }
+
+    struct JsonValue<'a> {
+        out: &'a mut dyn Write,
+        result: io::Result<()>,
+    }
+
+    impl JsonValue<'_> {
+        fn number<T: std::fmt::Display>(&mut self, value: T) -> Result<(), Error> {
+            self.result = write!(self.out, "{}", value);
+            Ok(())
+        }
+    }
+
+    impl<'v> VisitValue<'v> for JsonValue<'_> {
+        fn visit_any(&mut self, value: Value) -> Result<(), Error> {
+            self.result = string(self.out, format_args!("{}", value));
+            Ok(())
+        }
+        fn visit_null(&mut self) -> Result<(), Error> {
+            self.result = self.out.write_all(b"null");
+            Ok(())
+        }
+        fn visit_u64(&mut self, value: u64) -> Result<(), Error> {
+            self.number(value)
+        }
+        fn visit_i64(&mut self, value: i64) -> Result<(), Error> {
+            self.number(value)
+        }
+        fn visit_u128(&mut self, value: u128) -> Result<(), Error> {
+            self.number(value)
+        }
+        fn visit_i128(&mut self, value: i128) -> Result<(), Error> {
+            self.number(value)
+        }
+        fn visit_f64(&mut self, value: f64) -> Result<(), Error> {
+            if value.is_finite() {
+                self.number(value)
+            } else {
+                // JSON has no infinities and NaNs.
+                self.result = string(self.out, format_args!("{}", value));
+                Ok(())
+            }
+        }
+        fn visit_bool(&mut self, value: bool) -> Result<(), Error> {
+            self.number(value)
+        }
+        fn visit_str(&mut self, value: &str) -> Result<(), Error> {
+            self.result = string(self.out, format_args!("{}", value));
+            Ok(())
+        }
+    }
+
+    pub(super) fn write(out: &mut dyn Write, record: &Record) -> io::Result<()> {
+        let mut fields = Fields {
+            out,
+            result: Ok(()),
+        };
+        let _ = record.key_values().visit(&mut fields);
+        fields.result
+    }
+}
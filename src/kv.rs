@@ -0,0 +1,174 @@
+//! Owned copies of the structured key-values of records.
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::hash::{Hash, Hasher};
+
+use log::kv::{Error, Key, Source, ToValue, Value, VisitSource, VisitValue};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// An owned form of a [`Value`].
+///
+/// The primitive types are kept, anything else is formatted into a string.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub(crate) enum OwnedValue {
+    Null,
+    Bool(bool),
+    U64(u64),
+    I64(i64),
+    U128(u128),
+    I128(i128),
+    F64(f64),
+    Str(String),
+}
+
+impl OwnedValue {
+    fn from_value(value: &Value) -> Self {
+        struct Convert(Option<OwnedValue>);
+
+        impl<'v> VisitValue<'v> for Convert {
+            fn visit_any(&mut self, value: Value) -> Result<(), Error> {
+                self.0 = Some(OwnedValue::Str(value.to_string()));
+                Ok(())
+            }
+            fn visit_null(&mut self) -> Result<(), Error> {
+                self.0 = Some(OwnedValue::Null);
+                Ok(())
+            }
+            fn visit_u64(&mut self, value: u64) -> Result<(), Error> {
+                self.0 = Some(OwnedValue::U64(value));
+                Ok(())
+            }
+            fn visit_i64(&mut self, value: i64) -> Result<(), Error> {
+                self.0 = Some(OwnedValue::I64(value));
+                Ok(())
+            }
+            fn visit_u128(&mut self, value: u128) -> Result<(), Error> {
+                self.0 = Some(OwnedValue::U128(value));
+                Ok(())
+            }
+            fn visit_i128(&mut self, value: i128) -> Result<(), Error> {
+                self.0 = Some(OwnedValue::I128(value));
+                Ok(())
+            }
+            fn visit_f64(&mut self, value: f64) -> Result<(), Error> {
+                self.0 = Some(OwnedValue::F64(value));
+                Ok(())
+            }
+            fn visit_bool(&mut self, value: bool) -> Result<(), Error> {
+                self.0 = Some(OwnedValue::Bool(value));
+                Ok(())
+            }
+            fn visit_str(&mut self, value: &str) -> Result<(), Error> {
+                self.0 = Some(OwnedValue::Str(value.to_owned()));
+                Ok(())
+            }
+        }
+
+        let mut convert = Convert(None);
+        let _ = value.visit(&mut convert);
+        convert
+            .0
+            .unwrap_or_else(|| OwnedValue::Str(value.to_string()))
+    }
+}
+
+// Comparing the floats by their bits, so the records can be Eq and Hash.
+impl PartialEq for OwnedValue {
+    fn eq(&self, other: &Self) -> bool {
+        use OwnedValue::*;
+        match (self, other) {
+            (Null, Null) => true,
+            (Bool(a), Bool(b)) => a == b,
+            (U64(a), U64(b)) => a == b,
+            (I64(a), I64(b)) => a == b,
+            (U128(a), U128(b)) => a == b,
+            (I128(a), I128(b)) => a == b,
+            (F64(a), F64(b)) => a.to_bits() == b.to_bits(),
+            (Str(a), Str(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for OwnedValue {}
+
+impl Hash for OwnedValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        use OwnedValue::*;
+        match self {
+            Null => 0u8.hash(state),
+            Bool(v) => (1u8, v).hash(state),
+            U64(v) => (2u8, v).hash(state),
+            I64(v) => (3u8, v).hash(state),
+            U128(v) => (4u8, v).hash(state),
+            I128(v) => (5u8, v).hash(state),
+            F64(v) => (6u8, v.to_bits()).hash(state),
+            Str(v) => (7u8, v).hash(state),
+        }
+    }
+}
+
+impl Display for OwnedValue {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        self.to_value().fmt(fmt)
+    }
+}
+
+impl ToValue for OwnedValue {
+    fn to_value(&self) -> Value<'_> {
+        match self {
+            OwnedValue::Null => Value::null(),
+            OwnedValue::Bool(v) => Value::from(*v),
+            OwnedValue::U64(v) => Value::from(*v),
+            OwnedValue::I64(v) => Value::from(*v),
+            OwnedValue::U128(v) => Value::from(*v),
+            OwnedValue::I128(v) => Value::from(*v),
+            OwnedValue::F64(v) => Value::from(*v),
+            OwnedValue::Str(v) => Value::from(v.as_str()),
+        }
+    }
+}
+
+/// The key-values of a record, in their original order.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub(crate) struct KeyValues(Vec<(String, OwnedValue)>);
+
+impl KeyValues {
+    pub(crate) fn from_source(source: &dyn Source) -> Self {
+        struct Collect(Vec<(String, OwnedValue)>);
+
+        impl<'kvs> VisitSource<'kvs> for Collect {
+            fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), Error> {
+                self.0
+                    .push((key.as_str().to_owned(), OwnedValue::from_value(&value)));
+                Ok(())
+            }
+        }
+
+        let mut collect = Collect(Vec::new());
+        let _ = source.visit(&mut collect);
+        KeyValues(collect.0)
+    }
+
+    pub(crate) fn push(&mut self, key: String, value: &Value) {
+        self.0.push((key, OwnedValue::from_value(value)));
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&str, &OwnedValue)> {
+        self.0.iter().map(|(key, value)| (key.as_str(), value))
+    }
+}
+
+impl Source for KeyValues {
+    fn visit<'kvs>(&'kvs self, visitor: &mut dyn VisitSource<'kvs>) -> Result<(), Error> {
+        for (key, value) in &self.0 {
+            visitor.visit_pair(Key::from_str(key), value.to_value())?;
+        }
+        Ok(())
+    }
+}
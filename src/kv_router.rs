@@ -0,0 +1,130 @@
+//! Sending the records to different loggers by a key-value.
+
+use std::borrow::Cow;
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+
+use log::kv::Key;
+use log::{Log, Metadata, Record};
+
+use crate::SharedLog;
+
+/// A logger sending the records to different loggers by the value of a structured key.
+///
+/// For each record, the value of the configured key is found among its key-values (and
+/// formatted as a string, if it's not one already) and given to the resolver. The record goes
+/// to the logger returned by it, or to the default logger if the key is absent or the resolver
+/// returns `None`.
+///
+/// The resolver is called for each record. If resolving is expensive (eg. opens files), it's up
+/// to the resolver to cache the results; the router itself doesn't remember anything.
+///
+/// The metadata don't carry the key-values, so the router can't know where a record would go
+/// just from them. Its [`enabled`][Log::enabled] is therefore always `true`, and the final
+/// logger decides.
+///
+/// Requires the `kv` feature.
+///
+/// ```rust
+/// use std::collections::HashMap;
+/// use std::sync::{Arc, Mutex};
+///
+/// use log::{LevelFilter, Log, Metadata, Record};
+/// use log_reroute::{KvRouter, Reroute, SharedLog};
+///
+/// #[derive(Clone, Default)]
+/// struct Messages(Arc<Mutex<Vec<String>>>);
+///
+/// impl Log for Messages {
+///     fn enabled(&self, _: &Metadata) -> bool {
+///         true
+///     }
+///     fn log(&self, record: &Record) {
+///         self.0.lock().unwrap().push(record.args().to_string());
+///     }
+///     fn flush(&self) {}
+/// }
+///
+/// let acme = Messages::default();
+/// let tenants: HashMap<String, SharedLog> =
+///     [("acme".to_owned(), Arc::new(Box::new(acme.clone()) as Box<dyn Log>))].into();
+/// let other = Messages::default();
+///
+/// let reroute = Reroute::new();
+/// reroute.reroute(KvRouter::new(
+///     "tenant",
+///     move |tenant| tenants.get(tenant).cloned(),
+///     other.clone(),
+/// ));
+///
+/// log::set_max_level(LevelFilter::Info);
+/// log::info!(logger: &reroute, tenant = "acme"; "For acme");
+/// log::info!(logger: &reroute, tenant = "globex"; "Unknown tenant");
+/// log::info!(logger: &reroute, "No tenant");
+///
+/// assert_eq!(vec!["For acme"], *acme.0.lock().unwrap());
+/// assert_eq!(vec!["Unknown tenant", "No tenant"], *other.0.lock().unwrap());
+/// ```
+pub struct KvRouter<F> {
+    key: String,
+    resolver: F,
+    default: Box<dyn Log>,
+}
+
+impl<F> KvRouter<F>
+where
+    F: Fn(&str) -> Option<SharedLog> + Send + Sync,
+{
+    /// Creates the router by the key, the resolver and the default logger.
+    pub fn new<K, L>(key: K, resolver: F, default: L) -> Self
+    where
+        K: Into<String>,
+        L: Log + 'static,
+    {
+        Self {
+            key: key.into(),
+            resolver,
+            default: Box::new(default),
+        }
+    }
+
+    /// The key the records are routed by.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    fn resolve(&self, record: &Record) -> Option<SharedLog> {
+        let value = record.key_values().get(Key::from_str(&self.key))?;
+        let value = match value.to_borrowed_str() {
+            Some(value) => Cow::Borrowed(value),
+            None => Cow::Owned(value.to_string()),
+        };
+        (self.resolver)(&value)
+    }
+}
+
+impl<F> Debug for KvRouter<F> {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_struct("KvRouter")
+            .field("key", &self.key)
+            .finish()
+    }
+}
+
+impl<F> Log for KvRouter<F>
+where
+    F: Fn(&str) -> Option<SharedLog> + Send + Sync,
+{
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+    fn log(&self, record: &Record) {
+        match self.resolve(record) {
+            Some(log) => log.log(record),
+            None => self.default.log(record),
+        }
+    }
+    fn flush(&self) {
+        // The resolved loggers are not known here, the resolver owns them.
+        self.default.flush();
+    }
+}
@@ -0,0 +1,109 @@
+//! Layers in front of the slave of a [`Reroute`][crate::Reroute].
+
+use std::sync::Arc;
+
+use log::{Log, Metadata, Record};
+
+/// A layer sitting in front of whatever slave is installed in a [`Reroute`][crate::Reroute].
+///
+/// Unlike wrapping the slave, the layers stay in place when the slave is changed. See
+/// [`Reroute::layer`][crate::Reroute::layer].
+///
+/// Each method gets the rest of the pipeline as `next` (the other layers and finally the slave).
+/// The layer may pass the record on, modify it (by building a new one and passing that) or drop
+/// it (by not calling `next` at all). The default implementations just pass everything through.
+///
+/// ```rust
+/// use log::{Level, Log, Metadata, Record};
+/// use log_reroute::{LogLayer, Reroute};
+///
+/// /// Drops everything from hyper below warning.
+/// struct QuietHyper;
+///
+/// impl LogLayer for QuietHyper {
+///     fn enabled(&self, metadata: &Metadata, next: &dyn Log) -> bool {
+///         let noisy = metadata.target().starts_with("hyper") && metadata.level() > Level::Warn;
+///         !noisy && next.enabled(metadata)
+///     }
+///     fn log(&self, record: &Record, next: &dyn Log) {
+///         if self.enabled(record.metadata(), next) {
+///             next.log(record);
+///         }
+///     }
+/// }
+///
+/// let reroute = Reroute::new();
+/// let handle = reroute.layer(QuietHyper);
+/// // The layer is used here
+/// reroute.reroute(fern::Dispatch::new().chain(std::io::stderr()).into_log().1);
+/// // And here too
+/// reroute.reroute(fern::Dispatch::new().chain(std::io::stdout()).into_log().1);
+/// assert!(reroute.remove_layer(handle));
+/// ```
+pub trait LogLayer: Send + Sync {
+    /// Decides if records with this metadata would be logged.
+    fn enabled(&self, metadata: &Metadata, next: &dyn Log) -> bool {
+        next.enabled(metadata)
+    }
+
+    /// Handles a record.
+    fn log(&self, record: &Record, next: &dyn Log) {
+        next.log(record)
+    }
+
+    /// Flushes the pipeline.
+    fn flush(&self, next: &dyn Log) {
+        next.flush()
+    }
+}
+
+/// A handle to a layer, allowing its removal.
+///
+/// Returned by [`Reroute::layer`][crate::Reroute::layer], passed to
+/// [`Reroute::remove_layer`][crate::Reroute::remove_layer].
+#[derive(Debug, Eq, PartialEq)]
+pub struct LayerHandle(pub(crate) u64);
+
+pub(crate) type Layers = Vec<(u64, Arc<dyn LogLayer>)>;
+
+/// The rest of the pipeline, as seen from a layer.
+pub(crate) struct Pipeline<'a> {
+    layers: &'a [(u64, Arc<dyn LogLayer>)],
+    slave: &'a dyn Log,
+}
+
+impl<'a> Pipeline<'a> {
+    pub(crate) fn new(layers: &'a [(u64, Arc<dyn LogLayer>)], slave: &'a dyn Log) -> Self {
+        Self { layers, slave }
+    }
+
+    fn split(&self) -> Option<(&dyn LogLayer, Pipeline<'_>)> {
+        let ((_, first), rest) = self.layers.split_first()?;
+        let rest = Pipeline {
+            layers: rest,
+            slave: self.slave,
+        };
+        Some((&**first, rest))
+    }
+}
+
+impl Log for Pipeline<'_> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        match self.split() {
+            Some((layer, rest)) => layer.enabled(metadata, &rest),
+            None => self.slave.enabled(metadata),
+        }
+    }
+    fn log(&self, record: &Record) {
+        match self.split() {
+            Some((layer, rest)) => layer.log(record, &rest),
+            None => self.slave.log(record),
+        }
+    }
+    fn flush(&self) {
+        match self.split() {
+            Some((layer, rest)) => layer.flush(&rest),
+            None => self.slave.flush(),
+        }
+    }
+}
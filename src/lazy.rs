@@ -0,0 +1,94 @@
+//! Support for [`Reroute::reroute_lazy`][crate::Reroute::reroute_lazy].
+
+use std::cell::Cell;
+use std::sync::{Mutex, PoisonError};
+
+use log::{Log, Metadata, Record};
+use std::sync::OnceLock;
+
+use crate::Slave;
+
+thread_local! {
+    /// Is this thread currently constructing a lazy slave?
+    static CONSTRUCTING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Marks the current thread as constructing a lazy slave, until dropped (even by a panic).
+struct Constructing;
+
+impl Constructing {
+    fn enter() -> Self {
+        let _ = CONSTRUCTING.try_with(|c| c.set(true));
+        Constructing
+    }
+}
+
+impl Drop for Constructing {
+    fn drop(&mut self) {
+        let _ = CONSTRUCTING.try_with(|c| c.set(false));
+    }
+}
+
+type Init = Box<dyn FnOnce() -> Box<dyn Log> + Send>;
+
+/// A slave that gets constructed on the first use.
+///
+/// This forwards to the real slave once it exists, but the [`Reroute`][crate::Reroute] is
+/// expected to replace this shim by the real slave soon after that.
+pub(crate) struct LazySlave {
+    init: Mutex<Option<Init>>,
+    slave: OnceLock<Slave>,
+}
+
+impl LazySlave {
+    pub(crate) fn new<F>(init: F) -> Self
+    where
+        F: FnOnce() -> Box<dyn Log> + Send + 'static,
+    {
+        Self {
+            init: Mutex::new(Some(Box::new(init))),
+            slave: OnceLock::new(),
+        }
+    }
+
+    /// Returns the real slave, constructing it if necessary.
+    ///
+    /// Concurrent callers wait for the one constructing it. If called from within the constructor
+    /// (eg. the constructor logs something), `None` is returned instead of deadlocking.
+    pub(crate) fn force(&self) -> Option<&Slave> {
+        if CONSTRUCTING.try_with(Cell::get).unwrap_or(false) {
+            return self.slave.get();
+        }
+        let slave = self.slave.get_or_init(|| {
+            let init = self
+                .init
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .take()
+                .expect("Lazy slave constructor called twice");
+            let _constructing = Constructing::enter();
+            Slave::new(init())
+        });
+        Some(slave)
+    }
+}
+
+impl Log for LazySlave {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        // We don't know yet and if we said no, nobody would call log to construct it.
+        self.slave
+            .get()
+            .map(|slave| slave.enabled(metadata))
+            .unwrap_or(true)
+    }
+    fn log(&self, record: &Record) {
+        if let Some(slave) = self.force() {
+            slave.log(record);
+        }
+    }
+    fn flush(&self) {
+        if let Some(slave) = self.force() {
+            slave.flush();
+        }
+    }
+}
@@ -0,0 +1,153 @@
+//! Sending the records to different loggers by their level.
+
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::ops::{Bound, RangeBounds};
+
+use log::{Level, Log, Metadata, Record};
+
+/// Levels of a single route.
+type Levels = (Bound<Level>, Bound<Level>);
+
+/// A logger sending the records to different loggers by their level.
+///
+/// Each route covers a range of levels (note that the more severe levels are the smaller ones,
+/// so `Level::Error..=Level::Warn` are the two most severe levels). A record goes to all the
+/// routes covering its level; the routes may overlap, eg. to duplicate the warnings and errors
+/// into a small always-on alert sink while everything goes to the main logger. The records not
+/// covered by any route go to the [default][LevelRouter::default] logger, if any.
+///
+/// ```rust
+/// use std::sync::{Arc, Mutex};
+///
+/// use log::{Level, Log, Metadata, Record};
+/// use log_reroute::{LevelRouter, Reroute};
+///
+/// #[derive(Clone, Default)]
+/// struct Messages(Arc<Mutex<Vec<String>>>);
+///
+/// impl Log for Messages {
+///     fn enabled(&self, _: &Metadata) -> bool {
+///         true
+///     }
+///     fn log(&self, record: &Record) {
+///         self.0.lock().unwrap().push(record.args().to_string());
+///     }
+///     fn flush(&self) {}
+/// }
+///
+/// fn log(reroute: &Reroute, level: Level) {
+///     reroute.log(
+///         &Record::builder()
+///             .level(level)
+///             .args(format_args!("{}", level))
+///             .build(),
+///     );
+/// }
+///
+/// let alerts = Messages::default();
+/// let main = Messages::default();
+/// let debug = Messages::default();
+/// let reroute = Reroute::new();
+/// reroute.reroute(
+///     LevelRouter::new()
+///         .route(Level::Error..=Level::Warn, alerts.clone())
+///         .route(..=Level::Info, main.clone())
+///         .default(debug.clone()),
+/// );
+/// for level in [Level::Error, Level::Warn, Level::Info, Level::Debug, Level::Trace] {
+///     log(&reroute, level);
+/// }
+///
+/// assert_eq!(vec!["ERROR", "WARN"], *alerts.0.lock().unwrap());
+/// assert_eq!(vec!["ERROR", "WARN", "INFO"], *main.0.lock().unwrap());
+/// assert_eq!(vec!["DEBUG", "TRACE"], *debug.0.lock().unwrap());
+/// ```
+pub struct LevelRouter {
+    routes: Vec<(Levels, Box<dyn Log>)>,
+    default: Option<Box<dyn Log>>,
+}
+
+impl LevelRouter {
+    /// Creates a router without any routes, logging nowhere.
+    pub fn new() -> Self {
+        Self {
+            routes: Vec::new(),
+            default: None,
+        }
+    }
+
+    /// Adds a route for the range of levels.
+    pub fn route<R, L>(mut self, levels: R, log: L) -> Self
+    where
+        R: RangeBounds<Level>,
+        L: Log + 'static,
+    {
+        let levels = (levels.start_bound().cloned(), levels.end_bound().cloned());
+        self.routes.push((levels, Box::new(log)));
+        self
+    }
+
+    /// Sets the logger for the records not covered by any route.
+    ///
+    /// Replaces the previous one, if set.
+    pub fn default<L: Log + 'static>(mut self, log: L) -> Self {
+        self.default = Some(Box::new(log));
+        self
+    }
+
+    /// The loggers the records of this level go to.
+    fn targets(&self, level: Level) -> impl Iterator<Item = &dyn Log> {
+        let mut routed = self
+            .routes
+            .iter()
+            .filter(move |(levels, _)| levels.contains(&level))
+            .map(|(_, log)| &**log)
+            .peekable();
+        let default = if routed.peek().is_none() {
+            self.default.as_deref()
+        } else {
+            None
+        };
+        routed.chain(default)
+    }
+}
+
+impl Default for LevelRouter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for LevelRouter {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        let levels = self
+            .routes
+            .iter()
+            .map(|(levels, _)| levels)
+            .collect::<Vec<_>>();
+        fmt.debug_struct("LevelRouter")
+            .field("routes", &levels)
+            .field("default", &self.default.is_some())
+            .finish()
+    }
+}
+
+impl Log for LevelRouter {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.targets(metadata.level())
+            .any(|log| log.enabled(metadata))
+    }
+    fn log(&self, record: &Record) {
+        for log in self.targets(record.level()) {
+            if log.enabled(record.metadata()) {
+                log.log(record);
+            }
+        }
+    }
+    fn flush(&self) {
+        let routes = self.routes.iter().map(|(_, log)| &**log);
+        for log in routes.chain(self.default.as_deref()) {
+            log.flush();
+        }
+    }
+}
@@ -15,38 +15,233 @@
 //! This may be useful if you want to log to `stderr` before you know where the main logs will go.
 //!
 //! ```rust
-//! use fern::Dispatch;
+//! # #![cfg_attr(not(feature = "global"), allow(unused))]
 //! use log::{info, LevelFilter};
+//! use log_reroute::WriteLog;
 //!
+//! # #[cfg(feature = "global")]
 //! fn main() {
-//!     // Enable logging of Debug and more severe messages.
-//!     log::set_max_level(LevelFilter::Debug);
 //!     info!("This log message goes nowhere");
-//!     log_reroute::init().unwrap();
+//!     // Install and enable logging of Debug and more severe messages.
+//!     log_reroute::init_at(LevelFilter::Debug).unwrap();
 //!     info!("Still goes nowhere");
 //!     // Log to stderr
-//!     let early_logger = Dispatch::new().chain(std::io::stderr()).into_log().1;
-//!     log_reroute::reroute_boxed(early_logger);
+//!     log_reroute::reroute_stderr(LevelFilter::Debug);
 //!     info!("This one goes to stderr");
 //!     // Load file name from config and log to that file
 //!     let file = tempfile::tempfile().unwrap();
-//!     let logger = Dispatch::new().chain(file).into_log().1;
-//!     log_reroute::reroute_boxed(logger);
+//!     log_reroute::reroute(WriteLog::new(file));
 //!     info!("And this one to the file");
 //!     // Stop logging
 //!     log_reroute::reroute(log_reroute::Dummy);
 //! }
+//! # #[cfg(not(feature = "global"))]
+//! # fn main() {}
 //! ```
+//!
+//! # Features
+//!
+//! * `global` (on by default): The global `REROUTE` instance and the functions manipulating it.
+//!   Without it, only the types are provided, which is useful for libraries that must not install
+//!   global loggers.
+//! * `tokio`: Support for async code running on tokio.
+//! * `signal`: Reacting to signals on unix (see `Reroute::on_sighup`).
+//! * `serde`: The `Config`, a declarative configuration loadable by serde.
+//! * `watch`: Re-applying the `Config` when its file changes (implies `serde`).
+//! * `kv`: Keeping the structured key-values of the records when copying them (eg. in the
+//!   [`Buffer`]), routing by them (`KvRouter`) and adding them (`Enrich`).
+//! * `regex`: Scrubbing the secrets matching regular expressions (`Redact::with_regex`).
+//! * `clap`: Using the [`Verbosity`] as part of a clap parser.
+//! * `backtrace`: Backtraces in the records about panics (see [`Reroute::install_panic_hook`]).
+
+use std::any::{self, Any, TypeId};
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError, Weak};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use arc_swap::ArcSwapOption;
+use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
 
-use std::sync::Arc;
+mod announce;
+mod async_log;
+mod buffer;
+mod builder;
+mod chain;
+#[cfg(feature = "serde")]
+mod config;
+mod counting;
+mod cycle;
+mod dedup;
+mod displace;
+mod dispose;
+mod dyn_fanout;
+#[cfg(feature = "kv")]
+mod enrich;
+mod error_trigger;
+mod escalate;
+#[cfg(feature = "global")]
+mod exit;
+mod fail_safe;
+mod fallback;
+mod fanout;
+mod file_log;
+mod filter;
+mod flush_on;
+mod fn_log;
+mod format;
+#[cfg(feature = "global")]
+mod global;
+mod handle;
+mod history;
+mod hook;
+mod json;
+#[cfg(feature = "kv")]
+mod kv;
+#[cfg(feature = "kv")]
+mod kv_router;
+mod layer;
+mod lazy;
+mod level_router;
+mod local;
+mod logfmt;
+mod non_blocking;
+mod once;
+mod panic;
+mod pattern;
+mod pause;
+mod periodic;
+mod rate_limit;
+mod record;
+mod redact;
+#[cfg(feature = "global")]
+mod registry;
+mod sample;
+mod scope;
+#[cfg(all(unix, feature = "signal"))]
+mod signal;
+mod static_buffer;
+mod stats;
+mod stderr;
+mod target_router;
+#[cfg(feature = "tokio")]
+mod task;
+mod thread_info;
+mod transform;
+mod truncate;
+mod typed;
+mod verbosity;
+#[cfg(feature = "watch")]
+mod watch;
+mod watchdog;
+mod weak;
+mod worker;
+mod write_log;
 
-use arc_swap::ArcSwap;
-use log::{Log, Metadata, Record, SetLoggerError};
-use once_cell::sync::Lazy;
+use announce::Announcement;
+pub use announce::SWAP_TARGET;
+pub use async_log::{Async, WorkerGuard, DEFAULT_ASYNC_CAPACITY};
+pub use buffer::{Buffer, Overflow, DEFAULT_BUFFER_BYTES, DEFAULT_BUFFER_RECORDS};
+pub use builder::RerouteBuilder;
+pub use chain::Chain;
+#[cfg(feature = "serde")]
+pub use config::{ApplyError, Config, Destination};
+pub use counting::CountingDummy;
+pub use cycle::Reentry;
+pub use dedup::Dedup;
+use displace::Handoff;
+pub use dispose::join_disposal;
+pub use dyn_fanout::{DynFanout, SinkId};
+#[cfg(feature = "kv")]
+pub use enrich::Enrich;
+pub use error_trigger::{ErrorTrigger, ErrorTriggerHandle};
+pub use escalate::Escalate;
+#[cfg(feature = "global")]
+pub use exit::flush_on_main_thread_exit;
+pub use fail_safe::FailSafe;
+pub use fallback::StderrFallback;
+pub use fanout::Fanout;
+pub use file_log::{FileLog, FileOptions};
+use filter::Filters;
+pub use filter::{FilterError, FilterSpec};
+pub use flush_on::FlushOn;
+pub use fn_log::FnLog;
+pub use format::{ColorChoice, Format, Plain};
+#[cfg(feature = "global")]
+pub use global::{
+    guarded, history, init, init_at, init_from_env, init_with, init_with_boxed, init_with_level,
+    install_panic_hook, is_installed, reload_from_env, reroute, reroute_boxed, reroute_if_unset,
+    reroute_stderr, reroute_to_file, set_rest_state, swap, try_init, REROUTE,
+};
+pub use handle::RerouteHandle;
+use history::History;
+pub use history::{SwapEvent, DEFAULT_HISTORY_CAPACITY};
+pub use hook::HookHandle;
+use hook::Hooks;
+pub use json::Json;
+use layer::{Layers, Pipeline};
+use lazy::LazySlave;
+use pause::Held;
+
+#[cfg(feature = "kv")]
+pub use kv_router::KvRouter;
+pub use layer::{LayerHandle, LogLayer};
+pub use level_router::LevelRouter;
+pub use logfmt::Logfmt;
+pub use non_blocking::{Backpressure, NonBlockingFileLog};
+pub use once::{Once, DEFAULT_ONCE_KEYS};
+pub use pattern::{Pattern, PatternError};
+pub use pause::{PauseGuard, DEFAULT_PAUSE_CAPACITY};
+#[cfg(feature = "global")]
+pub use periodic::periodic_flush;
+pub use periodic::FlushHandle;
+pub use rate_limit::{RateLimit, DEFAULT_RATE_LIMIT_CALLSITES};
+pub use record::OwnedRecord;
+#[cfg(feature = "serde")]
+pub use record::{dump_json, read_json};
+pub use redact::{Redact, REDACTED};
+#[cfg(feature = "global")]
+pub use registry::{named, names};
+pub use sample::Sample;
+#[cfg(all(unix, feature = "signal"))]
+pub use signal::{current_level, SignalHandle};
+#[cfg(all(unix, feature = "signal", feature = "global"))]
+pub use signal::{on_sighup, verbosity_signals};
+use static_buffer::Early;
+pub use static_buffer::StaticBuffer;
+use stats::Counters;
+pub use stats::Stats;
+pub use stderr::Stderr;
+use stderr::StderrLog;
+pub use target_router::{SharedLog, TargetRouter, TargetRoutes};
+#[cfg(all(feature = "tokio", feature = "global"))]
+pub use task::{shutdown_async, with_task_logger};
+#[cfg(feature = "kv")]
+pub use thread_info::ThreadInfo;
+pub use transform::Transform;
+pub use truncate::Truncate;
+pub use typed::TypedReroute;
+#[cfg(feature = "global")]
+pub use verbosity::verbosity;
+pub use verbosity::Verbosity;
+#[cfg(all(feature = "watch", feature = "global"))]
+pub use watch::watch_config;
+#[cfg(feature = "watch")]
+pub use watch::WatchHandle;
+pub use watchdog::{Watchdog, WatchdogStats};
+pub use weak::WeakLog;
+pub use worker::{set_shutdown_timeout, shutdown, Worker, DEFAULT_SHUTDOWN_TIMEOUT};
+pub use write_log::WriteLog;
 
 /// A logger that doesn't log.
 ///
 /// This is used to stub out the reroute in case no other log is set.
+#[derive(Clone, Copy, Debug, Default)]
 pub struct Dummy;
 
 impl Log for Dummy {
@@ -57,6 +252,453 @@ impl Log for Dummy {
     fn flush(&self) {}
 }
 
+type Slave = Arc<Box<dyn Log>>;
+
+/// What the [`Reroute`] knows about its current slave.
+///
+/// Such information can't be extracted from the `dyn Log` itself, therefore it is captured at the
+/// time the slave is installed.
+#[derive(Clone)]
+struct SlaveInfo {
+    type_name: &'static str,
+    dummy: bool,
+    /// Is this the rest state slave (see [`Reroute::set_rest_state`])?
+    rest: bool,
+    /// Is this the early buffer of [`Reroute::const_with_buffer`], before any real slave?
+    early: bool,
+    last_swap: Option<SystemTime>,
+    /// The slave itself, for downcasting.
+    ///
+    /// Available only if it was passed by a concrete type.
+    any: Option<Arc<dyn Any + Send + Sync>>,
+}
+
+impl SlaveInfo {
+    fn is<T: Any>(&self) -> bool {
+        self.any.as_ref().map(|any| any.is::<T>()).unwrap_or(false)
+    }
+
+    /// The info of the slave of a new [`Reroute`].
+    const fn initial() -> Self {
+        Self {
+            type_name: "log_reroute::Dummy",
+            dummy: true,
+            rest: false,
+            early: false,
+            last_swap: None,
+            any: None,
+        }
+    }
+
+    fn boxed() -> Self {
+        Self {
+            type_name: "boxed",
+            dummy: false,
+            rest: false,
+            early: false,
+            last_swap: None,
+            any: None,
+        }
+    }
+}
+
+/// The static initial slave, as a slave.
+struct EarlyRef(&'static dyn Early);
+
+impl Log for EarlyRef {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.0.as_log().enabled(metadata)
+    }
+    fn log(&self, record: &Record) {
+        self.0.as_log().log(record)
+    }
+    fn flush(&self) {
+        self.0.as_log().flush()
+    }
+}
+
+/// Shares a concretely typed slave between the [`Reroute`] and whoever wants to access it.
+struct Shared<L>(Arc<L>);
+
+impl<L: Log> Log for Shared<L> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.0.enabled(metadata)
+    }
+    fn log(&self, record: &Record) {
+        self.0.log(record)
+    }
+    fn flush(&self) {
+        self.0.flush()
+    }
+}
+
+/// Prepares a slave passed by its concrete type.
+fn typed<L: Log + 'static>(log: L) -> (Slave, SlaveInfo) {
+    shared(Arc::new(log))
+}
+
+fn shared<L: Log + 'static>(log: Arc<L>) -> (Slave, SlaveInfo) {
+    let info = SlaveInfo {
+        type_name: any::type_name::<L>(),
+        dummy: TypeId::of::<L>() == TypeId::of::<Dummy>(),
+        rest: false,
+        early: false,
+        last_swap: None,
+        any: Some(Arc::clone(&log) as Arc<dyn Any + Send + Sync>),
+    };
+    (Arc::new(Box::new(Shared(log))), info)
+}
+
+/// The slave standing for the `None` in a [`Reroute`] that was never changed.
+///
+/// Shared by all such reroutes.
+fn initial() -> &'static (Slave, SlaveInfo) {
+    static INITIAL: OnceLock<(Slave, SlaveInfo)> = OnceLock::new();
+    INITIAL.get_or_init(|| typed(Dummy))
+}
+
+fn initial_slave() -> Slave {
+    Arc::clone(&initial().0)
+}
+
+/// Waits until the given slave is the last reference, then flushes and drops it.
+///
+/// Returns the slave back if the deadline passes first.
+fn wait_released(slave: Slave, deadline: Option<Instant>) -> Result<(), Slave> {
+    if Arc::ptr_eq(&slave, &initial().0) {
+        // Kept alive forever, but nobody else can use it any more.
+        slave.flush();
+        return Ok(());
+    }
+    let last = wait_unique(slave, deadline)?;
+    last.flush();
+    Ok(())
+}
+
+/// Waits until the given slave is the last reference and takes it out.
+///
+/// Returns the slave back if the deadline passes first.
+fn wait_unique(mut slave: Slave, deadline: Option<Instant>) -> Result<Box<dyn Log>, Slave> {
+    // Note that arc-swap converts all the outstanding loads into full references when swapping
+    // the pointer out, so the strong count includes the threads in the middle of logging.
+    let mut sleep = Duration::from_micros(50);
+    loop {
+        let shared = match Arc::try_unwrap(slave) {
+            Ok(last) => return Ok(last),
+            Err(shared) => shared,
+        };
+        let now = Instant::now();
+        let mut sleep_for = sleep;
+        if let Some(deadline) = deadline {
+            if now >= deadline {
+                return Err(shared);
+            }
+            sleep_for = sleep_for.min(deadline - now);
+        }
+        slave = shared;
+        thread::sleep(sleep_for);
+        sleep = (sleep * 2).min(Duration::from_millis(10));
+    }
+}
+
+/// What to do with the old slave when replacing it.
+///
+/// The `reroute` methods flush the old slave by default ([`FlushOld::Sync`]). This can be
+/// changed by [`Reroute::reroute_boxed_with`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum FlushOld {
+    /// Flush the old slave in the thread doing the reroute, before releasing it.
+    ///
+    /// Ensures all the buffered records reach their destination by the time the reroute
+    /// finishes, but the reroute can take long if the destination is slow or hangs.
+    #[default]
+    Sync,
+
+    /// Don't flush the old slave, only drop it.
+    ///
+    /// This is useful when rerouting away from a broken destination (eg. a network sink with the
+    /// other end down), where flushing could block for a long time. Whatever the old slave still
+    /// has buffered may be lost, unless it flushes itself in its own `Drop`. Note that the old
+    /// slave is dropped only once the last thread still logging into it lets go of it.
+    Skip,
+
+    /// Flush the old slave in a background thread, before releasing it.
+    ///
+    /// This takes the cost of flushing out of the thread doing the reroute. The background
+    /// [`Worker`] is started on the first use and shared by all the reroutes, processing the old
+    /// slaves in batches.
+    ///
+    /// To make sure the data buffered in the old slaves reaches its destination, call
+    /// [`join_disposal`] before the process exits.
+    Background,
+}
+
+impl FlushOld {
+    fn dispose(self, old: Slave) {
+        match self {
+            FlushOld::Sync => old.flush(),
+            FlushOld::Skip => (),
+            FlushOld::Background => dispose::background(old),
+        }
+    }
+}
+
+/// When does a replacement of the slave happen.
+enum Condition<'a> {
+    Always,
+    /// Only if the current slave is this one.
+    Slave(&'a Slave),
+    /// Only if the current generation is this one.
+    Generation(u64),
+    /// Only if the current slave is the dummy.
+    Unset,
+}
+
+/// The result of replacing a slave.
+struct Displaced {
+    /// The previous slave.
+    slave: Slave,
+    /// Info of the previous slave.
+    info: SlaveInfo,
+    /// The generation of the newly installed slave.
+    generation: u64,
+}
+
+/// A token to revert a change done by [`Reroute::reroute_tracked`].
+///
+/// See [`Reroute::revert_if_current`].
+#[must_use = "Dropping the token makes the change permanent"]
+pub struct SwapToken {
+    previous: Slave,
+    info: SlaveInfo,
+    generation: u64,
+}
+
+/// The part of [`Reroute`] touched only when changing the slave.
+struct State {
+    info: SlaveInfo,
+    /// Slaves (with their info) buried by [`Reroute::push`].
+    stack: Vec<(Slave, SlaveInfo)>,
+    /// Source of the IDs for layers and hooks.
+    next_id: u64,
+    /// What [`Reroute::clear`] installs, `None` for [`Dummy`].
+    rest: Option<(Slave, SlaveInfo)>,
+    /// To be sent once the lock is released.
+    announcements: Vec<Announcement>,
+    history: History,
+    hooks: Hooks,
+    /// Swaps to tell the hooks about once the lock is released.
+    fired: Vec<SwapEvent>,
+    /// Displaced slaves to tell about it once the lock is released.
+    handoffs: Vec<Handoff>,
+}
+
+impl State {
+    fn rest(&self) -> (Slave, SlaveInfo) {
+        let (slave, mut info) = self.rest.clone().unwrap_or_else(|| typed(Dummy));
+        info.rest = true;
+        (slave, info)
+    }
+
+    fn at_rest(&self) -> bool {
+        self.info.rest || self.info.dummy || self.info.early
+    }
+}
+
+/// The locked [`State`].
+///
+/// Sends the announcements and calls the hooks of the swaps done while locked once the lock is
+/// released.
+struct StateGuard<'a>(Option<MutexGuard<'a, State>>);
+
+impl Deref for StateGuard<'_> {
+    type Target = State;
+    fn deref(&self) -> &State {
+        self.0.as_ref().expect("Used after drop")
+    }
+}
+
+impl DerefMut for StateGuard<'_> {
+    fn deref_mut(&mut self) -> &mut State {
+        self.0.as_mut().expect("Used after drop")
+    }
+}
+
+impl Drop for StateGuard<'_> {
+    fn drop(&mut self) {
+        let mut guard = self.0.take().expect("Dropped twice");
+        if guard.announcements.is_empty() && guard.fired.is_empty() && guard.handoffs.is_empty() {
+            return;
+        }
+        let handoffs = mem::take(&mut guard.handoffs);
+        let announcements = mem::take(&mut guard.announcements);
+        let fired = mem::take(&mut guard.fired);
+        let hooks = if fired.is_empty() {
+            Vec::new()
+        } else {
+            guard.hooks.clone()
+        };
+        // The slaves and hooks may want to do something with the reroute, so unlock first.
+        drop(guard);
+        for handoff in handoffs {
+            handoff();
+        }
+        for announcement in announcements {
+            announcement.emit();
+        }
+        hook::fire(&hooks, &fired);
+    }
+}
+
+/// A handle to a slave installed by [`Reroute::reroute_handle`].
+///
+/// This gives access to the concrete type of the slave (through [`Deref`]), as long as the handle
+/// is kept around. It doesn't prevent the slave from being replaced, only keeps it alive.
+pub struct SlaveHandle<L> {
+    log: Arc<L>,
+    // Only to recognize the slave, doesn't keep it installed in any way. As the weak pointer
+    // prevents the allocation from going away (even if the slave is dropped), its address can't
+    // be reused by another slave.
+    slave: Weak<Box<dyn Log>>,
+}
+
+impl<L> SlaveHandle<L> {
+    /// Is this slave still the one installed in the given `reroute`?
+    pub fn is_current(&self, reroute: &Reroute) -> bool {
+        match &*reroute.inner.load() {
+            Some(current) => ptr::eq(Arc::as_ptr(current), self.slave.as_ptr()),
+            None => false,
+        }
+    }
+}
+
+impl<L> Clone for SlaveHandle<L> {
+    fn clone(&self) -> Self {
+        Self {
+            log: Arc::clone(&self.log),
+            slave: Weak::clone(&self.slave),
+        }
+    }
+}
+
+impl<L> Deref for SlaveHandle<L> {
+    type Target = L;
+    fn deref(&self) -> &L {
+        &self.log
+    }
+}
+
+/// A handle to a time-limited slave installed by [`Reroute::reroute_for`].
+///
+/// Dropping the handle doesn't affect the timer.
+pub struct TimedHandle {
+    reroute: &'static Reroute,
+    token: Arc<Mutex<Option<SwapToken>>>,
+    cancel: Sender<()>,
+}
+
+impl TimedHandle {
+    fn take_token(&self) -> Option<SwapToken> {
+        // Wake the timer thread up so it terminates; either way, the token is gone.
+        let _ = self.cancel.send(());
+        self.token
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+    }
+
+    /// Cancels the revert, keeping the slave installed.
+    ///
+    /// Returns `false` if it is too late (the revert already happened).
+    pub fn keep(self) -> bool {
+        self.take_token().is_some()
+    }
+
+    /// Reverts to the previous slave right away, without waiting for the timer.
+    ///
+    /// Returns if the revert happened. It doesn't if the timer already did it or if someone else
+    /// changed the slave in the meantime.
+    pub fn revert_now(self) -> bool {
+        match self.take_token() {
+            Some(token) => self.reroute.revert_if_current(token),
+            None => false,
+        }
+    }
+}
+
+/// Restores the previous slave of a [`Reroute`] on drop.
+///
+/// See [`Reroute::guard`].
+#[must_use = "The previous logger is restored right away if the guard is dropped"]
+pub struct RerouteGuard<'a> {
+    reroute: &'a Reroute,
+    previous: Option<(Slave, SlaveInfo)>,
+    /// The max level to put back, if it was changed.
+    level: Option<LevelFilter>,
+}
+
+impl Drop for RerouteGuard<'_> {
+    fn drop(&mut self) {
+        if let Some((log, info)) = self.previous.take() {
+            if self.reroute.can_restore(&info) {
+                let old = self
+                    .reroute
+                    .replace_unchecked(Condition::Always, log, info)
+                    .unwrap_or_else(|_| unreachable!("Unconditional replace failed"));
+                FlushOld::default().dispose(old.slave);
+            }
+        }
+        if let Some(level) = self.level {
+            log::set_max_level(level);
+        }
+    }
+}
+
+/// The outcome of [`Reroute::try_install`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InitResult {
+    /// The reroute was installed by this call.
+    Installed,
+    /// The same reroute was installed before, changing its slave works.
+    AlreadyReroute,
+    /// Some other logger is installed, changing the slave has no effect on the logging.
+    Foreign,
+}
+
+impl InitResult {
+    /// Is the reroute installed (either now or previously)?
+    pub fn is_reroute(self) -> bool {
+        self != InitResult::Foreign
+    }
+}
+
+/// A snapshot of the state of a [`Reroute`].
+///
+/// Returned by [`Reroute::status`].
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct RerouteStatus {
+    /// Is the slave the [`Dummy`] stub?
+    ///
+    /// If so, all the log messages are thrown away.
+    pub dummy: bool,
+
+    /// Type name of the current slave.
+    ///
+    /// The type is known only if the slave was passed by its concrete type (eg. through
+    /// [`reroute`][Reroute::reroute]). Slaves passed as `Box<dyn Log>` are reported as `"boxed"`.
+    pub type_name: &'static str,
+
+    /// When the slave was last changed.
+    ///
+    /// This is `None` if the slave wasn't changed since the [`Reroute`] was created.
+    pub last_swap: Option<SystemTime>,
+
+    /// The [generation][Reroute::generation] of the current slave.
+    pub generation: u64,
+}
+
 /// A logging proxy.
 ///
 /// This logger forwards all calls to currently configured slave logger.
@@ -65,7 +707,45 @@ impl Log for Dummy {
 /// than using a mutex, the performance should be more predictable and stable in face of contention
 /// from multiple threads. This assumes the slave logger also doesn't lock.
 pub struct Reroute {
-    inner: ArcSwap<Box<dyn Log>>,
+    // None is the Dummy, so it can be created without allocation.
+    inner: ArcSwapOption<Box<dyn Log>>,
+    // Only the swapping paths lock this, the logging itself stays lock-less. Holding it while
+    // swapping also keeps the info consistent with the slave in `inner`.
+    state: Mutex<State>,
+    generation: AtomicU64,
+    // None if there are no layers. The flag mirrors that and is cheaper to check on the fast path.
+    layers: ArcSwapOption<Layers>,
+    has_layers: AtomicBool,
+    // Is there something to be done on the next log call? Kept separately from whatever needs to
+    // be done so the usual path is only a cheap check.
+    deferred: AtomicBool,
+    /// Slave set by [`Reroute::schedule_reroute`], to be installed on the next log call.
+    pending: ArcSwapOption<Box<dyn Log>>,
+    /// Identifies this reroute's [thread overrides][Reroute::set_thread_override].
+    ///
+    /// Assigned on first use, 0 means no ID yet.
+    local_id: AtomicU64,
+    /// Set while [paused][Reroute::pause], the records are held in the buffer.
+    paused: AtomicBool,
+    buffer: Mutex<Held>,
+    announce: AtomicBool,
+    // None if there are no filters. The flag mirrors that and is cheaper to check on the fast path.
+    filters: ArcSwapOption<Filters>,
+    has_filters: AtomicBool,
+    /// The most verbose level let through, as `LevelFilter as usize`.
+    gate: AtomicUsize,
+    /// Stands for the `None` in `inner` instead of the `Dummy`, if set.
+    early: Option<&'static dyn Early>,
+    /// The `early` as a slave, if anyone holds it (not held here, the slave can be waited for).
+    early_shared: Mutex<Weak<Box<dyn Log>>>,
+    /// The [`Reentry`] policy, as its `u8`.
+    reentry: AtomicU8,
+    reentries: AtomicU64,
+    /// Is the [`counters`][Reroute::stats] on?
+    counting: AtomicBool,
+    counters: Counters,
+    /// Mirrors the `dummy` of the current slave's info, for counting the discarded records.
+    dummy: AtomicBool,
 }
 
 impl Reroute {
@@ -74,7 +754,312 @@ impl Reroute {
     /// No destination is set yet (it's sent to the [`Dummy`] instance), therefore all log messages
     /// are thrown away.
     pub fn new() -> Self {
-        Default::default()
+        Self::const_new()
+    }
+
+    /// Creates a new [`Reroute`] logger in a `const` context.
+    ///
+    /// This is the same as [`new`][Reroute::new], but allows declaring the reroute as a `static`
+    /// without any lazy initialization.
+    ///
+    /// ```rust
+    /// use log_reroute::{Dummy, Reroute};
+    ///
+    /// static MY_REROUTE: Reroute = Reroute::const_new();
+    ///
+    /// MY_REROUTE.reroute(Dummy);
+    /// ```
+    pub const fn const_new() -> Self {
+        Self::with_parts(ArcSwapOption::const_empty(), SlaveInfo::initial())
+    }
+
+    /// Creates a new [`Reroute`] logger, already pointing to the given slave.
+    ///
+    /// This is equivalent to [`new`][Reroute::new] followed by [`reroute`][Reroute::reroute], but
+    /// there's no time during which the logs would be thrown away.
+    pub fn with<L: Log + 'static>(log: L) -> Self {
+        let (log, info) = typed(log);
+        Self::with_info(log, info)
+    }
+
+    /// Creates a new [`Reroute`] logger with an already boxed slave.
+    ///
+    /// ```rust
+    /// use fern::Dispatch;
+    /// use log_reroute::Reroute;
+    ///
+    /// let logger = Dispatch::new().chain(std::io::stderr()).into_log().1;
+    /// let reroute = Reroute::with_boxed(logger);
+    /// # drop(reroute);
+    /// ```
+    pub fn with_boxed(log: Box<dyn Log>) -> Self {
+        Self::from(Arc::new(log))
+    }
+
+    fn with_info(log: Arc<Box<dyn Log>>, info: SlaveInfo) -> Self {
+        Self::with_parts(ArcSwapOption::new(Some(log)), info)
+    }
+
+    const fn with_parts(inner: ArcSwapOption<Box<dyn Log>>, info: SlaveInfo) -> Self {
+        let dummy = info.dummy;
+        Self {
+            inner,
+            state: Mutex::new(State {
+                info,
+                stack: Vec::new(),
+                next_id: 0,
+                rest: None,
+                announcements: Vec::new(),
+                handoffs: Vec::new(),
+                history: History::new(),
+                hooks: Vec::new(),
+                fired: Vec::new(),
+            }),
+            generation: AtomicU64::new(0),
+            layers: ArcSwapOption::const_empty(),
+            has_layers: AtomicBool::new(false),
+            deferred: AtomicBool::new(false),
+            pending: ArcSwapOption::const_empty(),
+            local_id: AtomicU64::new(0),
+            paused: AtomicBool::new(false),
+            buffer: Mutex::new(Held::new()),
+            announce: AtomicBool::new(false),
+            filters: ArcSwapOption::const_empty(),
+            has_filters: AtomicBool::new(false),
+            gate: AtomicUsize::new(LevelFilter::Trace as usize),
+            early: None,
+            early_shared: Mutex::new(Weak::new()),
+            reentry: AtomicU8::new(Reentry::Stderr.to_u8()),
+            reentries: AtomicU64::new(0),
+            counting: AtomicBool::new(false),
+            counters: Counters::new(),
+            dummy: AtomicBool::new(dummy),
+        }
+    }
+
+    /// Creates a reroute starting with the static buffer, in a const context.
+    ///
+    /// Until the first slave is installed, the records go into the buffer. The first slave then
+    /// gets all of them. See [`StaticBuffer`] for an example.
+    ///
+    /// The buffer doesn't count as a slave, the reroute is not [active][Reroute::is_active] and
+    /// is [unset][Reroute::reroute_if_unset] until the first real one comes.
+    ///
+    /// ```rust
+    /// use log::Record;
+    /// use log_reroute::{FnLog, Reroute, StaticBuffer};
+    ///
+    /// static EARLY: StaticBuffer<1024> = StaticBuffer::new();
+    /// static REROUTE: Reroute = Reroute::const_with_buffer(&EARLY);
+    ///
+    /// assert!(!REROUTE.is_active());
+    /// assert!(REROUTE.reroute_if_unset(FnLog::new(|_: &Record| ())));
+    /// assert!(REROUTE.is_active());
+    /// assert!(!REROUTE.reroute_if_unset(FnLog::new(|_: &Record| ())));
+    /// ```
+    pub const fn const_with_buffer<const BYTES: usize>(
+        buffer: &'static StaticBuffer<BYTES>,
+    ) -> Self {
+        let mut info = SlaveInfo::initial();
+        info.type_name = "log_reroute::StaticBuffer";
+        info.dummy = false;
+        info.early = true;
+        let mut reroute = Self::with_parts(ArcSwapOption::const_empty(), info);
+        reroute.early = Some(buffer);
+        reroute
+    }
+
+    /// The logger standing for the `None` in `inner`.
+    fn initial_log(&self) -> &dyn Log {
+        match self.early {
+            Some(early) => early.as_log(),
+            None => &Dummy,
+        }
+    }
+
+    fn current(&self) -> Slave {
+        self.inner
+            .load_full()
+            .unwrap_or_else(|| self.initial_current())
+    }
+
+    /// The slave standing for the `None` in `inner`.
+    fn initial_current(&self) -> Slave {
+        let early = match self.early {
+            Some(early) => early,
+            None => return initial_slave(),
+        };
+        let mut shared = self
+            .early_shared
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        shared.upgrade().unwrap_or_else(|| {
+            let slave: Slave = Arc::new(Box::new(EarlyRef(early)));
+            *shared = Arc::downgrade(&slave);
+            slave
+        })
+    }
+
+    fn is_current(&self, slave: &Slave) -> bool {
+        match (&*self.inner.load(), self.early) {
+            (Some(current), _) => Arc::ptr_eq(current, slave),
+            (None, None) => Arc::ptr_eq(&initial().0, slave),
+            (None, Some(_)) => {
+                let shared = self
+                    .early_shared
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner);
+                ptr::eq(shared.as_ptr(), Arc::as_ptr(slave))
+            }
+        }
+    }
+
+    fn lock_state(&self) -> StateGuard<'_> {
+        // We don't call out to user code while holding the lock and the state is always
+        // consistent, so poisoning is of no concern.
+        StateGuard(Some(
+            self.state.lock().unwrap_or_else(PoisonError::into_inner),
+        ))
+    }
+
+    /// The single place where the slave gets replaced.
+    ///
+    /// The replacement happens only if the condition holds. Returns the previous slave on success
+    /// and the current one on failure.
+    fn replace(
+        &self,
+        condition: Condition,
+        log: Arc<Box<dyn Log>>,
+        info: SlaveInfo,
+    ) -> Result<Displaced, Slave> {
+        self.check_cycle(&info);
+        self.replace_unchecked(condition, log, info)
+    }
+
+    /// Like [`replace`][Reroute::replace], for the callers that checked the cycles already.
+    fn replace_unchecked(
+        &self,
+        condition: Condition,
+        log: Arc<Box<dyn Log>>,
+        info: SlaveInfo,
+    ) -> Result<Displaced, Slave> {
+        let mut state = self.lock_state();
+        // All writers hold the lock, so nobody can change the slave between the check and swap.
+        let holds = match condition {
+            Condition::Always => true,
+            Condition::Slave(expected) => self.is_current(expected),
+            Condition::Generation(expected) => self.generation.load(Ordering::Relaxed) == expected,
+            Condition::Unset => state.at_rest(),
+        };
+        if holds {
+            Ok(self.replace_locked(&mut state, log, info))
+        } else {
+            Err(self.current())
+        }
+    }
+
+    /// Refuses slaves that would forward back into us.
+    ///
+    /// Must be called without holding the lock, it looks into the other reroutes.
+    fn check_cycle(&self, info: &SlaveInfo) {
+        if cycle::leads_to(self, info) {
+            panic!(
+                "Refusing to install {} as a slave of a Reroute, it forwards back into the \
+                 same Reroute and logging would recurse forever",
+                info.type_name,
+            );
+        }
+    }
+
+    /// Like [`check_cycle`][Reroute::check_cycle], for putting back a previous slave.
+    ///
+    /// These happen in destructors and the like, where panicking is not an option. So the cycle is
+    /// reported to stderr instead and `false` is returned, the caller is to leave the current
+    /// slave in place.
+    fn can_restore(&self, info: &SlaveInfo) -> bool {
+        if !cycle::leads_to(self, info) {
+            return true;
+        }
+        StderrLog.log(
+            &Record::builder()
+                .level(Level::Error)
+                .target("log_reroute")
+                .args(format_args!(
+                    "Not restoring {} as a slave of a Reroute, it forwards back into the same \
+                     Reroute by now and logging would recurse forever",
+                    info.type_name,
+                ))
+                .build(),
+        );
+        false
+    }
+
+    fn replace_locked(&self, state: &mut State, log: Slave, mut info: SlaveInfo) -> Displaced {
+        if info.is::<LazySlave>() {
+            self.deferred.store(true, Ordering::Release);
+        }
+        let old = match self.inner.swap(Some(Arc::clone(&log))) {
+            Some(old) => old,
+            None => {
+                if let Some(early) = self.early {
+                    let new = Arc::clone(&log);
+                    state
+                        .handoffs
+                        .push(Box::new(move || early.on_displace(&**new)));
+                }
+                self.initial_current()
+            }
+        };
+        // Bumped after the swap, so whoever sees the new generation also sees the new slave.
+        let generation = self.generation.fetch_add(1, Ordering::Release) + 1;
+        let now = SystemTime::now();
+        info.last_swap = Some(now);
+        let event = SwapEvent {
+            at: now,
+            type_name: info.type_name,
+            generation,
+        };
+        if !state.hooks.is_empty() {
+            state.fired.push(event.clone());
+        }
+        if let Some(old) = state.info.any.as_ref().and_then(displace::find) {
+            let new = Arc::clone(&log);
+            state
+                .handoffs
+                .push(Box::new(move || old.on_displace(&**new)));
+        }
+        state.history.push(event);
+        if self.announce.load(Ordering::Relaxed) {
+            // The dummy would swallow it.
+            let through = if info.dummy { Arc::clone(&old) } else { log };
+            state.announcements.push(Announcement {
+                old: state.info.type_name,
+                new: info.type_name,
+                at: now,
+                through,
+            });
+        }
+        self.dummy.store(info.dummy, Ordering::Relaxed);
+        let info = mem::replace(&mut state.info, info);
+        Displaced {
+            slave: old,
+            info,
+            generation,
+        }
+    }
+
+    fn replace_and_flush(&self, log: Arc<Box<dyn Log>>, info: SlaveInfo) {
+        self.replace_and_dispose(log, info, FlushOld::default());
+    }
+
+    fn replace_and_dispose(&self, log: Arc<Box<dyn Log>>, info: SlaveInfo, flush: FlushOld) {
+        let old = self.replace_unconditionally(log, info).slave;
+        flush.dispose(old);
+    }
+
+    fn replace_unconditionally(&self, log: Arc<Box<dyn Log>>, info: SlaveInfo) -> Displaced {
+        self.replace(Condition::Always, log, info)
+            .unwrap_or_else(|_| unreachable!("Unconditional replace failed"))
     }
 
     /// Sets a new slave logger.
@@ -97,83 +1082,1531 @@ impl Reroute {
     /// Note that the `Arc<Box<dyn Log>>` (double indirection) is necessary evil, since arc-swap
     /// can't accept `!Sized` types.
     pub fn reroute_arc(&self, log: Arc<Box<dyn Log>>) {
-        let old = self.inner.swap(log);
-        old.flush();
+        self.replace_and_flush(log, SlaveInfo::boxed());
     }
 
-    /// Sets a new slave logger.
+    /// Sets a new slave logger, with explicit handling of the old one.
     ///
-    /// See [`reroute_boxed`][Reroute::reroute_boxed] for more details.
-    pub fn reroute<L: Log + 'static>(&self, log: L) {
-        self.reroute_boxed(Box::new(log));
+    /// This is like [`reroute_boxed`][Reroute::reroute_boxed], but allows choosing what happens
+    /// with the old slave. See [`FlushOld`] for the trade-offs.
+    ///
+    /// ```rust
+    /// use log_reroute::{Dummy, FlushOld, Reroute};
+    ///
+    /// let reroute = Reroute::new();
+    /// // Don't wait for the (possibly broken) logger to flush
+    /// reroute.reroute_boxed_with(Box::new(Dummy), FlushOld::Skip);
+    /// ```
+    pub fn reroute_boxed_with(&self, log: Box<dyn Log>, flush: FlushOld) {
+        self.replace_and_dispose(Arc::new(log), SlaveInfo::boxed(), flush);
     }
 
-    /// Stubs out the logger.
+    /// Sets a new slave logger and returns the previous one.
     ///
-    /// Sets the slave logger to one that does nothing (eg. [`Dummy`](struct.Dummy.html)).
-    pub fn clear(&self) {
-        self.reroute(Dummy);
+    /// The exchange is a single atomic operation, therefore (unlike [`get`][Reroute::get] followed
+    /// by [`reroute`][Reroute::reroute]) no other thread can sneak its own logger in between. This
+    /// makes it possible to temporarily install a different logger and later put the original one
+    /// back with [`reroute_arc`][Reroute::reroute_arc].
+    ///
+    /// Unlike the `reroute` family, the old logger is *not* flushed, since it is handed to the
+    /// caller who may keep using it. The responsibility to flush it now lies with the caller.
+    ///
+    /// ```rust
+    /// use log_reroute::{Dummy, Reroute};
+    ///
+    /// let reroute = Reroute::new();
+    /// let original = reroute.swap(Box::new(Dummy));
+    /// // ... log somewhere else for a while ...
+    /// reroute.reroute_arc(original);
+    /// ```
+    pub fn swap(&self, log: Box<dyn Log>) -> Arc<Box<dyn Log>> {
+        self.replace_unconditionally(Arc::new(log), SlaveInfo::boxed())
+            .slave
     }
 
-    /// Gives access to the inner logger.
+    /// Sets a new slave logger and waits for the old one to be released.
     ///
-    /// # Notes
+    /// After the usual `reroute`, other threads might still be in the middle of logging into the
+    /// old slave for a while (see [`get`][Reroute::get]). This waits until no other reference to
+    /// the old slave exists, then flushes and drops it. After this returns, nothing will ever
+    /// write into the old slave again, so eg. the old log file can be safely renamed or
+    /// compressed.
     ///
-    /// The logger may be still in use by other threads, etc. It may be in use even after the
-    /// current thread called [`clear`][Reroute::clear] or [`reroute`][Reroute::reroute], at least
-    /// for a while.
-    pub fn get(&self) -> Arc<Box<dyn Log>> {
-        self.inner.load_full()
+    /// Note that this waits for *all* the references. If something holds onto the old slave for
+    /// a long time (eg. a result of [`get`][Reroute::get] stored somewhere), this blocks for that
+    /// long. See [`swap_and_wait_timeout`][Reroute::swap_and_wait_timeout] for a bounded version.
+    ///
+    /// ```rust
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    /// use std::thread;
+    /// use std::time::Duration;
+    ///
+    /// use log::{Log, Metadata, Record};
+    /// use log_reroute::{Dummy, Reroute};
+    ///
+    /// static STARTED: AtomicBool = AtomicBool::new(false);
+    /// static FINISHED: AtomicBool = AtomicBool::new(false);
+    ///
+    /// struct Slow;
+    ///
+    /// impl Log for Slow {
+    ///     fn enabled(&self, _: &Metadata) -> bool {
+    ///         true
+    ///     }
+    ///     fn log(&self, _: &Record) {
+    ///         STARTED.store(true, Ordering::SeqCst);
+    ///         thread::sleep(Duration::from_millis(100));
+    ///         FINISHED.store(true, Ordering::SeqCst);
+    ///     }
+    ///     fn flush(&self) {}
+    /// }
+    ///
+    /// let reroute = Reroute::with(Slow);
+    /// thread::scope(|s| {
+    ///     s.spawn(|| reroute.log(&Record::builder().args(format_args!("Hello")).build()));
+    ///     while !STARTED.load(Ordering::SeqCst) {
+    ///         thread::yield_now();
+    ///     }
+    ///     reroute.swap_and_wait(Box::new(Dummy));
+    ///     assert!(FINISHED.load(Ordering::SeqCst));
+    /// });
+    /// ```
+    pub fn swap_and_wait(&self, log: Box<dyn Log>) {
+        let old = self.swap(log);
+        if let Err(old) = wait_released(old, None) {
+            unreachable!(
+                "Waiting for {} references without timeout failed",
+                Arc::strong_count(&old)
+            );
+        }
     }
-}
 
-impl Log for Reroute {
-    fn enabled(&self, metadata: &Metadata) -> bool {
-        self.inner.load().enabled(metadata)
-    }
-    fn log(&self, record: &Record) {
-        self.inner.load().log(record)
+    /// Sets a new slave logger and waits (for a limited time) for the old one to be released.
+    ///
+    /// This is like [`swap_and_wait`][Reroute::swap_and_wait], but gives up after the timeout.
+    /// In such case the old slave is returned (not flushed), since some other thread still holds
+    /// it.
+    pub fn swap_and_wait_timeout(
+        &self,
+        log: Box<dyn Log>,
+        timeout: Duration,
+    ) -> Result<(), Arc<Box<dyn Log>>> {
+        let old = self.swap(log);
+        wait_released(old, Some(Instant::now() + timeout))
     }
-    fn flush(&self) {
-        self.inner.load().flush()
+
+    /// Sets a new slave logger, but only if the current one is still `expected`.
+    ///
+    /// The loggers are compared by pointer identity (eg. it must be the same [`Arc`], as
+    /// obtained by [`get`][Reroute::get] or [`swap`][Reroute::swap]). On success, the old logger
+    /// is flushed, the same as with [`reroute_boxed`][Reroute::reroute_boxed]. On failure,
+    /// nothing is installed and the actual current logger is returned.
+    ///
+    /// This allows for "replace the logger, unless someone else changed it in the meantime"
+    /// semantics. If the new logger needs to be derived from the old one, it can be used in a loop:
+    ///
+    /// ```rust
+    /// use log::Log;
+    /// use log_reroute::{Dummy, Reroute};
+    ///
+    /// fn derive(_old: &dyn Log) -> Box<dyn Log> {
+    ///     Box::new(Dummy)
+    /// }
+    ///
+    /// let reroute = Reroute::new();
+    /// let mut current = reroute.get();
+    /// while let Err(actual) = reroute.compare_and_reroute(&current, derive(&**current)) {
+    ///     current = actual;
+    /// }
+    /// ```
+    pub fn compare_and_reroute(
+        &self,
+        expected: &Arc<Box<dyn Log>>,
+        new: Box<dyn Log>,
+    ) -> Result<(), Arc<Box<dyn Log>>> {
+        let previous = self.replace(
+            Condition::Slave(expected),
+            Arc::new(new),
+            SlaveInfo::boxed(),
+        )?;
+        previous.slave.flush();
+        Ok(())
     }
-}
 
-impl Default for Reroute {
-    /// Creates a reroute with a [`Dummy`](struct.Dummy.html) slave logger.
-    fn default() -> Self {
-        Self {
-            inner: ArcSwap::from(Arc::new(Box::new(Dummy) as Box<dyn Log>)),
+    /// Sets a new slave logger, computed from the current one.
+    ///
+    /// This is the read-copy-update pattern. The closure gets the currently installed logger and
+    /// produces its replacement. If some other thread changes the logger in the meantime, the
+    /// closure is called again with the new current one, therefore it may run multiple times under
+    /// contention (and should not have side effects other than producing the new logger). There's
+    /// no window during which the logs would go to [`Dummy`] or anywhere else.
+    ///
+    /// The logger that ends up being replaced is flushed (exactly once), the loggers produced by
+    /// the discarded attempts are simply dropped.
+    ///
+    /// ```rust
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::thread;
+    ///
+    /// use log::{Log, Metadata, Record};
+    /// use log_reroute::Reroute;
+    ///
+    /// static FLUSHES: AtomicUsize = AtomicUsize::new(0);
+    ///
+    /// struct Counting;
+    ///
+    /// impl Log for Counting {
+    ///     fn enabled(&self, _: &Metadata) -> bool {
+    ///         true
+    ///     }
+    ///     fn log(&self, _: &Record) {}
+    ///     fn flush(&self) {
+    ///         FLUSHES.fetch_add(1, Ordering::Relaxed);
+    ///     }
+    /// }
+    ///
+    /// let reroute = Reroute::new();
+    /// // Displaces the Dummy, which doesn't count
+    /// reroute.reroute(Counting);
+    /// thread::scope(|s| {
+    ///     for _ in 0..8 {
+    ///         s.spawn(|| {
+    ///             reroute.reroute_with(|_old| Box::new(Counting));
+    ///         });
+    ///     }
+    /// });
+    /// // Every successful reroute displaced exactly one logger.
+    /// assert_eq!(8, FLUSHES.load(Ordering::Relaxed));
+    /// ```
+    pub fn reroute_with<F>(&self, mut f: F)
+    where
+        F: FnMut(&Arc<Box<dyn Log>>) -> Box<dyn Log>,
+    {
+        let mut current = self.current();
+        loop {
+            let new = Arc::new(f(&current));
+            match self.replace(Condition::Slave(&current), new, SlaveInfo::boxed()) {
+                Ok(old) => {
+                    old.slave.flush();
+                    return;
+                }
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    /// Sets a new slave logger, keeping a handle to it.
+    ///
+    /// This is like [`reroute`][Reroute::reroute], but the returned handle allows accessing the
+    /// slave by its concrete type later on (eg. to reconfigure it), without having to
+    /// [downcast][Reroute::get_downcast] it.
+    ///
+    /// ```rust
+    /// use log_reroute::{Dummy, Reroute};
+    ///
+    /// let reroute = Reroute::new();
+    /// let handle = reroute.reroute_handle(Dummy);
+    /// assert!(handle.is_current(&reroute));
+    /// reroute.clear();
+    /// assert!(!handle.is_current(&reroute));
+    /// ```
+    pub fn reroute_handle<L: Log + 'static>(&self, log: L) -> SlaveHandle<L> {
+        let log = Arc::new(log);
+        let (slave, info) = shared(Arc::clone(&log));
+        let handle = SlaveHandle {
+            log,
+            slave: Arc::downgrade(&slave),
+        };
+        self.replace_and_flush(slave, info);
+        handle
+    }
+
+    /// Sets a new slave logger, allowing to revert the change later.
+    ///
+    /// The returned token remembers the previous slave. Passing it to
+    /// [`revert_if_current`][Reroute::revert_if_current] puts the previous slave back, but only if
+    /// nobody else changed the slave in the meantime. Unlike [`swap`][Reroute::swap] followed by
+    /// [`reroute_arc`][Reroute::reroute_arc], this doesn't throw away a newer logger installed by
+    /// someone else.
+    ///
+    /// The previous slave is not flushed, since it is expected to be put back.
+    pub fn reroute_tracked<L: Log + 'static>(&self, log: L) -> SwapToken {
+        let (log, info) = typed(log);
+        let displaced = self.replace_unconditionally(log, info);
+        SwapToken {
+            previous: displaced.slave,
+            info: displaced.info,
+            generation: displaced.generation,
+        }
+    }
+
+    /// Sets a new slave logger for a limited time.
+    ///
+    /// After the duration elapses, the previous slave is put back (by a background timer thread),
+    /// unless someone else changed the slave in the meantime (the same as with
+    /// [`revert_if_current`][Reroute::revert_if_current]). The returned handle allows cancelling
+    /// the revert or doing it early; dropping the handle leaves the timer running.
+    ///
+    /// As the timer thread needs to access the [`Reroute`], this is available only for ones that
+    /// live forever (like the global `REROUTE`).
+    ///
+    /// ```rust
+    /// use std::thread;
+    /// use std::time::Duration;
+    ///
+    /// use log_reroute::{Dummy, Reroute};
+    ///
+    /// let reroute: &'static Reroute = Box::leak(Box::new(Reroute::new()));
+    /// let handle = reroute.reroute_for(Duration::from_millis(50), Dummy);
+    /// assert_eq!(1, reroute.generation());
+    /// thread::sleep(Duration::from_millis(100));
+    /// assert_eq!(2, reroute.generation());
+    /// // Too late now
+    /// assert!(!handle.revert_now());
+    /// ```
+    pub fn reroute_for<L: Log + 'static>(&'static self, duration: Duration, log: L) -> TimedHandle {
+        let deadline = Instant::now() + duration;
+        let token = Arc::new(Mutex::new(Some(self.reroute_tracked(log))));
+        let (cancel, cancelled) = mpsc::channel::<()>();
+        let timer_token = Arc::clone(&token);
+        let timer = move || {
+            match cancelled.recv_timeout(duration) {
+                Ok(()) => return,
+                Err(RecvTimeoutError::Timeout) => (),
+                // The handle is gone, but that doesn't cancel the timer
+                Err(RecvTimeoutError::Disconnected) => {
+                    thread::sleep(deadline.saturating_duration_since(Instant::now()))
+                }
+            }
+            let token = timer_token
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .take();
+            if let Some(token) = token {
+                self.revert_if_current(token);
+            }
+        };
+        let spawned = thread::Builder::new()
+            .name("log-reroute-timer".to_owned())
+            .spawn(timer);
+        if spawned.is_err() {
+            // Without the timer, the temporary logger would stay forever; don't pretend.
+            let token = token.lock().unwrap_or_else(PoisonError::into_inner).take();
+            if let Some(token) = token {
+                self.revert_if_current(token);
+            }
+        }
+        TimedHandle {
+            reroute: self,
+            token,
+            cancel,
         }
     }
+
+    /// Puts back the slave replaced by [`reroute_tracked`][Reroute::reroute_tracked].
+    ///
+    /// This happens only if the slave wasn't changed since the token was issued. Returns if the
+    /// revert happened. The temporary slave is flushed, as with [`reroute`][Reroute::reroute].
+    ///
+    /// If the previous slave forwards back into this reroute by now, it is not put back (that
+    /// would make the logging recurse forever). Unlike installing such slave, this doesn't panic,
+    /// it prints an error to stderr and returns `false`. The same goes for [`RerouteGuard`].
+    ///
+    /// ```rust
+    /// use log_reroute::{Dummy, Reroute};
+    ///
+    /// let reroute = Reroute::new();
+    /// let token = reroute.reroute_tracked(Dummy);
+    /// assert!(reroute.revert_if_current(token));
+    ///
+    /// let token = reroute.reroute_tracked(Dummy);
+    /// // Someone else changes the logger in the meantime
+    /// reroute.reroute(Dummy);
+    /// // So it is left alone
+    /// assert!(!reroute.revert_if_current(token));
+    /// ```
+    pub fn revert_if_current(&self, token: SwapToken) -> bool {
+        if !self.can_restore(&token.info) {
+            return false;
+        }
+        let condition = Condition::Generation(token.generation);
+        match self.replace_unchecked(condition, token.previous, token.info) {
+            Ok(temporary) => {
+                temporary.slave.flush();
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Sets a new slave logger, but only if there's none yet.
+    ///
+    /// The logger is installed only if the current slave is the [`Dummy`] (either because
+    /// nothing was set yet or because of [`clear`][Reroute::clear]). Returns if the logger was
+    /// installed. This allows "first one wins" semantics when multiple places try to set up the
+    /// logging.
+    ///
+    /// Note that boxed slaves are not recognized as [`Dummy`], see
+    /// [`is_active`][Reroute::is_active].
+    ///
+    /// ```rust
+    /// use log_reroute::{Dummy, Reroute};
+    ///
+    /// let reroute = Reroute::new();
+    /// assert!(reroute.reroute_if_unset(Dummy));
+    /// reroute.reroute_boxed(Box::new(Dummy));
+    /// assert!(!reroute.reroute_if_unset(Dummy));
+    /// reroute.clear();
+    /// assert!(reroute.reroute_if_unset(Dummy));
+    /// ```
+    pub fn reroute_if_unset<L: Log + 'static>(&self, log: L) -> bool {
+        let (log, info) = typed(log);
+        match self.replace(Condition::Unset, log, info) {
+            Ok(dummy) => {
+                dummy.slave.flush();
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Sets a new slave logger for the lifetime of the returned guard.
+    ///
+    /// When the guard is dropped, the previous slave is put back (and the temporary one flushed).
+    /// This happens even when the scope is left by a panic. Nested guards restore the slaves in
+    /// the reverse order, as they are dropped.
+    ///
+    /// Whatever is installed at the time the guard is dropped is replaced, even if it was
+    /// installed by someone else than the guard. If that's not desired, see
+    /// [`reroute_tracked`][Reroute::reroute_tracked].
+    ///
+    /// If the previous slave forwards back into this reroute by the time the guard is dropped,
+    /// putting it back would make the logging recurse forever. The current slave is left in place
+    /// then and an error is printed to stderr (the guard doesn't panic).
+    ///
+    /// ```rust
+    /// use std::panic::{self, AssertUnwindSafe};
+    /// use std::sync::Arc;
+    ///
+    /// use log_reroute::{Dummy, Reroute};
+    ///
+    /// let reroute = Reroute::new();
+    /// {
+    ///     let _guard = reroute.guard(Dummy);
+    ///     reroute.reroute_boxed(Box::new(Dummy));
+    ///     assert!(reroute.is_active());
+    /// }
+    /// assert!(!reroute.is_active());
+    ///
+    /// let result = panic::catch_unwind(AssertUnwindSafe(|| {
+    ///     let _guard = reroute.guard(Dummy);
+    ///     reroute.reroute_boxed(Box::new(Dummy));
+    ///     panic!("Test failed");
+    /// }));
+    /// assert!(result.is_err());
+    /// assert!(!reroute.is_active());
+    ///
+    /// // The restore would create a cycle
+    /// let first = Arc::new(Reroute::new());
+    /// let second = Arc::new(Reroute::new());
+    /// first.reroute(Arc::clone(&second));
+    /// let guard = first.guard(Dummy);
+    /// second.reroute(Arc::clone(&first));
+    /// drop(guard);
+    /// assert_eq!("log_reroute::Dummy", first.status().type_name);
+    /// ```
+    pub fn guard<L: Log + 'static>(&self, log: L) -> RerouteGuard<'_> {
+        let (log, info) = typed(log);
+        let displaced = self.replace_unconditionally(log, info);
+        RerouteGuard {
+            reroute: self,
+            previous: Some((displaced.slave, displaced.info)),
+            level: None,
+        }
+    }
+
+    /// Sets a new slave logger and the max level of the [`log`] facade.
+    ///
+    /// Changing the destination often goes together with changing how much is logged. This is
+    /// [`reroute`][Reroute::reroute] followed by [`log::set_max_level`].
+    pub fn reroute_with_level<L: Log + 'static>(&self, log: L, level: LevelFilter) {
+        self.reroute(log);
+        log::set_max_level(level);
+    }
+
+    /// Sets a slave logger and the max level until the returned guard is dropped.
+    ///
+    /// Like [`guard`][Reroute::guard], but the guard also puts back the max level of the [`log`]
+    /// facade as it was when this was called. Nested guards restore both in the reverse order, as
+    /// they are dropped, so with properly nested scopes the outermost one puts back the original
+    /// state. Note that the max level is global, nested guards of different reroutes restore it
+    /// too.
+    ///
+    /// ```rust
+    /// use log::LevelFilter;
+    /// use log_reroute::{Dummy, Reroute};
+    ///
+    /// let reroute = Reroute::new();
+    /// log::set_max_level(LevelFilter::Warn);
+    /// {
+    ///     let _outer = reroute.guard_with_level(Dummy, LevelFilter::Debug);
+    ///     {
+    ///         let _inner = reroute.guard_with_level(Dummy, LevelFilter::Trace);
+    ///         assert_eq!(LevelFilter::Trace, log::max_level());
+    ///     }
+    ///     assert_eq!(LevelFilter::Debug, log::max_level());
+    /// }
+    /// assert_eq!(LevelFilter::Warn, log::max_level());
+    /// ```
+    pub fn guard_with_level<L: Log + 'static>(
+        &self,
+        log: L,
+        level: LevelFilter,
+    ) -> RerouteGuard<'_> {
+        let previous = log::max_level();
+        let mut guard = self.guard(log);
+        log::set_max_level(level);
+        guard.level = Some(previous);
+        guard
+    }
+
+    /// Sets a slave logger that is constructed only once needed.
+    ///
+    /// The closure is called (exactly once) on the first [`log`][Log::log] or
+    /// [`flush`][Log::flush] call and the result replaces the placeholder, so further log calls
+    /// go directly to it. If the logger is never needed, the closure is never called. This is
+    /// useful if constructing the logger is expensive.
+    ///
+    /// Until the logger is constructed, the [`enabled`][Log::enabled] method returns `true`, since
+    /// it can't know better.
+    ///
+    /// ```rust
+    /// use log::{Log, Record};
+    /// use log_reroute::Reroute;
+    ///
+    /// let reroute = Reroute::new();
+    /// reroute.reroute_lazy(|| {
+    ///     // Open the files, connect to the log collector, ...
+    ///     fern::Dispatch::new().chain(std::io::stderr()).into_log().1
+    /// });
+    /// // Not constructed yet
+    /// assert_eq!("lazy", reroute.status().type_name);
+    /// reroute.log(&Record::builder().args(format_args!("Hello")).build());
+    /// assert_eq!("boxed", reroute.status().type_name);
+    /// ```
+    pub fn reroute_lazy<F>(&self, f: F)
+    where
+        F: FnOnce() -> Box<dyn Log> + Send + 'static,
+    {
+        let (log, mut info) = shared(Arc::new(LazySlave::new(f)));
+        info.type_name = "lazy";
+        self.replace_and_flush(log, info);
+    }
+
+    /// Schedules a reroute to happen on the next log call.
+    ///
+    /// This only stores the new slave aside and returns right away, without blocking, flushing or
+    /// dropping the old slave. That is useful in contexts which must not wait, like signal
+    /// handlers or real-time threads (note that there's still the allocation of the [`Arc`]).
+    ///
+    /// The actual reroute (including flushing the old slave) is performed by the next
+    /// [`log`][Log::log] or [`flush`][Log::flush] call, in whatever thread makes it. Until then,
+    /// the old slave stays in place, so if nothing logs, the change doesn't take effect.
+    ///
+    /// If multiple reroutes are scheduled before the next log call, only the last one is
+    /// installed and the skipped ones are dropped (in the thread scheduling the following one),
+    /// without flushing.
+    ///
+    /// ```rust
+    /// use log::{Log, Record};
+    /// use log_reroute::{Dummy, Reroute};
+    ///
+    /// let reroute = Reroute::new();
+    /// reroute.schedule_reroute(Box::new(Dummy));
+    /// assert!(!reroute.is_active());
+    /// reroute.log(&Record::builder().args(format_args!("Hello")).build());
+    /// assert!(reroute.is_active());
+    /// ```
+    pub fn schedule_reroute(&self, log: Box<dyn Log>) {
+        self.pending.store(Some(Arc::new(log)));
+        self.deferred.store(true, Ordering::SeqCst);
+    }
+
+    /// Sets a slave logger for the current thread only.
+    ///
+    /// Records logged from the current thread go to this slave instead of the one shared by all
+    /// the threads. The other threads are not affected (including the ones spawned from this
+    /// thread later on). This can be used eg. to separate logs of tests running in parallel, or
+    /// to send the logs of a specific worker thread elsewhere.
+    ///
+    /// The [layers][Reroute::layer] apply to the override too. When no override is set, this
+    /// costs only a check of a thread local flag on each log call.
+    ///
+    /// Returns the previous override of this thread, if any.
+    ///
+    /// ```rust
+    /// use log_reroute::{Dummy, Reroute};
+    ///
+    /// let reroute = Reroute::new();
+    /// reroute.set_thread_override(Dummy);
+    /// // ... the logs from this thread go to the override ...
+    /// assert!(reroute.clear_thread_override().is_some());
+    /// ```
+    pub fn set_thread_override<L: Log + 'static>(&self, log: L) -> Option<Arc<Box<dyn Log>>> {
+        let (slave, _) = typed(log);
+        local::set(self.local_id(), slave)
+    }
+
+    /// Removes the override of the current thread set by
+    /// [`set_thread_override`][Reroute::set_thread_override].
+    ///
+    /// The logs from this thread go to the shared slave again. The removed override is returned
+    /// (not flushed).
+    pub fn clear_thread_override(&self) -> Option<Arc<Box<dyn Log>>> {
+        match self.local_id.load(Ordering::Relaxed) {
+            0 => None,
+            id => local::remove(id),
+        }
+    }
+
+    fn local_id(&self) -> u64 {
+        let id = self.local_id.load(Ordering::Relaxed);
+        if id != 0 {
+            return id;
+        }
+        let id = local::new_id();
+        match self
+            .local_id
+            .compare_exchange(0, id, Ordering::Relaxed, Ordering::Relaxed)
+        {
+            Ok(_) => id,
+            // Someone else was faster
+            Err(existing) => existing,
+        }
+    }
+
+    /// Finds the pipeline for the current log call and passes it to the closure.
+    #[inline]
+    fn dispatch<R, F: FnOnce(&dyn Log) -> R>(&self, f: F) -> R {
+        #[cfg(feature = "tokio")]
+        if let Some(slave) = task::get(self.local_id()) {
+            return self.through_layers(&**slave, f);
+        }
+        if local::active() {
+            if let Some(slave) = local::get(self.local_id()) {
+                return self.through_layers(&**slave, f);
+            }
+        }
+        self.dispatch_shared(f)
+    }
+
+    /// Like [`dispatch`][Reroute::dispatch], but ignores the thread and task overrides.
+    #[inline]
+    fn dispatch_shared<R, F: FnOnce(&dyn Log) -> R>(&self, f: F) -> R {
+        match &*self.inner.load() {
+            Some(slave) => self.through_layers(&***slave, f),
+            None => self.through_layers(self.initial_log(), f),
+        }
+    }
+
+    /// Does the current log call go to a thread or task override?
+    fn overridden(&self) -> bool {
+        #[cfg(feature = "tokio")]
+        if task::get(self.local_id()).is_some() {
+            return true;
+        }
+        local::active() && local::get(self.local_id()).is_some()
+    }
+
+    #[inline]
+    fn through_layers<R, F: FnOnce(&dyn Log) -> R>(&self, slave: &dyn Log, f: F) -> R {
+        // Checking the flag is cheaper than loading the layers.
+        if !self.has_layers.load(Ordering::Relaxed) {
+            return f(slave);
+        }
+        match &*self.layers.load() {
+            None => f(slave),
+            Some(layers) => f(&Pipeline::new(layers, slave)),
+        }
+    }
+
+    #[inline]
+    fn run_deferred(&self) {
+        if self.deferred.load(Ordering::Acquire) {
+            self.run_deferred_slow();
+        }
+    }
+
+    #[cold]
+    fn run_deferred_slow(&self) {
+        // Reset first, so anything scheduled from now on sets it again and is not lost.
+        self.deferred.store(false, Ordering::SeqCst);
+        if let Some(pending) = self.pending.swap(None) {
+            self.replace_and_flush(pending, SlaveInfo::boxed());
+        }
+        let (slave, any) = {
+            let state = self.lock_state();
+            (self.current(), state.info.any.clone())
+        };
+        if let Some(lazy) = any.and_then(|any| any.downcast::<LazySlave>().ok()) {
+            // Construct it outside of the lock, the constructor might want to do something with
+            // us. None means it's this thread constructing it right now.
+            if let Some(real) = lazy.force() {
+                let real = Arc::clone(real);
+                // If it fails, someone replaced the lazy slave by something else in the meantime.
+                let _ = self.replace(Condition::Slave(&slave), real, SlaveInfo::boxed());
+            }
+        }
+    }
+
+    /// Sets a borrowed slave logger for the duration of a closure.
+    ///
+    /// The other methods require the slave to be `'static`. This one allows using a logger that
+    /// lives on the stack, for example in a test. Once the closure returns (or panics), the
+    /// previous slave is put back and this waits until nothing can reach the borrowed logger any
+    /// more (eg. another thread in the middle of logging into it).
+    ///
+    /// As the borrowed logger can't be installed directly, it is served from a helper thread
+    /// (living only for the duration of the call). Each call to the logger is sent to it and
+    /// waited for, which is considerably slower than the direct calls. Calls made from within the
+    /// borrowed logger itself (eg. it logs something) are dropped. If the helper thread can't be
+    /// started, a warning is printed to stderr and the closure runs with the current slave.
+    ///
+    /// The wait at the end covers only the calls already in progress. Anyone who still holds onto
+    /// the temporary slave (eg. obtained by [`get`][Reroute::get]) afterwards gets a disconnected
+    /// one, logging nowhere.
+    ///
+    /// ```rust
+    /// use std::panic::{self, AssertUnwindSafe};
+    /// use std::sync::Mutex;
+    ///
+    /// use log::{Log, Metadata, Record};
+    /// use log_reroute::Reroute;
+    ///
+    /// #[derive(Default)]
+    /// struct Capture(Mutex<Vec<String>>);
+    ///
+    /// impl Log for Capture {
+    ///     fn enabled(&self, _: &Metadata) -> bool {
+    ///         true
+    ///     }
+    ///     fn log(&self, record: &Record) {
+    ///         self.0.lock().unwrap().push(record.args().to_string());
+    ///     }
+    ///     fn flush(&self) {}
+    /// }
+    ///
+    /// let reroute = Reroute::new();
+    /// let capture = Capture::default();
+    /// reroute.scope(&capture, || {
+    ///     reroute.log(&Record::builder().args(format_args!("Hello")).build());
+    /// });
+    /// assert_eq!(vec!["Hello".to_owned()], *capture.0.lock().unwrap());
+    /// assert!(!reroute.is_active());
+    ///
+    /// let result = panic::catch_unwind(AssertUnwindSafe(|| {
+    ///     reroute.scope(&capture, || panic!("Test failed"));
+    /// }));
+    /// assert!(result.is_err());
+    /// assert!(!reroute.is_active());
+    ///
+    /// // Holding onto the temporary slave doesn't block the end of the scope
+    /// let kept = reroute.scope(&capture, || reroute.get());
+    /// kept.log(&Record::builder().args(format_args!("Late")).build());
+    /// assert_eq!(vec!["Hello".to_owned()], *capture.0.lock().unwrap());
+    /// ```
+    pub fn scope<R, F: FnOnce() -> R>(&self, logger: &dyn Log, f: F) -> R {
+        scope::run(self, logger, f)
+    }
+
+    /// Sets a new slave logger.
+    ///
+    /// See [`reroute_boxed`][Reroute::reroute_boxed] for more details.
+    ///
+    /// # Panics
+    ///
+    /// If the slave forwards back into the same reroute (eg. it is the same reroute, possibly
+    /// through other reroutes), logging would recurse forever. This is detected and refused with a
+    /// panic by all the methods installing a slave. Only the types from this crate can be looked
+    /// into, as a last resort the calls nested too deep in reroutes are dropped.
+    ///
+    /// ```rust
+    /// use std::panic::{self, AssertUnwindSafe};
+    /// use std::sync::Arc;
+    ///
+    /// use log::{Log, Record};
+    /// use log_reroute::Reroute;
+    ///
+    /// let a = Arc::new(Reroute::new());
+    /// let b = Arc::new(Reroute::new());
+    ///
+    /// // Directly into itself
+    /// let direct = panic::catch_unwind(AssertUnwindSafe(|| a.reroute(Arc::clone(&a))));
+    /// assert!(direct.is_err());
+    ///
+    /// // Through another reroute
+    /// b.reroute(Arc::clone(&a));
+    /// let indirect = panic::catch_unwind(AssertUnwindSafe(|| a.reroute(b.handle())));
+    /// assert!(indirect.is_err());
+    ///
+    /// // Not detected when boxed, but the logging doesn't recurse forever
+    /// a.reroute_boxed(Box::new(Arc::clone(&b)));
+    /// a.log(&Record::builder().args(format_args!("Hello")).build());
+    /// # a.clear();
+    /// ```
+    pub fn reroute<L: Log + 'static>(&self, log: L) {
+        let (log, info) = typed(log);
+        self.replace_and_flush(log, info);
+    }
+
+    /// Temporarily sets a new slave logger, remembering the current one.
+    ///
+    /// The current slave is put onto an internal stack and can be restored by
+    /// [`pop`][Reroute::pop]. It is not flushed, since it is expected to come back. The pushes and
+    /// pops can be nested.
+    ///
+    /// The other ways of changing the slave (eg. [`reroute`][Reroute::reroute]) replace the top
+    /// of the stack, leaving the buried slaves alone, while [`clear`][Reroute::clear] empties the
+    /// whole stack.
+    ///
+    /// ```rust
+    /// use log_reroute::{Dummy, Reroute};
+    ///
+    /// let reroute = Reroute::new();
+    /// reroute.reroute_boxed(Box::new(Dummy));
+    /// reroute.push(Dummy);
+    /// assert_eq!("log_reroute::Dummy", reroute.status().type_name);
+    /// assert!(reroute.pop().is_some());
+    /// assert_eq!("boxed", reroute.status().type_name);
+    /// // The last one falls back to Dummy
+    /// assert!(reroute.pop().is_some());
+    /// assert!(!reroute.is_active());
+    /// assert!(reroute.pop().is_none());
+    /// ```
+    pub fn push<L: Log + 'static>(&self, log: L) {
+        let (log, info) = typed(log);
+        self.check_cycle(&info);
+        let mut state = self.lock_state();
+        let displaced = self.replace_locked(&mut state, log, info);
+        state.stack.push((displaced.slave, displaced.info));
+    }
+
+    /// Restores the slave buried by the last [`push`][Reroute::push].
+    ///
+    /// The current slave is removed and returned. It is not flushed, that is left up to the
+    /// caller.
+    ///
+    /// If there's nothing on the stack, the [`Dummy`] (or the [rest
+    /// state][Reroute::set_rest_state]) is installed in place of the current slave. If there's
+    /// nothing on the stack and the current slave already is one of these, nothing happens and
+    /// `None` is returned.
+    pub fn pop(&self) -> Option<Arc<Box<dyn Log>>> {
+        let mut state = self.lock_state();
+        let (log, info) = match state.stack.pop() {
+            Some(buried) => buried,
+            None if state.at_rest() => return None,
+            None => state.rest(),
+        };
+        Some(self.replace_locked(&mut state, log, info).slave)
+    }
+
+    /// Stubs out the logger.
+    ///
+    /// Sets the slave logger to one that does nothing (eg. [`Dummy`](struct.Dummy.html)), or
+    /// to the [rest state][Reroute::set_rest_state] if one is configured. This also drops all the
+    /// slaves stored by [`push`][Reroute::push].
+    pub fn clear(&self) {
+        let mut state = self.lock_state();
+        let buried = mem::take(&mut state.stack);
+        let (rest, info) = state.rest();
+        let old = self.replace_locked(&mut state, rest, info);
+        drop(state);
+        old.slave.flush();
+        drop(buried);
+    }
+
+    /// Sets the logger used when there's no other slave.
+    ///
+    /// By default, [`clear`][Reroute::clear] (and similar methods) install the [`Dummy`], which
+    /// throws all the logs away. This allows to configure a different fallback, eg. a minimal
+    /// logger to stderr, so the logs never silently vanish.
+    ///
+    /// If the [`Reroute`] is currently at rest (with the [`Dummy`] or the previous rest state
+    /// installed), the new rest state is installed right away. The slave in the rest state
+    /// counts as unset for the purpose of [`reroute_if_unset`][Reroute::reroute_if_unset].
+    ///
+    /// Note that explicitly installing a [`Dummy`] (eg. `reroute(Dummy)`) still installs the
+    /// [`Dummy`], not the rest state.
+    ///
+    /// ```rust
+    /// use log_reroute::{Dummy, Reroute};
+    ///
+    /// let reroute = Reroute::new();
+    /// reroute.set_rest_state(fern::Dispatch::new().chain(std::io::stderr()).into_log().1);
+    /// assert!(reroute.is_active());
+    /// reroute.reroute(Dummy);
+    /// assert!(!reroute.is_active());
+    /// reroute.clear();
+    /// assert!(reroute.is_active());
+    /// ```
+    pub fn set_rest_state<L: Log + 'static>(&self, log: L) {
+        let rest = typed(log);
+        self.check_cycle(&rest.1);
+        let mut state = self.lock_state();
+        // Dropped only once unlocked, its Drop may call back into the reroute.
+        let previous = state.rest.replace(rest);
+        let old = if state.at_rest() {
+            let (rest, info) = state.rest();
+            Some(self.replace_locked(&mut state, rest, info))
+        } else {
+            None
+        };
+        drop(state);
+        drop(previous);
+        if let Some(old) = old {
+            old.slave.flush();
+        }
+    }
+
+    /// Takes the current slave out, stubbing out the logger.
+    ///
+    /// This installs the [`Dummy`] (or the [rest state][Reroute::set_rest_state]) and returns the
+    /// previous slave, without flushing it. It can be put back later by
+    /// [`restore`][Reroute::restore], eg. when the very same instance needs to be reused. Unlike
+    /// [`clear`][Reroute::clear], this leaves the slaves stored by [`push`][Reroute::push] alone.
+    ///
+    /// ```rust
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    ///
+    /// use log::{Log, Metadata, Record};
+    /// use log_reroute::Reroute;
+    ///
+    /// static RECORDS: AtomicUsize = AtomicUsize::new(0);
+    ///
+    /// struct Counting;
+    ///
+    /// impl Log for Counting {
+    ///     fn enabled(&self, _: &Metadata) -> bool {
+    ///         true
+    ///     }
+    ///     fn log(&self, _: &Record) {
+    ///         RECORDS.fetch_add(1, Ordering::Relaxed);
+    ///     }
+    ///     fn flush(&self) {}
+    /// }
+    ///
+    /// let reroute = Reroute::with(Counting);
+    /// let record = || Record::builder().args(format_args!("Hello")).build();
+    /// reroute.log(&record());
+    /// let taken = reroute.take();
+    /// reroute.log(&record());
+    /// assert_eq!(1, RECORDS.load(Ordering::Relaxed));
+    /// reroute.restore(taken);
+    /// reroute.log(&record());
+    /// assert_eq!(2, RECORDS.load(Ordering::Relaxed));
+    /// ```
+    pub fn take(&self) -> Arc<Box<dyn Log>> {
+        let mut state = self.lock_state();
+        let (rest, info) = state.rest();
+        self.replace_locked(&mut state, rest, info).slave
+    }
+
+    /// Puts back a slave previously obtained by [`take`][Reroute::take].
+    ///
+    /// This is the same as [`reroute_arc`][Reroute::reroute_arc], provided for readability.
+    pub fn restore(&self, log: Arc<Box<dyn Log>>) {
+        self.reroute_arc(log);
+    }
+
+    /// Adds a layer in front of the slave.
+    ///
+    /// Unlike wrapping the slave itself, the layer stays in place even when the slave is replaced
+    /// (no matter by which method). Multiple layers are applied in the order they were added.
+    /// See [`LogLayer`] for details.
+    ///
+    /// The layer stays until removed by [`remove_layer`][Reroute::remove_layer].
+    pub fn layer<L: LogLayer + 'static>(&self, layer: L) -> LayerHandle {
+        let mut state = self.lock_state();
+        let id = state.next_id;
+        state.next_id += 1;
+        let mut layers = self
+            .layers
+            .load_full()
+            .map(|l| (*l).clone())
+            .unwrap_or_default();
+        layers.push((id, Arc::new(layer)));
+        self.layers.store(Some(Arc::new(layers)));
+        self.has_layers.store(true, Ordering::Relaxed);
+        LayerHandle(id)
+    }
+
+    /// Removes a layer previously added by [`layer`][Reroute::layer].
+    ///
+    /// Returns if the layer was found in this [`Reroute`].
+    pub fn remove_layer(&self, handle: LayerHandle) -> bool {
+        let _state = self.lock_state();
+        let mut layers = match self.layers.load_full() {
+            Some(layers) => (*layers).clone(),
+            None => return false,
+        };
+        let len = layers.len();
+        layers.retain(|(id, _)| *id != handle.0);
+        let removed = layers.len() != len;
+        self.has_layers.store(!layers.is_empty(), Ordering::Relaxed);
+        self.layers
+            .store(Some(Arc::new(layers)).filter(|l| !l.is_empty()));
+        removed
+    }
+
+    /// Registers a hook called after each change of the slave.
+    ///
+    /// The hook is called after every successful swap (no matter by which method, including
+    /// [`clear`][Reroute::clear]), once the swap is complete and no internal locks are held. It
+    /// may therefore use the reroute, even change the slave again (which calls the hooks again).
+    /// Hooks of swaps happening concurrently in multiple threads may be called in any order. The
+    /// logging itself doesn't call the hooks.
+    ///
+    /// A panic inside a hook is caught, so it doesn't influence the swap or the other hooks.
+    ///
+    /// The hook stays until removed by [`remove_hook`][Reroute::remove_hook].
+    ///
+    /// ```rust
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// use log_reroute::{Dummy, Reroute};
+    ///
+    /// let reroute = Reroute::new();
+    /// let seen = Arc::new(Mutex::new(Vec::new()));
+    ///
+    /// let first_seen = Arc::clone(&seen);
+    /// let first = reroute.on_swap(move |event| {
+    ///     first_seen.lock().unwrap().push(("first", event.generation));
+    /// });
+    /// let second_seen = Arc::clone(&seen);
+    /// reroute.on_swap(move |event| {
+    ///     second_seen.lock().unwrap().push(("second", event.generation));
+    /// });
+    ///
+    /// reroute.reroute(Dummy);
+    /// reroute.clear();
+    /// assert!(reroute.remove_hook(first));
+    /// reroute.reroute(Dummy);
+    ///
+    /// assert_eq!(
+    ///     vec![("first", 1), ("second", 1), ("first", 2), ("second", 2), ("second", 3)],
+    ///     *seen.lock().unwrap(),
+    /// );
+    /// ```
+    pub fn on_swap<F>(&self, hook: F) -> HookHandle
+    where
+        F: Fn(&SwapEvent) + Send + Sync + 'static,
+    {
+        let mut state = self.lock_state();
+        let id = state.next_id;
+        state.next_id += 1;
+        state.hooks.push((id, Arc::new(hook)));
+        HookHandle(id)
+    }
+
+    /// Removes a hook previously registered by [`on_swap`][Reroute::on_swap].
+    ///
+    /// Returns if it was found. Note that the hook may still be called by a swap that happened
+    /// before the removal.
+    pub fn remove_hook(&self, handle: HookHandle) -> bool {
+        let mut state = self.lock_state();
+        let len = state.hooks.len();
+        state.hooks.retain(|(id, _)| *id != handle.0);
+        state.hooks.len() != len
+    }
+
+    /// Gives access to the inner logger.
+    ///
+    /// # Notes
+    ///
+    /// The logger may be still in use by other threads, etc. It may be in use even after the
+    /// current thread called [`clear`][Reroute::clear] or [`reroute`][Reroute::reroute], at least
+    /// for a while.
+    pub fn get(&self) -> Arc<Box<dyn Log>> {
+        self.current()
+    }
+
+    /// Gives access to the inner logger by its concrete type.
+    ///
+    /// This returns the slave if it is of type `T`, so its own methods can be called. The type of
+    /// the slave is known only if it was installed by its concrete type (eg. through
+    /// [`reroute`][Reroute::reroute], not [`reroute_boxed`][Reroute::reroute_boxed]), otherwise
+    /// this returns `None`.
+    ///
+    /// ```rust
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    ///
+    /// use log::{Log, Metadata, Record};
+    /// use log_reroute::Reroute;
+    ///
+    /// #[derive(Default)]
+    /// struct Switchable(AtomicBool);
+    ///
+    /// impl Switchable {
+    ///     fn switch(&self, on: bool) {
+    ///         self.0.store(on, Ordering::Relaxed);
+    ///     }
+    /// }
+    ///
+    /// impl Log for Switchable {
+    ///     fn enabled(&self, _: &Metadata) -> bool {
+    ///         self.0.load(Ordering::Relaxed)
+    ///     }
+    ///     fn log(&self, _: &Record) {}
+    ///     fn flush(&self) {}
+    /// }
+    ///
+    /// let reroute = Reroute::with(Switchable::default());
+    /// reroute.get_downcast::<Switchable>().unwrap().switch(true);
+    /// assert!(reroute.enabled(&Metadata::builder().build()));
+    /// ```
+    pub fn get_downcast<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+        let state = self.lock_state();
+        let any = match &state.info.any {
+            Some(any) => Arc::clone(any),
+            None if self.inner.load().is_none() && self.early.is_none() => {
+                initial().1.any.clone()?
+            }
+            None => return None,
+        };
+        any.downcast().ok()
+    }
+
+    /// Runs a closure with the current slave.
+    ///
+    /// This is a cheaper alternative to [`get`][Reroute::get], for when the slave is needed only
+    /// for a short while. It doesn't allocate or clone the [`Arc`], the slave is only kept alive
+    /// for the duration of the closure.
+    ///
+    /// Changing the slave from within the closure is safe (the closure still sees the old one).
+    /// But [`swap_and_wait`][Reroute::swap_and_wait] would wait for the closure to finish and
+    /// deadlock.
+    ///
+    /// ```rust
+    /// use log::{Level, Metadata};
+    ///
+    /// let metadata = Metadata::builder().level(Level::Debug).target("my_crate").build();
+    /// let reroute = log_reroute::Reroute::new();
+    /// let debug_enabled = reroute.with_current(|log| log.enabled(&metadata));
+    /// assert!(!debug_enabled);
+    /// ```
+    pub fn with_current<R, F: FnOnce(&dyn Log) -> R>(&self, f: F) -> R {
+        match &*self.inner.load() {
+            Some(current) => f(&***current),
+            None => f(self.initial_log()),
+        }
+    }
+
+    /// Is a real logger installed?
+    ///
+    /// Returns `false` if the slave is the [`Dummy`] stub and the log messages are thrown away
+    /// (or if it's still the [early buffer][Reroute::const_with_buffer], waiting for the first
+    /// slave).
+    ///
+    /// Note that this is known only for slaves passed by their concrete type (eg. through
+    /// [`reroute`][Reroute::reroute] or [`clear`][Reroute::clear]). Boxed slaves are always
+    /// considered active, even if there's a [`Dummy`] inside the box.
+    ///
+    /// ```rust
+    /// use log_reroute::{Dummy, Reroute};
+    ///
+    /// let reroute = Reroute::new();
+    /// assert!(!reroute.is_active());
+    /// reroute.reroute_boxed(Box::new(Dummy));
+    /// assert!(reroute.is_active());
+    /// reroute.clear();
+    /// assert!(!reroute.is_active());
+    /// ```
+    pub fn is_active(&self) -> bool {
+        let info = &self.lock_state().info;
+        !info.dummy && !info.early
+    }
+
+    /// Provides more detailed information about the current slave.
+    ///
+    /// This can be used for health checks, reporting eg. that the logging is misconfigured:
+    ///
+    /// ```rust
+    /// let status = log_reroute::Reroute::new().status();
+    /// if status.dummy {
+    ///     eprintln!("Logs go nowhere (last changed at {:?})", status.last_swap);
+    /// }
+    /// ```
+    pub fn status(&self) -> RerouteStatus {
+        let info = &self.lock_state().info;
+        RerouteStatus {
+            dummy: info.dummy,
+            type_name: info.type_name,
+            last_swap: info.last_swap,
+            generation: self.generation(),
+        }
+    }
+
+    /// The generation of the current slave.
+    ///
+    /// This starts at 0 and is incremented every time the slave is changed (by any of the
+    /// `reroute` methods, [`swap`][Reroute::swap], [`clear`][Reroute::clear], …). It can be used
+    /// to cheaply find out the slave has changed, without comparing the [`Arc`]s from
+    /// [`get`][Reroute::get].
+    ///
+    /// # Ordering
+    ///
+    /// The generation is incremented right after the slave is replaced (with release ordering,
+    /// and it is read with acquire ordering). Therefore, if the generation is read *first* and
+    /// the slave by [`get`][Reroute::get] *after* that, the slave is at least as new as the
+    /// generation. Anything derived from the slave can then be cached together with the read
+    /// generation; in the worst case it is recomputed once more than necessary.
+    ///
+    /// ```rust
+    /// let reroute = log_reroute::Reroute::new();
+    /// let generation = reroute.generation();
+    /// reroute.clear();
+    /// assert_eq!(generation + 1, reroute.generation());
+    /// ```
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Installs this reroute into the [`log`] facade and sets the max level.
+    ///
+    /// This is for own instances, the global one can be installed by `init_at`. The level is set
+    /// only if the installation succeeds.
+    ///
+    /// ```rust
+    /// use log::LevelFilter;
+    /// use log_reroute::Reroute;
+    ///
+    /// let reroute: &'static Reroute = Box::leak(Box::new(Reroute::new()));
+    /// reroute.install_at(LevelFilter::Info).unwrap();
+    /// assert_eq!(LevelFilter::Info, log::max_level());
+    /// ```
+    pub fn install_at(&'static self, level: LevelFilter) -> Result<(), SetLoggerError> {
+        log::set_logger(self)?;
+        log::set_max_level(level);
+        Ok(())
+    }
+
+    /// Installs this reroute into the [`log`] facade, tolerating an already installed logger.
+    ///
+    /// Unlike [`log::set_logger`], this can be called repeatedly (eg. defensively from multiple
+    /// components). The result tells if this reroute is the installed logger and therefore if
+    /// changing its slave has any effect.
+    pub fn try_install(&'static self) -> InitResult {
+        if log::set_logger(self).is_ok() {
+            InitResult::Installed
+        } else if self.is_installed() {
+            InitResult::AlreadyReroute
+        } else {
+            InitResult::Foreign
+        }
+    }
+
+    /// Is this reroute the logger installed in the [`log`] facade?
+    pub fn is_installed(&self) -> bool {
+        ptr::eq(
+            log::logger() as *const dyn Log as *const u8,
+            self as *const Self as *const u8,
+        )
+    }
+
+    /// Turns announcing of the swaps on or off.
+    ///
+    /// When turned on, each change of the slave is recorded in the log itself. A record at the
+    /// `Info` level and with the [`SWAP_TARGET`] target, naming the old and new slaves (see
+    /// [`RerouteStatus::type_name`]) and the time of the change, is sent to the new slave right
+    /// after the swap. If the new slave is the [`Dummy`] (eg. after [`clear`][Reroute::clear]),
+    /// the record goes into the old one instead.
+    ///
+    /// The record is sent directly to the slave, bypassing the [layers][Reroute::layer] and
+    /// [pauses][Reroute::pause]. This is off by default.
+    ///
+    /// ```rust
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// use log::{Log, Metadata, Record};
+    /// use log_reroute::{Reroute, SWAP_TARGET};
+    ///
+    /// #[derive(Clone, Default)]
+    /// struct Collect(Arc<Mutex<Vec<String>>>);
+    ///
+    /// impl Log for Collect {
+    ///     fn enabled(&self, _: &Metadata) -> bool {
+    ///         true
+    ///     }
+    ///     fn log(&self, record: &Record) {
+    ///         assert_eq!(SWAP_TARGET, record.target());
+    ///         self.0.lock().unwrap().push(record.args().to_string());
+    ///     }
+    ///     fn flush(&self) {}
+    /// }
+    ///
+    /// let collect = Collect::default();
+    /// let reroute = Reroute::new();
+    /// reroute.announce_swaps(true);
+    /// reroute.reroute(collect.clone());
+    /// reroute.clear();
+    ///
+    /// let records = collect.0.lock().unwrap();
+    /// assert_eq!(2, records.len());
+    /// assert!(records[0].contains("from log_reroute::Dummy to "), "{}", records[0]);
+    /// assert!(records[1].contains("to log_reroute::Dummy"), "{}", records[1]);
+    /// ```
+    pub fn announce_swaps(&self, announce: bool) {
+        self.announce.store(announce, Ordering::Relaxed);
+    }
+
+    /// Sets what happens with the records logged from inside the slave into this same reroute.
+    ///
+    /// A slave logging through the reroute it's installed in (eg. a network logger reporting its
+    /// own connection errors through the global logger) would recurse, possibly forever. Such
+    /// nested records are detected (by a cheap thread-local check) and, by default, written to
+    /// stderr instead of the slave. They can be dropped, or let through for slaves that nest on
+    /// purpose; see [`Reentry`]. Either way, they are counted by
+    /// [`reentries`][Reroute::reentries]. The nested flushes are skipped unless allowed.
+    ///
+    /// Logging into a different reroute from inside a slave is not affected.
+    ///
+    /// ```rust
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    ///
+    /// use log::{Level, Log, Metadata, Record};
+    /// use log_reroute::{Reentry, Reroute};
+    ///
+    /// static REROUTE: Reroute = Reroute::const_new();
+    /// static RECEIVED: AtomicUsize = AtomicUsize::new(0);
+    ///
+    /// /// Reports each record it gets through the reroute, recursing without the guard.
+    /// struct Chatty;
+    ///
+    /// impl Log for Chatty {
+    ///     fn enabled(&self, _: &Metadata) -> bool {
+    ///         true
+    ///     }
+    ///     fn log(&self, record: &Record) {
+    ///         RECEIVED.fetch_add(1, Ordering::Relaxed);
+    ///         REROUTE.log(
+    ///             &Record::builder()
+    ///                 .level(Level::Debug)
+    ///                 .args(format_args!("Sent {}", record.args()))
+    ///                 .build(),
+    ///         );
+    ///     }
+    ///     fn flush(&self) {}
+    /// }
+    ///
+    /// REROUTE.set_reentry(Reentry::Drop);
+    /// REROUTE.reroute(Chatty);
+    /// let record = Record::builder().level(Level::Info).build();
+    /// REROUTE.log(&record);
+    /// REROUTE.log(&record);
+    /// assert_eq!(2, RECEIVED.load(Ordering::Relaxed));
+    /// assert_eq!(2, REROUTE.reentries());
+    ///
+    /// // Nesting on purpose, up to the limit of nesting
+    /// REROUTE.set_reentry(Reentry::Allow);
+    /// REROUTE.log(&record);
+    /// assert!(RECEIVED.load(Ordering::Relaxed) > 3);
+    /// ```
+    pub fn set_reentry(&self, reentry: Reentry) {
+        self.reentry.store(reentry.to_u8(), Ordering::Relaxed);
+    }
+
+    /// How many records were logged from inside the slave into this same reroute.
+    ///
+    /// See [`set_reentry`][Reroute::set_reentry].
+    pub fn reentries(&self) -> u64 {
+        self.reentries.load(Ordering::Relaxed)
+    }
+
+    /// Turns on or off counting of the records.
+    ///
+    /// When on, each record passing the [filter][Reroute::set_filter] is counted by its level,
+    /// and the ones that go to the [`Dummy`] are also counted as discarded (the explicitly set
+    /// thread overrides don't matter for that). The counts belong to the reroute, so they stay
+    /// across reroutes. They can be read by [`stats`][Reroute::stats]. Counting is off by default,
+    /// so it costs nothing unless wanted.
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use std::thread;
+    ///
+    /// use log::{Level, Log, Record};
+    /// use log_reroute::{FnLog, Reroute};
+    ///
+    /// let reroute = Arc::new(Reroute::new());
+    /// reroute.set_counting(true);
+    /// reroute.log(&Record::builder().level(Level::Warn).build());
+    ///
+    /// reroute.reroute(FnLog::new(|_: &Record| ()));
+    /// let threads = (0..4)
+    ///     .map(|_| {
+    ///         let reroute = Arc::clone(&reroute);
+    ///         thread::spawn(move || {
+    ///             for i in 0..1000 {
+    ///                 let level = if i % 10 == 0 { Level::Error } else { Level::Info };
+    ///                 reroute.log(&Record::builder().level(level).build());
+    ///             }
+    ///         })
+    ///     })
+    ///     .collect::<Vec<_>>();
+    /// for thread in threads {
+    ///     thread.join().unwrap();
+    /// }
+    ///
+    /// let stats = reroute.stats();
+    /// assert_eq!((400, 1, 3600), (stats.error, stats.warn, stats.info));
+    /// assert_eq!(4001, stats.total());
+    /// // Only the first one went to the initial dummy
+    /// assert_eq!(1, stats.discarded);
+    ///
+    /// reroute.reset_stats();
+    /// assert_eq!(0, reroute.stats().total());
+    /// ```
+    pub fn set_counting(&self, counting: bool) {
+        self.counting.store(counting, Ordering::Relaxed);
+    }
+
+    /// The counts of the records, see [`set_counting`][Reroute::set_counting].
+    pub fn stats(&self) -> Stats {
+        self.counters.snapshot()
+    }
+
+    /// Sets all the counts of the records back to zero.
+    pub fn reset_stats(&self) {
+        self.counters.reset();
+    }
+
+    /// Is this call from inside our own slave, to be handled by the reentry policy?
+    #[inline]
+    fn reentered(&self) -> Option<Reentry> {
+        let reentry = Reentry::from_u8(self.reentry.load(Ordering::Relaxed));
+        if reentry != Reentry::Allow && cycle::inside_of(self) {
+            Some(reentry)
+        } else {
+            None
+        }
+    }
+
+    /// Handles a record logged from inside our own slave, according to the policy.
+    #[cold]
+    fn log_reentered(&self, reentry: Reentry, record: &Record) {
+        self.reentries.fetch_add(1, Ordering::Relaxed);
+        if reentry == Reentry::Stderr {
+            // Still nested, in case formatting the record logs again.
+            cycle::nest(self, (), || StderrLog.log(record));
+        }
+    }
+
+    /// Returns the last few changes of the slave, the oldest first.
+    ///
+    /// Up to [`DEFAULT_HISTORY_CAPACITY`] changes are remembered, this can be changed by
+    /// [`set_history_capacity`][Reroute::set_history_capacity]. The history is kept by the
+    /// swapping operations only, it doesn't slow down the logging.
+    ///
+    /// ```rust
+    /// use log_reroute::{Dummy, Reroute};
+    ///
+    /// let reroute = Reroute::new();
+    /// reroute.reroute(Dummy);
+    /// reroute.reroute_boxed(Box::new(Dummy));
+    ///
+    /// let history = reroute.history();
+    /// assert_eq!(2, history.len());
+    /// assert_eq!("log_reroute::Dummy", history[0].type_name);
+    /// assert_eq!("boxed", history[1].type_name);
+    /// assert_eq!(reroute.generation(), history[1].generation);
+    /// ```
+    pub fn history(&self) -> Vec<SwapEvent> {
+        self.lock_state().history.events()
+    }
+
+    /// Changes how many past swaps are remembered.
+    ///
+    /// If there are more in the [history][Reroute::history] already, the oldest ones are
+    /// forgotten. Setting it to 0 turns the history off.
+    pub fn set_history_capacity(&self, capacity: usize) {
+        self.lock_state().history.set_capacity(capacity);
+    }
 }
 
-/// A global [`Reroute`](struct.Reroute.html) object.
-///
-/// This one is manipulated by the global functions:
-///
-/// * [`init`](fn.init.html)
-/// * [`reroute`](fn.reroute.html)
-/// * [`reroute_boxed`](fn.reroute_boxed.html)
-pub static REROUTE: Lazy<Reroute> = Lazy::new(Reroute::default);
+impl Log for Reroute {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        if !self.gate_passes(metadata) || !self.passes(metadata) {
+            return false;
+        }
+        // No deferred work here, the lazy slave says yes on its own.
+        cycle::nest(self, false, || self.dispatch(|log| log.enabled(metadata)))
+    }
+    fn log(&self, record: &Record) {
+        if !self.gate_passes(record.metadata()) || !self.passes(record.metadata()) {
+            return;
+        }
+        if self.counting.load(Ordering::Relaxed) {
+            self.counters
+                .count(record.level(), self.dummy.load(Ordering::Relaxed));
+        }
+        if let Some(reentry) = self.reentered() {
+            return self.log_reentered(reentry, record);
+        }
+        self.run_deferred();
+        if self.paused.load(Ordering::Relaxed) && self.hold(record) {
+            return;
+        }
+        cycle::nest(self, (), || self.dispatch(|log| log.log(record)))
+    }
+    fn flush(&self) {
+        if self.reentered().is_some() {
+            return;
+        }
+        self.run_deferred();
+        if self.paused.load(Ordering::Relaxed) && !self.overridden() {
+            return;
+        }
+        cycle::nest(self, (), || self.dispatch(|log| log.flush()))
+    }
+}
 
-/// Installs the global [`Reroute`](struct.Reroute.html) instance into the
-/// [`log`](https://crates.io/crates/log) facade.
-///
-/// Note that the default slave is [`Dummy`](struct.Dummy.html) and you need to call
-/// [`reroute`](fn.reroute.html) or [`reroute_boxed`](fn.reroute_boxed.html).
-pub fn init() -> Result<(), SetLoggerError> {
-    log::set_logger(&*REROUTE)
+impl Debug for Reroute {
+    /// Names the type of the current slave.
+    ///
+    /// Boxed slaves are shown as `"boxed"`, since their type is not known (see
+    /// [`RerouteStatus::type_name`]).
+    ///
+    /// ```rust
+    /// let reroute = log_reroute::Reroute::new();
+    /// assert_eq!(
+    ///     r#"Reroute { slave: "log_reroute::Dummy", generation: 0 }"#,
+    ///     format!("{:?}", reroute)
+    /// );
+    /// ```
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        let info = &self.lock_state().info;
+        fmt.debug_struct("Reroute")
+            .field("slave", &info.type_name)
+            .field("generation", &self.generation())
+            .finish()
+    }
 }
 
-/// Changes the slave of the global [`Reroute`](struct.Reroute.html) instance.
-///
-/// If you have a boxed logger, use [`reroute_boxed`](fn.reroute_boxed.html).
-pub fn reroute<L: Log + 'static>(log: L) {
-    REROUTE.reroute(log);
+impl Default for Reroute {
+    /// Creates a reroute with a [`Dummy`](struct.Dummy.html) slave logger.
+    fn default() -> Self {
+        Self::const_new()
+    }
 }
 
-/// Changes the slave of the global [`Reroute`](struct.Reroute.html) instance.
-pub fn reroute_boxed(log: Box<dyn Log>) {
-    REROUTE.reroute_boxed(log)
+impl From<Box<dyn Log>> for Reroute {
+    fn from(log: Box<dyn Log>) -> Self {
+        Self::with_boxed(log)
+    }
+}
+
+impl From<Arc<Box<dyn Log>>> for Reroute {
+    fn from(log: Arc<Box<dyn Log>>) -> Self {
+        Self::with_info(log, SlaveInfo::boxed())
+    }
 }
@@ -0,0 +1,62 @@
+//! Thread-local overrides of the slave.
+//!
+//! See [`Reroute::set_thread_override`][crate::Reroute::set_thread_override].
+
+use std::cell::{Cell, RefCell};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::Slave;
+
+thread_local! {
+    /// Is there any override in this thread?
+    ///
+    /// Checking this is cheaper than looking into the overrides.
+    static ACTIVE: Cell<bool> = const { Cell::new(false) };
+
+    /// The overrides, with IDs of the reroutes they belong to.
+    static OVERRIDES: RefCell<Vec<(u64, Slave)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Source of IDs for the reroutes that use overrides.
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+pub(crate) fn new_id() -> u64 {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+#[inline]
+pub(crate) fn active() -> bool {
+    // During thread destruction, pretend there's nothing.
+    ACTIVE.try_with(Cell::get).unwrap_or(false)
+}
+
+pub(crate) fn get(id: u64) -> Option<Slave> {
+    OVERRIDES
+        .try_with(|overrides| {
+            let overrides = overrides.try_borrow().ok()?;
+            overrides
+                .iter()
+                .find(|(o, _)| *o == id)
+                .map(|(_, slave)| Slave::clone(slave))
+        })
+        .ok()
+        .flatten()
+}
+
+/// Sets the override, returning the previous one.
+pub(crate) fn set(id: u64, slave: Slave) -> Option<Slave> {
+    let previous = remove(id);
+    OVERRIDES.with(|overrides| overrides.borrow_mut().push((id, slave)));
+    ACTIVE.with(|active| active.set(true));
+    previous
+}
+
+pub(crate) fn remove(id: u64) -> Option<Slave> {
+    OVERRIDES.with(|overrides| {
+        let mut overrides = overrides.borrow_mut();
+        let pos = overrides.iter().position(|(o, _)| *o == id)?;
+        let (_, removed) = overrides.swap_remove(pos);
+        ACTIVE.with(|active| active.set(!overrides.is_empty()));
+        Some(removed)
+    })
+}
@@ -0,0 +1,161 @@
+//! The logfmt format.
+
+use std::fmt::Display;
+use std::io::{self, Write};
+use std::time::SystemTime;
+
+use log::Record;
+
+use crate::format::{Format, Rfc3339};
+
+/// Does the value need quoting?
+fn needs_quotes(value: &str) -> bool {
+    value.is_empty()
+        || value
+            .chars()
+            .any(|c| c <= ' ' || c == '=' || c == '"' || c == '\\' || c.is_control())
+}
+
+/// Writes the value, quoted and escaped if necessary.
+fn value(out: &mut dyn Write, value: &str) -> io::Result<()> {
+    if !needs_quotes(value) {
+        return out.write_all(value.as_bytes());
+    }
+    out.write_all(b"\"")?;
+    for c in value.chars() {
+        match c {
+            '"' => out.write_all(b"\\\"")?,
+            '\\' => out.write_all(b"\\\\")?,
+            '\n' => out.write_all(b"\\n")?,
+            '\r' => out.write_all(b"\\r")?,
+            '\t' => out.write_all(b"\\t")?,
+            c if c.is_control() => write!(out, "\\u{{{:x}}}", u32::from(c))?,
+            c => write!(out, "{}", c)?,
+        }
+    }
+    out.write_all(b"\"")
+}
+
+/// Writes the key, replacing the unsafe characters.
+fn key(out: &mut dyn Write, key: &str) -> io::Result<()> {
+    if key.is_empty() {
+        return out.write_all(b"_");
+    }
+    for c in key.chars() {
+        let c = if c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-') {
+            c
+        } else {
+            '_'
+        };
+        write!(out, "{}", c)?;
+    }
+    Ok(())
+}
+
+fn pair(out: &mut dyn Write, name: &str, content: &dyn Display) -> io::Result<()> {
+    out.write_all(b" ")?;
+    key(out, name)?;
+    out.write_all(b"=")?;
+    value(out, &content.to_string())
+}
+
+/// Records as [logfmt](https://brandur.org/logfmt) lines.
+///
+/// Each line is `ts=… level=… target=… msg=…`, with the time of writing in RFC 3339 in UTC
+/// and the level in lowercase. The values containing spaces, `=`, quotes, backslashes or control
+/// characters (and the empty ones) are quoted, with the quotes, backslashes and control
+/// characters escaped (`\"`, `\\`, `\n`, `\r`, `\t` and `\u{…}` for the rest). A line therefore
+/// never contains a newline.
+///
+/// With the `kv` feature, the key-values of the record are appended as additional pairs. Their
+/// keys are sanitized: any character other than ASCII letters, digits, `_`, `.` and `-` is
+/// replaced by `_` (and an empty key becomes `_`).
+///
+/// ```rust
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// use log::{Level, Record};
+/// use log_reroute::{Format, Logfmt};
+///
+/// let mut out = Vec::new();
+/// let time = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+/// let record = Record::builder()
+///     .level(Level::Info)
+///     .target("app")
+///     .args(format_args!("Said \"a=b\"\nthen left"))
+///     .build();
+/// Logfmt.format(&mut out, &record, time).unwrap();
+/// assert_eq!(
+///     "ts=2023-11-14T22:13:20.123Z level=info target=app msg=\"Said \\\"a=b\\\"\\nthen left\"\n",
+///     String::from_utf8(out).unwrap(),
+/// );
+/// ```
+///
+/// With the key-values:
+///
+/// ```rust
+/// # #![cfg_attr(not(feature = "kv"), allow(unused))]
+/// # #[cfg(feature = "kv")]
+/// # fn main() {
+/// use log::LevelFilter;
+/// use log_reroute::{Logfmt, WriteLog};
+///
+/// let out = WriteLog::new(Vec::new()).with_format(Logfmt);
+/// log::set_max_level(LevelFilter::Info);
+/// log::info!(logger: &out, target: "app", port = 8080, peer = "my host"; "listening");
+///
+/// let line = String::from_utf8(out.into_inner()).unwrap();
+/// assert!(line.ends_with(" level=info target=app msg=listening port=8080 peer=\"my host\"\n"));
+/// # }
+/// # #[cfg(not(feature = "kv"))]
+/// # fn main() {}
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Logfmt;
+
+impl Format for Logfmt {
+    fn format(&self, out: &mut dyn Write, record: &Record, time: SystemTime) -> io::Result<()> {
+        write!(out, "ts={}", Rfc3339(time))?;
+        let level = record.level().as_str().to_ascii_lowercase();
+        pair(out, "level", &level)?;
+        pair(out, "target", &record.target())?;
+        pair(out, "msg", record.args())?;
+        #[cfg(feature = "kv")]
+        kv::write(out, record)?;
+        out.write_all(b"\n")
+    }
+}
+
+#[cfg(feature = "kv")]
+mod kv {
+    use std::io::{self, Write};
+
+    use log::kv::{Error, Key, Value, VisitSource};
+    use log::Record;
+
+    use super::pair;
+
+    struct Pairs<'a> {
+        out: &'a mut dyn Write,
+        result: io::Result<()>,
+    }
+
+    impl<'kvs> VisitSource<'kvs> for Pairs<'_> {
+        fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), Error> {
+            self.result = pair(self.out, key.as_str(), &value);
+            match &self.result {
+                Ok(()) => Ok(()),
+                Err(_) => Err(Error::msg("Failed to write")),
+            }
+        }
+    }
+
+    pub(super) fn write(out: &mut dyn Write, record: &Record) -> io::Result<()> {
+        let mut pairs = Pairs {
+            out,
+            result: Ok(()),
+        };
+        let _ = record.key_values().visit(&mut pairs);
+        pairs.result
+    }
+}
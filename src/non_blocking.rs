@@ -0,0 +1,351 @@
+//! Writing to a file in a background thread.
+
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+use crate::file_log::FileWriter;
+use crate::format::{Format, Plain};
+use crate::worker::Worker;
+use crate::{FileOptions, WorkerGuard};
+
+/// How often the dropped lines are reported (and the file flushed when there's nothing to write).
+const IDLE: Duration = Duration::from_secs(1);
+
+/// How long [`Backpressure::Block`] waits for room in the queue.
+const BLOCK_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The longest sleep between the attempts to queue a line.
+const MAX_BACKOFF: Duration = Duration::from_millis(1);
+
+/// What a [`NonBlockingFileLog`] does with a line when its queue is full.
+///
+/// Set by [`FileOptions::backpressure`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Backpressure {
+    /// The logging thread waits until there's room.
+    ///
+    /// It waits at most a second, the line is dropped (and counted) if the queue doesn't make
+    /// room by then, so a stuck writer doesn't stop all the logging threads forever.
+    #[default]
+    Block,
+    /// The line is dropped (and counted).
+    Drop,
+}
+
+enum Message {
+    Line(Vec<u8>),
+    /// Flush and confirm it's done.
+    Flush(SyncSender<()>),
+    Stop,
+}
+
+#[derive(Default)]
+struct Counters {
+    dropped: AtomicU64,
+    errors: AtomicU64,
+}
+
+/// The part living in the writer thread.
+struct Writer<F> {
+    file: FileWriter,
+    format: Arc<F>,
+    counters: Arc<Counters>,
+    reported: u64,
+    last_report: Instant,
+    stopping: Arc<AtomicBool>,
+}
+
+impl<F: Format> Writer<F> {
+    fn count(&self, result: io::Result<()>) {
+        if result.is_err() {
+            self.counters.errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn write(&mut self, line: &[u8]) {
+        let result = self.file.write_all(line);
+        self.count(result);
+        if self.file.take_failure().is_some() {
+            self.counters.errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Writes a line about the newly dropped lines, if there are any.
+    fn report(&mut self) {
+        self.last_report = Instant::now();
+        let dropped = self.counters.dropped.load(Ordering::Relaxed);
+        if dropped == self.reported {
+            return;
+        }
+        let mut line = Vec::new();
+        let _ = self.format.format(
+            &mut line,
+            &Record::builder()
+                .level(Level::Warn)
+                .target("log_reroute")
+                .args(format_args!(
+                    "{} records were dropped because the queue was full",
+                    dropped - self.reported
+                ))
+                .build(),
+            SystemTime::now(),
+        );
+        self.reported = dropped;
+        self.write(&line);
+    }
+
+    fn flush(&mut self) {
+        self.report();
+        let result = self.file.flush();
+        self.count(result);
+    }
+
+    /// Handles one message, returns if there'll be more.
+    fn step(&mut self, messages: &Receiver<Message>) -> bool {
+        let message = if self.stopping.load(Ordering::Acquire) {
+            Ok(Message::Stop)
+        } else {
+            messages.recv_timeout(IDLE)
+        };
+        match message {
+            Ok(Message::Line(line)) => self.write(&line),
+            Ok(Message::Flush(done)) => {
+                self.flush();
+                let _ = done.send(());
+            }
+            Ok(Message::Stop) | Err(RecvTimeoutError::Disconnected) => {
+                for message in messages.try_iter() {
+                    match message {
+                        Message::Line(line) => self.write(&line),
+                        Message::Flush(done) => {
+                            let _ = done.send(());
+                        }
+                        Message::Stop => (),
+                    }
+                }
+                self.flush();
+                return false;
+            }
+            Err(RecvTimeoutError::Timeout) => self.flush(),
+        }
+        // Even when busy, so the drops are reported close to when they happen.
+        if self.last_report.elapsed() >= IDLE {
+            self.report();
+        }
+        true
+    }
+}
+
+/// A logger writing into a file in a background thread.
+///
+/// The logging threads only format the records into lines and queue them, a single background
+/// thread (a [`Worker`]) writes them into the file. When the queue is full (see
+/// [`FileOptions::queue_capacity`]), the logging threads either wait (for a while) or the lines
+/// are dropped, by the [`Backpressure`]; the dropped lines are counted (see
+/// [`dropped`][NonBlockingFileLog::dropped]) and reported by a line in the file itself, at most
+/// once a second. The background thread flushes the file when there's nothing to write for a
+/// second, a [flush][Log::flush] waits until the lines queued before it are written and flushed.
+///
+/// The thread runs as long as the returned [`WorkerGuard`] is kept. Dropping it writes all the
+/// queued lines and waits for the thread. The lines logged after that are lost (and counted as
+/// dropped).
+///
+/// ```rust
+/// use std::fs;
+///
+/// use log::{Level, Log, Record};
+/// use log_reroute::{FileOptions, NonBlockingFileLog};
+///
+/// let dir = tempfile::tempdir().unwrap();
+/// let path = dir.path().join("app.log");
+/// let (log, guard) = NonBlockingFileLog::open(&path, FileOptions::new()).unwrap();
+/// for i in 0..100 {
+///     log.log(
+///         &Record::builder()
+///             .level(Level::Info)
+///             .target("app")
+///             .args(format_args!("Message {}", i))
+///             .build(),
+///     );
+/// }
+/// // Writes everything before returning
+/// drop(guard);
+/// let content = fs::read_to_string(&path).unwrap();
+/// assert_eq!(100, content.lines().count());
+/// assert!(content.ends_with("INFO app: Message 99\n"));
+/// ```
+///
+/// Dropping the lines when the queue is full:
+///
+/// ```rust
+/// use std::fs;
+///
+/// use log::{Level, Log, Record};
+/// use log_reroute::{Backpressure, FileOptions, NonBlockingFileLog};
+///
+/// let dir = tempfile::tempdir().unwrap();
+/// let path = dir.path().join("app.log");
+/// let options = FileOptions::new()
+///     .queue_capacity(1)
+///     .backpressure(Backpressure::Drop);
+/// let (log, guard) = NonBlockingFileLog::open(&path, options).unwrap();
+/// for _ in 0..1000 {
+///     log.log(&Record::builder().level(Level::Info).build());
+/// }
+/// drop(guard);
+/// let dropped = log.dropped();
+/// let content = fs::read_to_string(&path).unwrap();
+/// if dropped > 0 {
+///     assert!(content.contains("records were dropped because the queue was full"));
+/// }
+/// let written = content.lines().filter(|l| l.starts_with("INFO ")).count() as u64;
+/// assert_eq!(1000, written + dropped);
+/// ```
+pub struct NonBlockingFileLog<F = Plain> {
+    path: PathBuf,
+    level: LevelFilter,
+    backpressure: Backpressure,
+    format: Arc<F>,
+    sender: SyncSender<Message>,
+    counters: Arc<Counters>,
+}
+
+impl NonBlockingFileLog {
+    /// Opens (or creates) the file and starts the background thread.
+    pub fn open<P: AsRef<Path>>(path: P, options: FileOptions) -> io::Result<(Self, WorkerGuard)> {
+        Self::open_with_format(path, options, Plain::new())
+    }
+}
+
+impl<F: Format + 'static> NonBlockingFileLog<F> {
+    /// Opens (or creates) the file and starts the background thread, writing in the format.
+    pub fn open_with_format<P: AsRef<Path>>(
+        path: P,
+        options: FileOptions,
+        format: F,
+    ) -> io::Result<(Self, WorkerGuard)> {
+        let path = path.as_ref();
+        let file = options.open(path)?;
+        let format = Arc::new(format);
+        let counters = Arc::new(Counters::default());
+        let (sender, receiver) = mpsc::sync_channel(options.capacity);
+        let stopping = Arc::new(AtomicBool::new(false));
+        let mut writer = Writer {
+            file,
+            format: Arc::clone(&format),
+            counters: Arc::clone(&counters),
+            reported: 0,
+            last_report: Instant::now(),
+            stopping: Arc::clone(&stopping),
+        };
+        let stop = sender.clone();
+        let worker = Worker::spawn(
+            move || writer.step(&receiver),
+            move || {
+                stopping.store(true, Ordering::Release);
+                // If the queue is full, the writer sees the flag after the next line.
+                let _ = stop.try_send(Message::Stop);
+            },
+        )?;
+        let log = Self {
+            path: path.to_owned(),
+            level: options.max_level(),
+            backpressure: options.backpressure,
+            format,
+            sender,
+            counters,
+        };
+        Ok((log, WorkerGuard::new(Some(worker))))
+    }
+
+    /// The path of the file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// How many lines were dropped.
+    ///
+    /// These are the lines that didn't fit into the full queue with [`Backpressure::Drop`] and
+    /// the ones logged after the background thread ended.
+    pub fn dropped(&self) -> u64 {
+        self.counters.dropped.load(Ordering::Relaxed)
+    }
+
+    /// How many times writing or flushing failed.
+    pub fn errors(&self) -> u64 {
+        self.counters.errors.load(Ordering::Relaxed)
+    }
+
+    fn drop_line(&self) {
+        self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Queues the line, waiting for room up to [`BLOCK_TIMEOUT`].
+    fn send_blocking(&self, line: Vec<u8>) -> bool {
+        let deadline = Instant::now() + BLOCK_TIMEOUT;
+        let mut backoff = Duration::from_micros(10);
+        let mut message = Message::Line(line);
+        loop {
+            match self.sender.try_send(message) {
+                Ok(()) => return true,
+                Err(TrySendError::Disconnected(_)) => return false,
+                Err(TrySendError::Full(returned)) => message = returned,
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            thread::sleep(backoff.min(deadline - now));
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+}
+
+impl<F> Debug for NonBlockingFileLog<F> {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_struct("NonBlockingFileLog")
+            .field("path", &self.path)
+            .field("level", &self.level)
+            .field("backpressure", &self.backpressure)
+            .field("dropped", &self.counters.dropped.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+impl<F: Format + 'static> Log for NonBlockingFileLog<F> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let mut line = Vec::new();
+        let _ = self.format.format(&mut line, record, SystemTime::now());
+        let sent = match self.backpressure {
+            Backpressure::Block => self.send_blocking(line),
+            Backpressure::Drop => match self.sender.try_send(Message::Line(line)) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => false,
+            },
+        };
+        if !sent {
+            self.drop_line();
+        }
+    }
+    fn flush(&self) {
+        let (done, wait) = mpsc::sync_channel(1);
+        if self.sender.send(Message::Flush(done)).is_ok() {
+            let _ = wait.recv();
+        }
+    }
+}
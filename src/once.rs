@@ -0,0 +1,179 @@
+//! Passing each record only once.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fmt::{Debug, Formatter, Result as FmtResult, Write};
+use std::hash::{Hash, Hasher};
+use std::sync::{Mutex, PoisonError};
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+use crate::dedup::HashWriter;
+
+/// How many keys a [`Once`] remembers by default.
+pub const DEFAULT_ONCE_KEYS: usize = 4096;
+
+/// A wrapper passing each record only the first time.
+///
+/// The records are identified by their callsite (the module path, file and line) or, with
+/// [`by_message`][Once::by_message], by their level, target and formatted message. Only the
+/// first record of each is passed, the later ones are silently dropped. This is for the messages
+/// useful exactly once per process, like warnings about deprecated configuration.
+///
+/// The keys are remembered as hashes. At most [`DEFAULT_ONCE_KEYS`] (or what is set by
+/// [`with_capacity`][Once::with_capacity]) are kept; when there'd be more, all are forgotten (so
+/// each record can pass once again). They can also be forgotten explicitly by
+/// [`reset`][Once::reset], eg. on configuration reload. The severe records can be exempt by
+/// [`always`][Once::always].
+///
+/// ```rust
+/// use std::sync::{Arc, Mutex};
+///
+/// use log::{Level, LevelFilter, Log, Metadata, Record};
+/// use log_reroute::Once;
+///
+/// #[derive(Clone, Default)]
+/// struct Messages(Arc<Mutex<Vec<String>>>);
+///
+/// impl Log for Messages {
+///     fn enabled(&self, _: &Metadata) -> bool {
+///         true
+///     }
+///     fn log(&self, record: &Record) {
+///         self.0.lock().unwrap().push(record.args().to_string());
+///     }
+///     fn flush(&self) {}
+/// }
+///
+/// fn log(log: &dyn Log, level: Level, line: u32, msg: &str) {
+///     log.log(
+///         &Record::builder()
+///             .level(level)
+///             .module_path(Some("app"))
+///             .line(Some(line))
+///             .args(format_args!("{}", msg))
+///             .build(),
+///     );
+/// }
+///
+/// let messages = Messages::default();
+/// let once = Once::new(messages.clone()).always(LevelFilter::Error);
+/// for _ in 0..3 {
+///     log(&once, Level::Warn, 10, "Option X is deprecated");
+///     log(&once, Level::Error, 20, "Failed");
+/// }
+/// once.reset();
+/// log(&once, Level::Warn, 10, "Option X is deprecated");
+/// assert_eq!(
+///     vec![
+///         "Option X is deprecated",
+///         "Failed",
+///         "Failed",
+///         "Failed",
+///         "Option X is deprecated",
+///     ],
+///     *messages.0.lock().unwrap(),
+/// );
+/// ```
+pub struct Once<L> {
+    inner: L,
+    by_message: bool,
+    always: LevelFilter,
+    capacity: usize,
+    seen: Mutex<HashSet<u64>>,
+}
+
+impl<L: Log> Once<L> {
+    /// Wraps the logger, identifying the records by their callsites.
+    pub fn new(inner: L) -> Self {
+        Self {
+            inner,
+            by_message: false,
+            always: LevelFilter::Off,
+            capacity: DEFAULT_ONCE_KEYS,
+            seen: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Identifies the records by their level, target and formatted message instead.
+    pub fn by_message(mut self) -> Self {
+        self.by_message = true;
+        self
+    }
+
+    /// Always passes the records of this level and more severe.
+    pub fn always(mut self, level: LevelFilter) -> Self {
+        self.always = level;
+        self
+    }
+
+    /// Sets how many keys are remembered.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Forgets all the records, so each can pass once again.
+    pub fn reset(&self) {
+        self.seen
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+
+    /// The wrapped logger.
+    pub fn inner(&self) -> &L {
+        &self.inner
+    }
+
+    fn key(&self, record: &Record) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        if self.by_message {
+            record.level().hash(&mut hasher);
+            record.target().hash(&mut hasher);
+            let _ = HashWriter(&mut hasher).write_fmt(*record.args());
+        } else {
+            record.module_path().hash(&mut hasher);
+            record.file().hash(&mut hasher);
+            record.line().hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    fn first(&self, record: &Record) -> bool {
+        // The key may need the message formatted, outside of the lock of the seen set.
+        let key = self.key(record);
+        let mut seen = self.seen.lock().unwrap_or_else(PoisonError::into_inner);
+        if seen.contains(&key) {
+            return false;
+        }
+        if seen.len() >= self.capacity {
+            seen.clear();
+        }
+        seen.insert(key)
+    }
+}
+
+impl<L> Debug for Once<L> {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_struct("Once")
+            .field("by_message", &self.by_message)
+            .field("always", &self.always)
+            .field("capacity", &self.capacity)
+            .finish()
+    }
+}
+
+impl<L: Log> Log for Once<L> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+    fn log(&self, record: &Record) {
+        if record.level() <= self.always || self.first(record) {
+            self.inner.log(record);
+        }
+    }
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
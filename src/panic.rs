@@ -0,0 +1,77 @@
+//! Logging of panics.
+
+use std::any::Any;
+#[cfg(feature = "backtrace")]
+use std::backtrace::Backtrace;
+use std::panic::{self, PanicHookInfo};
+
+use log::{Level, Log, Record};
+
+use crate::{cycle, Reroute};
+
+/// Extracts the message of the panic.
+fn payload<'a>(info: &'a PanicHookInfo) -> &'a str {
+    message(info.payload())
+}
+
+/// Extracts the message from the payload of a panic.
+pub(crate) fn message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg
+    } else {
+        "Box<dyn Any>"
+    }
+}
+
+fn log_panic(reroute: &Reroute, info: &PanicHookInfo) {
+    let thread = std::thread::current();
+    let thread = thread.name().unwrap_or("<unnamed>");
+    let location = info
+        .location()
+        .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()))
+        .unwrap_or_else(|| "unknown location".to_owned());
+    #[cfg(feature = "backtrace")]
+    let backtrace = format!("\n{}", Backtrace::force_capture());
+    #[cfg(not(feature = "backtrace"))]
+    let backtrace = "";
+    reroute.log(
+        &Record::builder()
+            .level(Level::Error)
+            .target("panic")
+            .file(info.location().map(|l| l.file()))
+            .line(info.location().map(|l| l.line()))
+            .args(format_args!(
+                "thread '{}' panicked at {}: {}{}",
+                thread,
+                location,
+                payload(info),
+                backtrace,
+            ))
+            .build(),
+    );
+    reroute.flush();
+}
+
+impl Reroute {
+    /// Logs panics through this reroute.
+    ///
+    /// Installs a panic hook that sends an `Error` record (with the `panic` target) with the panic
+    /// message and location to the slave and flushes it. With the `backtrace` feature, the record
+    /// also contains a backtrace. Then it calls the previously installed hook, so it composes with
+    /// the hooks of other crates (and the default one still prints the panic to stderr).
+    ///
+    /// If the panic happens inside a slave of a reroute (on the same thread), it is not logged, as
+    /// the slave is likely in an inconsistent state and logging into it would risk a deadlock.
+    /// Note that if a slave panics while logging the panic, Rust aborts the process.
+    pub fn install_panic_hook(&'static self) {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if !cycle::inside() {
+                log_panic(self, info);
+            }
+            previous(info);
+        }));
+    }
+}
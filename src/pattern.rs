@@ -0,0 +1,312 @@
+//! Lines formatted by a pattern.
+
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io::{self, Write};
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::Record;
+
+use crate::format::{Civil, Format, Rfc3339};
+use crate::thread_info::with_thread_name;
+
+/// An error in a [`Pattern`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PatternError {
+    fragment: String,
+    position: usize,
+}
+
+impl PatternError {
+    fn new(pattern: &str, start: usize, end: usize) -> Self {
+        Self {
+            fragment: pattern[start..end].to_owned(),
+            position: start,
+        }
+    }
+
+    /// The part of the pattern that was not understood.
+    pub fn fragment(&self) -> &str {
+        &self.fragment
+    }
+
+    /// The position of the fragment in the pattern (in bytes).
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl Display for PatternError {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        write!(
+            fmt,
+            "Invalid placeholder '{}' at position {}",
+            self.fragment, self.position
+        )
+    }
+}
+
+impl Error for PatternError {}
+
+#[derive(Clone, Debug)]
+enum Time {
+    Literal(String),
+    Year,
+    Month,
+    Day,
+    Hour,
+    Minute,
+    Second,
+    /// The fraction of the second, with the dot and this many digits.
+    Fraction(u32),
+    Unix,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Field {
+    Level,
+    Target,
+    Module,
+    File,
+    Line,
+    Msg,
+    Thread,
+    Pid,
+}
+
+#[derive(Clone, Debug)]
+enum Piece {
+    Literal(String),
+    Rfc3339,
+    Time(Vec<Time>),
+    Field(Field, usize),
+}
+
+fn literal(pieces: &mut Vec<Piece>, c: char) {
+    match pieces.last_mut() {
+        Some(Piece::Literal(s)) => s.push(c),
+        _ => pieces.push(Piece::Literal(c.to_string())),
+    }
+}
+
+/// Parses the strftime-like time format.
+fn time(format: &str) -> Option<Vec<Time>> {
+    let mut result = Vec::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            match result.last_mut() {
+                Some(Time::Literal(s)) => s.push(c),
+                _ => result.push(Time::Literal(c.to_string())),
+            }
+            continue;
+        }
+        let piece = match chars.next()? {
+            'Y' => Time::Year,
+            'm' => Time::Month,
+            'd' => Time::Day,
+            'H' => Time::Hour,
+            'M' => Time::Minute,
+            'S' => Time::Second,
+            's' => Time::Unix,
+            '%' => Time::Literal("%".to_owned()),
+            '.' => match (chars.next()?, chars.next()?) {
+                ('3', 'f') => Time::Fraction(3),
+                ('6', 'f') => Time::Fraction(6),
+                ('9', 'f') => Time::Fraction(9),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        result.push(piece);
+    }
+    Some(result)
+}
+
+/// Parses the inside of a placeholder.
+fn placeholder(content: &str) -> Option<Piece> {
+    let (name, spec) = match content.split_once(':') {
+        Some((name, spec)) => (name, Some(spec)),
+        None => (content, None),
+    };
+    let field = match name {
+        "ts" => {
+            return match spec {
+                None => Some(Piece::Rfc3339),
+                Some(spec) => time(spec).map(Piece::Time),
+            }
+        }
+        "level" => Field::Level,
+        "target" => Field::Target,
+        "module" => Field::Module,
+        "file" => Field::File,
+        "line" => Field::Line,
+        "msg" => Field::Msg,
+        "thread" => Field::Thread,
+        "pid" => Field::Pid,
+        _ => return None,
+    };
+    let width = match spec {
+        None => 0,
+        Some(width) => width.parse().ok()?,
+    };
+    Some(Piece::Field(field, width))
+}
+
+/// Records formatted by a pattern.
+///
+/// The pattern is the layout of a line (without the newline, which is added). It is parsed once
+/// when created, any error is reported then. The pattern may contain these placeholders:
+///
+/// * `{ts}`: The time of writing, in RFC 3339 in UTC with milliseconds
+///   (`2023-11-14T22:13:20.123Z`).
+/// * `{ts:format}`: The time of writing in UTC, in a strftime-like format. It supports `%Y`
+///   (year), `%m` (month), `%d` (day), `%H` (hour), `%M` (minute), `%S` (second), `%.3f`,
+///   `%.6f`, `%.9f` (the fraction of the second, with the dot), `%s` (seconds since the epoch)
+///   and `%%`.
+/// * `{level}`, `{target}`, `{module}`, `{file}`, `{line}`, `{msg}`: The parts of the record (the
+///   unknown module, file and line are empty).
+/// * `{thread}`: The name of the thread, or its ID for the unnamed ones (like `ThreadId(12)`).
+/// * `{pid}`: The process ID.
+/// * `{level:5}` (and alike for the other parts): The part, padded by spaces to the width.
+///
+/// The `{{` and `}}` are the literal braces.
+///
+/// ```rust
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// use log::{Level, Record};
+/// use log_reroute::{Format, Pattern};
+///
+/// let time = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+/// let record = Record::builder()
+///     .level(Level::Info)
+///     .target("app")
+///     .line(Some(42))
+///     .args(format_args!("Hello"))
+///     .build();
+/// let render = |pattern: &str| {
+///     let mut out = Vec::new();
+///     Pattern::new(pattern).unwrap().format(&mut out, &record, time).unwrap();
+///     String::from_utf8(out).unwrap()
+/// };
+///
+/// assert_eq!(
+///     "2023-11-14T22:13:20.123Z INFO  app:42 {Hello}\n",
+///     render("{ts} {level:5} {target}:{line} {{{msg}}}"),
+/// );
+/// assert_eq!(
+///     "14.11.2023 22:13:20.123 [INFO]\n",
+///     render("{ts:%d.%m.%Y %H:%M:%S%.3f} [{level}]"),
+/// );
+/// assert_eq!("1700000000 Hello\n", render("{ts:%s} {msg}"));
+/// assert_eq!(format!("main {}\n", std::process::id()), render("{thread} {pid}"));
+///
+/// let error = Pattern::new("{ts} {lvl}").unwrap_err();
+/// assert_eq!(("{lvl}", 5), (error.fragment(), error.position()));
+/// assert!(Pattern::new("{ts:%Q}").is_err());
+/// assert!(Pattern::new("{msg").is_err());
+/// assert!(Pattern::new("} {msg}").is_err());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    pieces: Vec<Piece>,
+}
+
+impl Pattern {
+    /// Parses the pattern.
+    pub fn new(pattern: &str) -> Result<Self, PatternError> {
+        let mut pieces = Vec::new();
+        let mut chars = pattern.char_indices().peekable();
+        while let Some((start, c)) = chars.next() {
+            match c {
+                '{' if chars.peek().map(|(_, c)| *c) == Some('{') => {
+                    chars.next();
+                    literal(&mut pieces, '{');
+                }
+                '}' if chars.peek().map(|(_, c)| *c) == Some('}') => {
+                    chars.next();
+                    literal(&mut pieces, '}');
+                }
+                '{' => {
+                    let end = match pattern[start..].find('}') {
+                        Some(len) => start + len + 1,
+                        None => return Err(PatternError::new(pattern, start, pattern.len())),
+                    };
+                    let piece = placeholder(&pattern[start + 1..end - 1])
+                        .ok_or_else(|| PatternError::new(pattern, start, end))?;
+                    pieces.push(piece);
+                    while chars.peek().is_some_and(|(i, _)| *i < end) {
+                        chars.next();
+                    }
+                }
+                '}' => return Err(PatternError::new(pattern, start, start + 1)),
+                c => literal(&mut pieces, c),
+            }
+        }
+        Ok(Self { pieces })
+    }
+}
+
+fn write_time(out: &mut dyn Write, format: &[Time], time: SystemTime) -> io::Result<()> {
+    let c = Civil::new(time);
+    for piece in format {
+        match piece {
+            Time::Literal(s) => out.write_all(s.as_bytes())?,
+            Time::Year => write!(out, "{:04}", c.year)?,
+            Time::Month => write!(out, "{:02}", c.month)?,
+            Time::Day => write!(out, "{:02}", c.day)?,
+            Time::Hour => write!(out, "{:02}", c.hour)?,
+            Time::Minute => write!(out, "{:02}", c.minute)?,
+            Time::Second => write!(out, "{:02}", c.second)?,
+            Time::Fraction(digits) => {
+                let value = c.nanos / 10u32.pow(9 - digits);
+                write!(out, ".{:0width$}", value, width = *digits as usize)?
+            }
+            Time::Unix => write!(
+                out,
+                "{}",
+                time.duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs()
+            )?,
+        }
+    }
+    Ok(())
+}
+
+impl Format for Pattern {
+    fn format(&self, out: &mut dyn Write, record: &Record, time: SystemTime) -> io::Result<()> {
+        for piece in &self.pieces {
+            match piece {
+                Piece::Literal(s) => out.write_all(s.as_bytes())?,
+                Piece::Rfc3339 => write!(out, "{}", Rfc3339(time))?,
+                Piece::Time(format) => write_time(out, format, time)?,
+                Piece::Field(Field::Thread, width) => {
+                    with_thread_name(|thread| write!(out, "{:width$}", thread, width = width))?
+                }
+                Piece::Field(field, width) => {
+                    let line = record.line().map(|line| line.to_string());
+                    let pid = process::id();
+                    let value: &dyn Display = match field {
+                        Field::Level => &record.level(),
+                        Field::Target => &record.target(),
+                        Field::Module => &record.module_path().unwrap_or_default(),
+                        Field::File => &record.file().unwrap_or_default(),
+                        Field::Line => &line.as_deref().unwrap_or_default(),
+                        Field::Msg => record.args(),
+                        Field::Pid => &pid,
+                        Field::Thread => unreachable!("Handled above"),
+                    };
+                    if *width == 0 {
+                        write!(out, "{}", value)?;
+                    } else {
+                        write!(out, "{:width$}", value.to_string(), width = width)?;
+                    }
+                }
+            }
+        }
+        out.write_all(b"\n")
+    }
+}
@@ -0,0 +1,275 @@
+//! Holding the records back for a while.
+//!
+//! See [`Reroute::pause`][crate::Reroute::pause].
+
+use std::collections::VecDeque;
+use std::sync::atomic::Ordering;
+use std::sync::{MutexGuard, PoisonError};
+
+use log::{Log, Metadata, Record};
+
+use crate::cycle::{self, Reentry};
+use crate::record::OwnedRecord;
+use crate::Reroute;
+
+/// How many records [`Reroute::pause`] keeps by default.
+pub const DEFAULT_PAUSE_CAPACITY: usize = 1024;
+
+/// The records collected while paused.
+pub(crate) struct Held {
+    /// How many pauses are active.
+    depth: usize,
+    /// Used by [`Reroute::pause`].
+    pub(crate) default_capacity: usize,
+    capacity: usize,
+    records: VecDeque<OwnedRecord>,
+    dropped: u64,
+}
+
+impl Held {
+    pub(crate) const fn new() -> Self {
+        Self {
+            depth: 0,
+            default_capacity: DEFAULT_PAUSE_CAPACITY,
+            capacity: 0,
+            records: VecDeque::new(),
+            dropped: 0,
+        }
+    }
+}
+
+/// Gets the held records replayed from within the slave, for the reentry policy.
+struct Reentered<'a>(&'a Reroute, Reentry);
+
+impl Log for Reentered<'_> {
+    fn enabled(&self, _: &Metadata) -> bool {
+        true
+    }
+    fn log(&self, record: &Record) {
+        self.0.log_reentered(self.1, record);
+    }
+    fn flush(&self) {}
+}
+
+/// Keeps a [`Reroute`] paused until dropped.
+///
+/// Returned by [`Reroute::pause`]. Dropping it resumes the logging, the same as
+/// [`resume`][PauseGuard::resume].
+#[must_use = "The logging is resumed right away if the guard is dropped"]
+pub struct PauseGuard<'a> {
+    reroute: &'a Reroute,
+}
+
+impl PauseGuard<'_> {
+    /// Resumes the logging and replays the held records.
+    ///
+    /// Returns how many records were dropped because the buffer was full (if there are nested
+    /// pauses, all the records dropped since the outermost one started are counted).
+    pub fn resume(self) -> u64 {
+        let dropped = self.dropped();
+        drop(self);
+        dropped
+    }
+
+    /// How many records were dropped so far because the buffer was full.
+    pub fn dropped(&self) -> u64 {
+        self.reroute.lock_buffer().dropped
+    }
+}
+
+impl Drop for PauseGuard<'_> {
+    fn drop(&mut self) {
+        self.reroute.resume();
+    }
+}
+
+impl Reroute {
+    /// Holds the records back, instead of sending them to the slave.
+    ///
+    /// This is useful when the slave's resources are being replaced (eg. rotating a log file) and
+    /// the records shouldn't go to the old ones. While paused, the records are stored (as owned
+    /// copies) in an internal buffer holding up to [`DEFAULT_PAUSE_CAPACITY`] records (unless
+    /// configured otherwise by
+    /// [`RerouteBuilder::buffer_capacity`][crate::RerouteBuilder::buffer_capacity]). Once it's
+    /// full, the oldest records are dropped and counted.
+    ///
+    /// When the returned guard is dropped, the held records are sent, in order, to whichever slave
+    /// is installed at that time. Flushing during the pause does nothing (the records are kept).
+    ///
+    /// Pauses can nest, the records are released when the last guard is dropped. Records logged
+    /// from other threads while the held ones are being replayed may get mixed in between them.
+    ///
+    /// Only the records for the shared slave are held. The ones going to a
+    /// [thread override][Reroute::set_thread_override] (or a task override) pass right through,
+    /// and the held ones are replayed into the shared slave even if the resuming thread has an
+    /// override:
+    ///
+    /// ```rust
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// use log::{Log, Metadata, Record};
+    /// use log_reroute::Reroute;
+    ///
+    /// #[derive(Clone, Default)]
+    /// struct Collect(Arc<Mutex<Vec<String>>>);
+    ///
+    /// impl Log for Collect {
+    ///     fn enabled(&self, _: &Metadata) -> bool {
+    ///         true
+    ///     }
+    ///     fn log(&self, record: &Record) {
+    ///         self.0.lock().unwrap().push(record.args().to_string());
+    ///     }
+    ///     fn flush(&self) {}
+    /// }
+    ///
+    /// fn log(reroute: &Reroute, msg: &str) {
+    ///     reroute.log(&Record::builder().args(format_args!("{}", msg)).build());
+    /// }
+    ///
+    /// let shared = Collect::default();
+    /// let local = Collect::default();
+    /// let reroute = Reroute::with(shared.clone());
+    ///
+    /// let pause = reroute.pause();
+    /// std::thread::scope(|s| {
+    ///     s.spawn(|| log(&reroute, "held"));
+    /// });
+    /// reroute.set_thread_override(local.clone());
+    /// log(&reroute, "local");
+    /// assert_eq!(vec!["local"], *local.0.lock().unwrap());
+    /// assert_eq!(0, pause.resume());
+    ///
+    /// assert_eq!(vec!["held"], *shared.0.lock().unwrap());
+    /// assert_eq!(vec!["local"], *local.0.lock().unwrap());
+    /// ```
+    ///
+    /// ```rust
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// use log::{Log, Metadata, Record};
+    /// use log_reroute::Reroute;
+    ///
+    /// #[derive(Clone, Default)]
+    /// struct Collect(Arc<Mutex<Vec<String>>>);
+    ///
+    /// impl Log for Collect {
+    ///     fn enabled(&self, _: &Metadata) -> bool {
+    ///         true
+    ///     }
+    ///     fn log(&self, record: &Record) {
+    ///         self.0.lock().unwrap().push(record.args().to_string());
+    ///     }
+    ///     fn flush(&self) {}
+    /// }
+    ///
+    /// fn log(reroute: &Reroute, msg: &str) {
+    ///     reroute.log(&Record::builder().args(format_args!("{}", msg)).build());
+    /// }
+    ///
+    /// let old = Collect::default();
+    /// let new = Collect::default();
+    /// let reroute = Reroute::with(old.clone());
+    ///
+    /// log(&reroute, "first");
+    /// let pause = reroute.pause();
+    /// log(&reroute, "second");
+    /// reroute.flush();
+    /// reroute.reroute(new.clone());
+    /// log(&reroute, "third");
+    /// assert_eq!(0, pause.resume());
+    /// log(&reroute, "fourth");
+    ///
+    /// assert_eq!(vec!["first"], *old.0.lock().unwrap());
+    /// assert_eq!(vec!["second", "third", "fourth"], *new.0.lock().unwrap());
+    /// ```
+    pub fn pause(&self) -> PauseGuard<'_> {
+        let capacity = self.lock_buffer().default_capacity;
+        self.pause_with_capacity(capacity)
+    }
+
+    /// Holds the records back, with a custom size of the buffer.
+    ///
+    /// This is like [`pause`][Reroute::pause], but keeps up to `capacity` records. If the reroute
+    /// is already paused, the capacity of the outermost pause stays in effect.
+    pub fn pause_with_capacity(&self, capacity: usize) -> PauseGuard<'_> {
+        let mut buffer = self.lock_buffer();
+        if buffer.depth == 0 {
+            buffer.capacity = capacity;
+            buffer.dropped = 0;
+        }
+        buffer.depth += 1;
+        self.paused.store(true, Ordering::SeqCst);
+        PauseGuard { reroute: self }
+    }
+
+    /// Is the reroute paused right now?
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    fn lock_buffer(&self) -> MutexGuard<'_, Held> {
+        // Nothing in here panics while holding the lock.
+        self.buffer.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn resume(&self) {
+        let records = {
+            let mut buffer = self.lock_buffer();
+            buffer.depth -= 1;
+            if buffer.depth > 0 {
+                return;
+            }
+            self.paused.store(false, Ordering::SeqCst);
+            buffer.capacity = 0;
+            // Replay outside of the lock, the slave might want to log through us.
+            buffer.records.split_off(0)
+        };
+        if records.is_empty() {
+            return;
+        }
+        // Resumed from within our own slave.
+        if let Some(reentry) = self.reentered() {
+            for record in records {
+                record.emit_to(&Reentered(self, reentry));
+            }
+            return;
+        }
+        // Only the records for the shared slave were held, so they go there no matter what
+        // overrides the resuming thread has.
+        cycle::nest(self, (), || {
+            self.dispatch_shared(|log| {
+                for record in records {
+                    record.emit_to(log);
+                }
+            })
+        })
+    }
+
+    /// Stores the record if paused.
+    ///
+    /// In case it is not paused (any more) or the record goes to an override, it returns false and the caller should send it to the
+    /// slave.
+    #[cold]
+    pub(crate) fn hold(&self, record: &Record) -> bool {
+        // The overrides are not paused.
+        if self.overridden() {
+            return false;
+        }
+        let record = OwnedRecord::from_record(record);
+        let mut buffer = self.lock_buffer();
+        if buffer.depth == 0 {
+            return false;
+        }
+        if buffer.capacity == 0 {
+            buffer.dropped += 1;
+            return true;
+        }
+        if buffer.records.len() >= buffer.capacity {
+            buffer.records.pop_front();
+            buffer.dropped += 1;
+        }
+        buffer.records.push_back(record);
+        true
+    }
+}
@@ -0,0 +1,170 @@
+//! Flushing regularly in a background thread.
+
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::time::{Duration, Instant};
+
+use log::Log;
+
+use crate::worker::Worker;
+use crate::Reroute;
+
+/// The shortest interval, shorter ones are clamped to it.
+const MIN_INTERVAL: Duration = Duration::from_millis(1);
+
+enum Message {
+    Trigger,
+    Stop,
+}
+
+/// A running periodic flush.
+///
+/// Returned by [`Reroute::periodic_flush`]. Dropping it stops the flushing (and waits for the
+/// background thread, at most for the [shutdown timeout][crate::set_shutdown_timeout]).
+pub struct FlushHandle {
+    sender: Sender<Message>,
+    worker: Option<Worker>,
+}
+
+impl FlushHandle {
+    /// Flushes right now (in the background thread), without waiting for the next tick.
+    ///
+    /// This doesn't wait for the flush to happen.
+    pub fn trigger_now(&self) {
+        let _ = self.sender.send(Message::Trigger);
+    }
+
+    /// The background thread, if it could be started.
+    pub fn worker(&self) -> Option<&Worker> {
+        self.worker.as_ref()
+    }
+}
+
+impl Debug for FlushHandle {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_struct("FlushHandle")
+            .field("worker", &self.worker)
+            .finish()
+    }
+}
+
+impl Drop for FlushHandle {
+    fn drop(&mut self) {
+        if let Some(worker) = self.worker.take() {
+            worker.stop();
+        }
+    }
+}
+
+impl Reroute {
+    /// Flushes the reroute regularly in a background thread.
+    ///
+    /// This keeps the buffered records at most about the interval old, even if the application
+    /// logs rarely. The thread (a [`Worker`]) flushes once per interval, or earlier when
+    /// [triggered][FlushHandle::trigger_now]. If a flush takes longer than the interval, the
+    /// missed ticks are skipped and the next flush comes a whole interval after the slow one
+    /// ended (the flushes don't pile up). A panicking flush is survived. Intervals shorter than a
+    /// millisecond (including zero) are taken as a millisecond.
+    ///
+    /// The thread doesn't keep the process alive, it's simply ended when the process exits, and it
+    /// doesn't flush one last time when stopped. To not lose the last records, flush explicitly
+    /// before exiting (or at least use `flush_on_main_thread_exit`).
+    ///
+    /// ```rust
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::thread;
+    /// use std::time::Duration;
+    ///
+    /// use log::Record;
+    /// use log_reroute::{FnLog, Reroute};
+    ///
+    /// static REROUTE: Reroute = Reroute::const_new();
+    /// static FLUSHES: AtomicUsize = AtomicUsize::new(0);
+    ///
+    /// REROUTE.reroute(FnLog::new(|_: &Record| ()).with_flush(|| {
+    ///     FLUSHES.fetch_add(1, Ordering::Relaxed);
+    /// }));
+    ///
+    /// let handle = REROUTE.periodic_flush(Duration::from_secs(3600));
+    /// handle.trigger_now();
+    /// while FLUSHES.load(Ordering::Relaxed) == 0 {
+    ///     thread::sleep(Duration::from_millis(1));
+    /// }
+    /// drop(handle);
+    ///
+    /// let _handle = REROUTE.periodic_flush(Duration::from_millis(5));
+    /// while FLUSHES.load(Ordering::Relaxed) < 3 {
+    ///     thread::sleep(Duration::from_millis(1));
+    /// }
+    /// ```
+    ///
+    /// A flush slower than the interval still gets a whole interval of a break:
+    ///
+    /// ```rust
+    /// use std::sync::Mutex;
+    /// use std::thread;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// use log::Record;
+    /// use log_reroute::{FnLog, Reroute};
+    ///
+    /// static REROUTE: Reroute = Reroute::const_new();
+    /// static FLUSHES: Mutex<Vec<(Instant, Instant)>> = Mutex::new(Vec::new());
+    ///
+    /// let interval = Duration::from_millis(10);
+    /// REROUTE.reroute(FnLog::new(|_: &Record| ()).with_flush(|| {
+    ///     let start = Instant::now();
+    ///     thread::sleep(Duration::from_millis(30));
+    ///     FLUSHES.lock().unwrap().push((start, Instant::now()));
+    /// }));
+    ///
+    /// let handle = REROUTE.periodic_flush(interval);
+    /// while FLUSHES.lock().unwrap().len() < 4 {
+    ///     thread::sleep(Duration::from_millis(1));
+    /// }
+    /// drop(handle);
+    ///
+    /// let flushes = FLUSHES.lock().unwrap();
+    /// for pair in flushes.windows(2) {
+    ///     let (_, previous_end) = pair[0];
+    ///     let (start, _) = pair[1];
+    ///     assert!(start >= previous_end + interval);
+    /// }
+    /// ```
+    pub fn periodic_flush(&'static self, interval: Duration) -> FlushHandle {
+        let interval = interval.max(MIN_INTERVAL);
+        let (sender, receiver) = mpsc::channel();
+        let stop = sender.clone();
+        let mut next = Instant::now() + interval;
+        let worker = Worker::spawn(
+            move || {
+                let timeout = next.saturating_duration_since(Instant::now());
+                match receiver.recv_timeout(timeout) {
+                    Ok(Message::Trigger) => (),
+                    Ok(Message::Stop) | Err(RecvTimeoutError::Disconnected) => return false,
+                    Err(RecvTimeoutError::Timeout) => next += interval,
+                }
+                self.flush();
+                let now = Instant::now();
+                if next <= now {
+                    // Skip the ticks missed by a slow flush, counting from its end.
+                    next = now + interval;
+                }
+                true
+            },
+            move || {
+                let _ = stop.send(Message::Stop);
+            },
+        )
+        .ok();
+        FlushHandle { sender, worker }
+    }
+}
+
+/// Flushes the global [`Reroute`] regularly in a background thread.
+///
+/// See [`Reroute::periodic_flush`].
+#[cfg(feature = "global")]
+pub fn periodic_flush(interval: Duration) -> FlushHandle {
+    crate::REROUTE.periodic_flush(interval)
+}
@@ -0,0 +1,279 @@
+//! Limiting the rate of records from each callsite.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::hash::{Hash, Hasher};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use log::{Level, Log, Metadata, Record};
+
+/// How many callsites a [`RateLimit`] remembers by default.
+pub const DEFAULT_RATE_LIMIT_CALLSITES: usize = 1024;
+
+/// The callsites are split into this many independently locked shards.
+const SHARDS: usize = 16;
+
+/// The records suppressed at a callsite, to be summarized.
+struct Summary {
+    level: Level,
+    target: String,
+    module_path: Option<String>,
+    line: Option<u32>,
+    suppressed: u64,
+}
+
+impl Summary {
+    fn emit_to(&self, log: &dyn Log) {
+        log.log(
+            &Record::builder()
+                .level(self.level)
+                .target(&self.target)
+                .module_path(self.module_path.as_deref())
+                .line(self.line)
+                .args(format_args!(
+                    "previous message repeated {} times (suppressed)",
+                    self.suppressed
+                ))
+                .build(),
+        );
+    }
+}
+
+struct Callsite {
+    window_start: Instant,
+    passed: u32,
+    summary: Summary,
+    /// When it was last used, for evicting the least recently used.
+    used: u64,
+}
+
+impl Callsite {
+    fn take_summary(&mut self) -> Option<Summary> {
+        if self.summary.suppressed == 0 {
+            return None;
+        }
+        let summary = Summary {
+            level: self.summary.level,
+            target: self.summary.target.clone(),
+            module_path: self.summary.module_path.clone(),
+            line: self.summary.line,
+            suppressed: self.summary.suppressed,
+        };
+        self.summary.suppressed = 0;
+        Some(summary)
+    }
+}
+
+#[derive(Default)]
+struct Shard {
+    callsites: HashMap<u64, Callsite>,
+    tick: u64,
+}
+
+/// What to do with a record.
+struct Verdict {
+    pass: bool,
+    summaries: Vec<Summary>,
+}
+
+/// A wrapper limiting how many records each callsite can log in a time window.
+///
+/// A callsite is identified by the module path and the line of the record. Each can pass at
+/// most `max` records per `window`, the rest are suppressed and counted. When the window rolls
+/// over (noticed by the next record of the callsite), a `previous message repeated N times
+/// (suppressed)` record with the original level and target is sent first. [Flushing][Log::flush]
+/// sends all the pending summaries.
+///
+/// At most [`DEFAULT_RATE_LIMIT_CALLSITES`] callsites are remembered (unless set by
+/// [`with_capacity`][RateLimit::with_capacity]), the least recently used ones are forgotten
+/// (after sending their summaries). The callsites are split into several independently locked
+/// shards, so threads logging from different places rarely contend.
+///
+/// ```rust
+/// use std::sync::{Arc, Mutex};
+/// use std::time::Duration;
+///
+/// use log::{Level, Log, Metadata, Record};
+/// use log_reroute::RateLimit;
+///
+/// #[derive(Clone, Default)]
+/// struct Messages(Arc<Mutex<Vec<String>>>);
+///
+/// impl Log for Messages {
+///     fn enabled(&self, _: &Metadata) -> bool {
+///         true
+///     }
+///     fn log(&self, record: &Record) {
+///         self.0.lock().unwrap().push(record.args().to_string());
+///     }
+///     fn flush(&self) {}
+/// }
+///
+/// fn warn(log: &dyn Log, i: usize) {
+///     log.log(
+///         &Record::builder()
+///             .level(Level::Warn)
+///             .module_path(Some("app"))
+///             .line(Some(42))
+///             .args(format_args!("Hot loop {}", i))
+///             .build(),
+///     );
+/// }
+///
+/// let messages = Messages::default();
+/// let limited = RateLimit::new(messages.clone(), 2, Duration::from_secs(3600));
+/// for i in 0..10 {
+///     warn(&limited, i);
+/// }
+/// limited.flush();
+/// assert_eq!(
+///     vec![
+///         "Hot loop 0",
+///         "Hot loop 1",
+///         "previous message repeated 8 times (suppressed)",
+///     ],
+///     *messages.0.lock().unwrap(),
+/// );
+///
+/// // The summary comes when the window rolls over
+/// let messages = Messages::default();
+/// let limited = RateLimit::new(messages.clone(), 1, Duration::from_millis(10));
+/// warn(&limited, 0);
+/// warn(&limited, 1);
+/// std::thread::sleep(Duration::from_millis(20));
+/// warn(&limited, 2);
+/// assert_eq!(
+///     vec![
+///         "Hot loop 0",
+///         "previous message repeated 1 times (suppressed)",
+///         "Hot loop 2",
+///     ],
+///     *messages.0.lock().unwrap(),
+/// );
+/// ```
+pub struct RateLimit<L> {
+    inner: L,
+    max: u32,
+    window: Duration,
+    shard_capacity: usize,
+    shards: [Mutex<Shard>; SHARDS],
+}
+
+impl<L: Log> RateLimit<L> {
+    /// Wraps the logger, passing at most `max` records per `window` from each callsite.
+    pub fn new(inner: L, max: u32, window: Duration) -> Self {
+        Self {
+            inner,
+            max,
+            window,
+            shard_capacity: DEFAULT_RATE_LIMIT_CALLSITES.div_ceil(SHARDS),
+            shards: Default::default(),
+        }
+    }
+
+    /// Sets how many callsites are remembered.
+    pub fn with_capacity(mut self, callsites: usize) -> Self {
+        self.shard_capacity = callsites.div_ceil(SHARDS).max(1);
+        self
+    }
+
+    /// The wrapped logger.
+    pub fn inner(&self) -> &L {
+        &self.inner
+    }
+
+    fn shard(&self, index: usize) -> MutexGuard<'_, Shard> {
+        self.shards[index]
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn judge(&self, record: &Record, now: Instant) -> Verdict {
+        let mut hasher = DefaultHasher::new();
+        record.module_path().hash(&mut hasher);
+        record.line().hash(&mut hasher);
+        let key = hasher.finish();
+        let mut summaries = Vec::new();
+
+        let mut shard = self.shard(key as usize % SHARDS);
+        shard.tick += 1;
+        let tick = shard.tick;
+        if !shard.callsites.contains_key(&key) && shard.callsites.len() >= self.shard_capacity {
+            let oldest = shard
+                .callsites
+                .iter()
+                .min_by_key(|(_, callsite)| callsite.used)
+                .map(|(key, _)| *key);
+            if let Some(mut evicted) = oldest.and_then(|key| shard.callsites.remove(&key)) {
+                summaries.extend(evicted.take_summary());
+            }
+        }
+        let callsite = shard.callsites.entry(key).or_insert_with(|| Callsite {
+            window_start: now,
+            passed: 0,
+            summary: Summary {
+                level: record.level(),
+                target: record.target().to_owned(),
+                module_path: record.module_path().map(str::to_owned),
+                line: record.line(),
+                suppressed: 0,
+            },
+            used: tick,
+        });
+        callsite.used = tick;
+        if now.duration_since(callsite.window_start) >= self.window {
+            summaries.extend(callsite.take_summary());
+            callsite.window_start = now;
+            callsite.passed = 0;
+        }
+        let pass = callsite.passed < self.max;
+        if pass {
+            callsite.passed += 1;
+        } else {
+            callsite.summary.suppressed += 1;
+        }
+        Verdict { pass, summaries }
+    }
+}
+
+impl<L> Debug for RateLimit<L> {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_struct("RateLimit")
+            .field("max", &self.max)
+            .field("window", &self.window)
+            .field("callsites", &(self.shard_capacity * SHARDS))
+            .finish()
+    }
+}
+
+impl<L: Log> Log for RateLimit<L> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+    fn log(&self, record: &Record) {
+        let verdict = self.judge(record, Instant::now());
+        // Not holding the lock while logging.
+        for summary in verdict.summaries {
+            summary.emit_to(&self.inner);
+        }
+        if verdict.pass {
+            self.inner.log(record);
+        }
+    }
+    fn flush(&self) {
+        for index in 0..SHARDS {
+            let summaries = self
+                .shard(index)
+                .callsites
+                .values_mut()
+                .filter_map(Callsite::take_summary)
+                .collect::<Vec<_>>();
+            for summary in summaries {
+                summary.emit_to(&self.inner);
+            }
+        }
+        self.inner.flush();
+    }
+}
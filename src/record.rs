@@ -0,0 +1,260 @@
+//! Owned representation of log records.
+
+use std::borrow::Cow;
+#[cfg(feature = "serde")]
+use std::io::{self, BufRead, Write};
+use std::time::SystemTime;
+
+#[cfg(feature = "kv")]
+use log::kv::Source;
+use log::{Level, Log, Record};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "kv")]
+use crate::kv::KeyValues;
+
+/// A log record that doesn't borrow anything.
+///
+/// The [`Record`] borrows its message and metadata, so it can't be stored. This is a copy of it
+/// that can, with the message formatted into a string. The module path and file name are kept
+/// without allocation if the original has them `'static` (as the ones from the [`log`] macros
+/// do), and are passed on as `'static` when re-emitted. The time of the copying is kept too
+/// (but is lost on re-emitting, the [`Record`] has no place for it). With the `kv` feature, the
+/// structured key-values are kept as well.
+///
+/// With the `serde` feature, it can be serialized and deserialized. The fields are (and will
+/// stay) named `level` (the name of the level in capitals, like `"WARN"`), `target`, `message`,
+/// `module_path`, `file`, `line` (these three can be `null`) and `time` (in the serde format of
+/// the [`SystemTime`], an object with `secs_since_epoch` and `nanos_since_epoch`). With the
+/// `kv` feature, there's also `key_values`, a list of `[key, value]` pairs, where the value is
+/// an object with a single field named by its type (`null`, `bool`, `u64`, `i64`, `u128`,
+/// `i128`, `f64` or `str`).
+///
+/// ```rust
+/// use std::sync::Mutex;
+///
+/// use log::{Level, Log, Metadata, Record};
+/// use log_reroute::OwnedRecord;
+///
+/// #[derive(Default)]
+/// struct Capture(Mutex<Vec<OwnedRecord>>);
+///
+/// impl Log for Capture {
+///     fn enabled(&self, _: &Metadata) -> bool {
+///         true
+///     }
+///     fn log(&self, record: &Record) {
+///         let owned = OwnedRecord::from_record(record);
+///         assert_eq!(record.file_static(), owned.file_static());
+///         self.0.lock().unwrap().push(owned);
+///     }
+///     fn flush(&self) {}
+/// }
+///
+/// let original = OwnedRecord::from_record(
+///     &Record::builder()
+///         .level(Level::Warn)
+///         .target("my_crate::db")
+///         .args(format_args!("Disk {} full", 3))
+///         .module_path_static(Some("my_crate::db"))
+///         .file_static(Some("src/db.rs"))
+///         .line(Some(42))
+///         .build(),
+/// );
+/// assert_eq!("Disk 3 full", original.message());
+///
+/// // Nothing observable is lost on the way through (except the time of capture)
+/// let capture = Capture::default();
+/// original.emit_to(&capture);
+/// original.emit_to(&capture);
+/// let captured = capture.0.lock().unwrap();
+/// assert_eq!(2, captured.len());
+/// for copy in captured.iter() {
+///     assert_eq!(original.level(), copy.level());
+///     assert_eq!(original.target(), copy.target());
+///     assert_eq!(original.message(), copy.message());
+///     assert_eq!(original.module_path_static(), copy.module_path_static());
+///     assert_eq!(original.file_static(), copy.file_static());
+///     assert_eq!(original.line(), copy.line());
+///     assert!(original.time() <= copy.time());
+/// }
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct OwnedRecord {
+    level: Level,
+    target: String,
+    message: String,
+    module_path: Option<Cow<'static, str>>,
+    file: Option<Cow<'static, str>>,
+    line: Option<u32>,
+    time: SystemTime,
+    #[cfg(feature = "kv")]
+    key_values: KeyValues,
+}
+
+/// Keeps the static variant if present.
+fn owned(static_variant: Option<&'static str>, variant: Option<&str>) -> Option<Cow<'static, str>> {
+    match static_variant {
+        Some(s) => Some(Cow::Borrowed(s)),
+        None => variant.map(|s| Cow::Owned(s.to_owned())),
+    }
+}
+
+/// The static variant, if it is one.
+fn as_static(value: &Option<Cow<'static, str>>) -> Option<&'static str> {
+    match value {
+        Some(Cow::Borrowed(s)) => Some(s),
+        _ => None,
+    }
+}
+
+impl OwnedRecord {
+    /// Copies the record.
+    ///
+    /// This formats the message, running the `Display` impls of its arguments. These may log
+    /// too, so a logger storing the copy should make it before locking anything the nested record
+    /// would need.
+    pub fn from_record(record: &Record) -> Self {
+        Self {
+            level: record.level(),
+            target: record.target().to_owned(),
+            message: record.args().to_string(),
+            module_path: owned(record.module_path_static(), record.module_path()),
+            file: owned(record.file_static(), record.file()),
+            line: record.line(),
+            time: SystemTime::now(),
+            #[cfg(feature = "kv")]
+            key_values: KeyValues::from_source(record.key_values()),
+        }
+    }
+
+    /// The level of the record.
+    pub fn level(&self) -> Level {
+        self.level
+    }
+
+    /// The target of the record.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// The formatted message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The module path, if known.
+    pub fn module_path(&self) -> Option<&str> {
+        self.module_path.as_deref()
+    }
+
+    /// The module path, if it was `'static` in the original record.
+    pub fn module_path_static(&self) -> Option<&'static str> {
+        as_static(&self.module_path)
+    }
+
+    /// The source file, if known.
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+
+    /// The source file, if it was `'static` in the original record.
+    pub fn file_static(&self) -> Option<&'static str> {
+        as_static(&self.file)
+    }
+
+    /// The line in the source file, if known.
+    pub fn line(&self) -> Option<u32> {
+        self.line
+    }
+
+    /// When the record was copied.
+    pub fn time(&self) -> SystemTime {
+        self.time
+    }
+
+    /// The structured key-values of the record.
+    ///
+    /// The keys and values are owned copies. The values of primitive types (numbers, booleans,
+    /// strings) are kept as they were, anything else is formatted into a string.
+    #[cfg(feature = "kv")]
+    pub fn key_values(&self) -> &dyn Source {
+        &self.key_values
+    }
+
+    /// Reconstructs the record and sends it into the logger.
+    pub fn emit_to(&self, log: &dyn Log) {
+        let mut builder = Record::builder();
+        #[cfg(feature = "kv")]
+        builder.key_values(&self.key_values);
+        builder
+            .level(self.level)
+            .target(&self.target)
+            .line(self.line);
+        match &self.module_path {
+            Some(Cow::Borrowed(path)) => builder.module_path_static(Some(path)),
+            path => builder.module_path(path.as_deref()),
+        };
+        match &self.file {
+            Some(Cow::Borrowed(file)) => builder.file_static(Some(file)),
+            file => builder.file(file.as_deref()),
+        };
+        log.log(&builder.args(format_args!("{}", self.message)).build());
+    }
+}
+
+impl From<&Record<'_>> for OwnedRecord {
+    fn from(record: &Record) -> Self {
+        Self::from_record(record)
+    }
+}
+
+/// Writes the records as newline-delimited JSON, one record per line.
+///
+/// See [`OwnedRecord`] for the names of the fields. The records can be read back by
+/// [`read_json`].
+///
+/// ```rust
+/// use log::{Level, Record};
+/// use log_reroute::{dump_json, read_json, OwnedRecord};
+///
+/// let record = OwnedRecord::from_record(
+///     &Record::builder()
+///         .level(Level::Info)
+///         .target("app")
+///         .args(format_args!("Hello"))
+///         .build(),
+/// );
+/// let mut out = Vec::new();
+/// dump_json(&[record.clone(), record.clone()], &mut out).unwrap();
+/// let text = String::from_utf8(out).unwrap();
+/// assert_eq!(2, text.lines().count());
+/// assert!(text.starts_with(r#"{"level":"INFO","target":"app","message":"Hello""#));
+///
+/// assert_eq!(vec![record.clone(), record], read_json(text.as_bytes()).unwrap());
+/// ```
+#[cfg(feature = "serde")]
+pub fn dump_json<W: Write>(records: &[OwnedRecord], mut w: W) -> io::Result<()> {
+    for record in records {
+        serde_json::to_writer(&mut w, record)?;
+        w.write_all(b"\n")?;
+    }
+    w.flush()
+}
+
+/// Reads the records written by [`dump_json`].
+///
+/// The empty lines are skipped.
+#[cfg(feature = "serde")]
+pub fn read_json<R: BufRead>(r: R) -> io::Result<Vec<OwnedRecord>> {
+    let mut records = Vec::new();
+    for line in r.lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            records.push(serde_json::from_str(&line)?);
+        }
+    }
+    Ok(records)
+}
@@ -0,0 +1,348 @@
+//! Scrubbing the secrets from the records.
+
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::ops::Range;
+
+use log::{Log, Metadata, Record};
+
+use crate::transform::rebuild;
+
+/// The replacement used by `Redact::with_key` and `Redact::with_regex`.
+pub const REDACTED: &str = "[REDACTED]";
+
+type Matcher = Box<dyn Fn(&str) -> Option<(Range<usize>, &'static str)> + Send + Sync>;
+
+fn valid(text: &str, range: &Range<usize>) -> bool {
+    range.start <= range.end
+        && range.end <= text.len()
+        && text.is_char_boundary(range.start)
+        && text.is_char_boundary(range.end)
+}
+
+/// A wrapper scrubbing secrets (tokens, email addresses…) from the records.
+///
+/// It is configured by matchers. A matcher looks for the first secret in the text and returns
+/// its byte range and the placeholder to replace it with. All the matchers are applied to the
+/// formatted message, repeatedly, until none matches. The records with nothing to scrub are
+/// passed on untouched (without allocating another copy of the message).
+///
+/// With the `regex` feature, a regular expression can be used as a matcher (see
+/// `with_regex`). With the `kv` feature, the values of the key-values are scrubbed by the
+/// matchers too (as they would be formatted), and the values of the keys listed by `with_key`
+/// are replaced entirely.
+///
+/// ```rust
+/// use std::sync::{Arc, Mutex};
+///
+/// use log::{Level, Log, Record};
+/// use log_reroute::{FnLog, Redact};
+///
+/// let messages = Arc::new(Mutex::new(Vec::new()));
+/// let collect = Arc::clone(&messages);
+/// let redact = Redact::new(FnLog::new(move |record| {
+///     collect.lock().unwrap().push(record.args().to_string());
+/// }))
+/// .with_matcher(|text| {
+///     let start = text.find("Bearer ")? + "Bearer ".len();
+///     let len = text[start..].find(' ').unwrap_or(text.len() - start);
+///     Some((start..start + len, "***"))
+/// });
+///
+/// for msg in ["Auth Bearer abc123 and Bearer xyz", "Nothing secret"] {
+///     redact.log(
+///         &Record::builder()
+///             .level(Level::Info)
+///             .args(format_args!("{}", msg))
+///             .build(),
+///     );
+/// }
+/// assert_eq!(
+///     vec!["Auth Bearer *** and Bearer ***", "Nothing secret"],
+///     *messages.lock().unwrap(),
+/// );
+/// ```
+///
+/// The values are scrubbed no matter how they were captured:
+///
+/// ```rust
+/// # #![cfg_attr(not(feature = "kv"), allow(unused))]
+/// # #[cfg(feature = "kv")]
+/// # fn main() {
+/// use log::kv::Key;
+/// use log::LevelFilter;
+/// use log_reroute::{FnLog, Redact};
+///
+/// let redact = Redact::new(FnLog::new(|record| {
+///     let kvs = record.key_values();
+///     let get = |key| kvs.get(Key::from_str(key)).unwrap().to_string();
+///     assert_eq!("token ***", get("secret"));
+///     assert_eq!("\"token ***\"", get("debug"));
+/// }))
+/// .with_matcher(|text| {
+///     let start = text.find("abc")?;
+///     Some((start..start + 3, "***"))
+/// });
+/// let token = String::from("abc");
+/// log::set_max_level(LevelFilter::Info);
+/// log::info!(
+///     logger: &redact,
+///     secret:% = format!("token {}", token),
+///     debug:? = format!("token {}", token);
+///     "Login"
+/// );
+/// # }
+/// # #[cfg(not(feature = "kv"))]
+/// # fn main() {}
+/// ```
+pub struct Redact<L> {
+    inner: L,
+    matchers: Vec<Matcher>,
+    #[cfg(feature = "kv")]
+    keys: Vec<String>,
+}
+
+impl<L: Log> Redact<L> {
+    /// Wraps the logger, without any matchers yet.
+    pub fn new(inner: L) -> Self {
+        Self {
+            inner,
+            matchers: Vec::new(),
+            #[cfg(feature = "kv")]
+            keys: Vec::new(),
+        }
+    }
+
+    /// Adds a matcher.
+    ///
+    /// It gets the text and returns the byte range of the first secret in it and the
+    /// placeholder to put there. An invalid range (out of the text or not at char boundaries)
+    /// is ignored.
+    pub fn with_matcher<F>(mut self, matcher: F) -> Self
+    where
+        F: Fn(&str) -> Option<(Range<usize>, &'static str)> + Send + Sync + 'static,
+    {
+        self.matchers.push(Box::new(matcher));
+        self
+    }
+
+    /// Adds a regular expression as a matcher, replacing the matches by [`REDACTED`].
+    ///
+    /// Requires the `regex` feature.
+    ///
+    /// ```rust
+    /// # #![cfg_attr(not(feature = "regex"), allow(unused))]
+    /// # #[cfg(feature = "regex")]
+    /// # fn main() {
+    /// use log::{Level, Log, Record};
+    /// use log_reroute::{FnLog, Redact};
+    /// use regex::Regex;
+    ///
+    /// let redact = Redact::new(FnLog::new(|record| {
+    ///     assert_eq!("Mail from [REDACTED]", record.args().to_string());
+    /// }))
+    /// .with_regex(Regex::new(r"[\w.+-]+@[\w-]+\.[\w.]+").unwrap());
+    /// redact.log(
+    ///     &Record::builder()
+    ///         .level(Level::Info)
+    ///         .args(format_args!("Mail from {}", "john.doe@example.com"))
+    ///         .build(),
+    /// );
+    /// # }
+    /// # #[cfg(not(feature = "regex"))]
+    /// # fn main() {}
+    /// ```
+    #[cfg(feature = "regex")]
+    pub fn with_regex(self, regex: regex::Regex) -> Self {
+        self.with_matcher(move |text| regex.find(text).map(|m| (m.range(), REDACTED)))
+    }
+
+    /// Replaces the values of the key entirely by [`REDACTED`].
+    ///
+    /// Requires the `kv` feature.
+    ///
+    /// ```rust
+    /// # #![cfg_attr(not(feature = "kv"), allow(unused))]
+    /// # #[cfg(feature = "kv")]
+    /// # fn main() {
+    /// use log::kv::Key;
+    /// use log::LevelFilter;
+    /// use log_reroute::{FnLog, Redact};
+    ///
+    /// let redact = Redact::new(FnLog::new(|record| {
+    ///     let kvs = record.key_values();
+    ///     let get = |key| kvs.get(Key::from_str(key)).unwrap().to_string();
+    ///     assert_eq!("[REDACTED]", get("password"));
+    ///     assert_eq!("alice", get("user"));
+    /// }))
+    /// .with_key("password");
+    /// log::set_max_level(LevelFilter::Info);
+    /// log::info!(logger: &redact, user = "alice", password = "hunter2"; "Login");
+    /// # }
+    /// # #[cfg(not(feature = "kv"))]
+    /// # fn main() {}
+    /// ```
+    #[cfg(feature = "kv")]
+    pub fn with_key<K: Into<String>>(mut self, key: K) -> Self {
+        self.keys.push(key.into());
+        self
+    }
+
+    /// The wrapped logger.
+    pub fn inner(&self) -> &L {
+        &self.inner
+    }
+
+    /// Scrubs the text, returns `None` if there's nothing to scrub.
+    fn scrub(&self, text: &str) -> Option<String> {
+        let mut result: Option<String> = None;
+        let mut rest = text;
+        loop {
+            let first = self
+                .matchers
+                .iter()
+                .filter_map(|matcher| matcher(rest))
+                .filter(|(range, _)| valid(rest, range))
+                .min_by_key(|(range, _)| range.start);
+            let (range, placeholder) = match first {
+                Some(found) => found,
+                None => break,
+            };
+            let out = result.get_or_insert_with(|| String::with_capacity(text.len()));
+            out.push_str(&rest[..range.start]);
+            out.push_str(placeholder);
+            let mut end = range.end;
+            if range.is_empty() {
+                // Move on, not to match the same empty place forever.
+                match rest[end..].chars().next() {
+                    Some(c) => {
+                        out.push(c);
+                        end += c.len_utf8();
+                    }
+                    None => {
+                        rest = "";
+                        break;
+                    }
+                }
+            }
+            rest = &rest[end..];
+        }
+        let mut out = result?;
+        out.push_str(rest);
+        Some(out)
+    }
+}
+
+impl<L> Debug for Redact<L> {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_struct("Redact")
+            .field("matchers", &self.matchers.len())
+            .finish()
+    }
+}
+
+impl<L: Log> Log for Redact<L> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+    fn log(&self, record: &Record) {
+        let formatted;
+        let message = match record.args().as_str() {
+            Some(message) => message,
+            None => {
+                formatted = record.args().to_string();
+                &formatted
+            }
+        };
+        let scrubbed = self.scrub(message);
+        #[cfg(feature = "kv")]
+        let key_values = kv::scrub(self, record);
+        #[cfg(not(feature = "kv"))]
+        let key_values: Option<()> = None;
+        if scrubbed.is_none() && key_values.is_none() {
+            return self.inner.log(record);
+        }
+        let mut builder = rebuild(record);
+        #[cfg(feature = "kv")]
+        if let Some(key_values) = &key_values {
+            builder.key_values(key_values);
+        }
+        let message = scrubbed.as_deref().unwrap_or(message);
+        self.inner
+            .log(&builder.args(format_args!("{}", message)).build());
+    }
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+#[cfg(feature = "kv")]
+mod kv {
+    use log::kv::{Error, Key, Value, VisitSource};
+    use log::{Log, Record};
+
+    use super::{Redact, REDACTED};
+    use crate::kv::KeyValues;
+
+    impl<L: Log> Redact<L> {
+        fn scrub_value(&self, key: &Key, value: &Value) -> Option<String> {
+            if self.keys.iter().any(|k| k == key.as_str()) {
+                return Some(REDACTED.to_owned());
+            }
+            match value.to_borrowed_str() {
+                Some(text) => self.scrub(text),
+                // Captured by Display, Debug or as an owned string, check how it'll look.
+                None => self.scrub(&value.to_string()),
+            }
+        }
+    }
+
+    struct Check<'a, L> {
+        redact: &'a Redact<L>,
+        dirty: bool,
+    }
+
+    impl<'kvs, L: Log> VisitSource<'kvs> for Check<'_, L> {
+        fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), Error> {
+            if self.redact.scrub_value(&key, &value).is_some() {
+                self.dirty = true;
+                return Err(Error::msg("Found"));
+            }
+            Ok(())
+        }
+    }
+
+    struct Scrub<'a, L> {
+        redact: &'a Redact<L>,
+        result: KeyValues,
+    }
+
+    impl<'kvs, L: Log> VisitSource<'kvs> for Scrub<'_, L> {
+        fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), Error> {
+            let scrubbed = self.redact.scrub_value(&key, &value);
+            let value = match &scrubbed {
+                Some(scrubbed) => Value::from(scrubbed.as_str()),
+                None => value,
+            };
+            self.result.push(key.as_str().to_owned(), &value);
+            Ok(())
+        }
+    }
+
+    /// Scrubs the key-values, returns `None` if there's nothing to scrub.
+    pub(super) fn scrub<L: Log>(redact: &Redact<L>, record: &Record) -> Option<KeyValues> {
+        let mut check = Check {
+            redact,
+            dirty: false,
+        };
+        let _ = record.key_values().visit(&mut check);
+        if !check.dirty {
+            return None;
+        }
+        let mut scrub = Scrub {
+            redact,
+            result: KeyValues::default(),
+        };
+        let _ = record.key_values().visit(&mut scrub);
+        Some(scrub.result)
+    }
+}
@@ -0,0 +1,68 @@
+//! Independent reroutes, addressable by a name.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+use arc_swap::ArcSwap;
+
+use crate::{Reroute, REROUTE};
+
+type Registry = HashMap<String, &'static Reroute>;
+
+/// The named reroutes.
+///
+/// Read without locking, the writers hold the lock to not lose each other's updates.
+static REGISTRY: OnceLock<ArcSwap<Registry>> = OnceLock::new();
+static WRITE: Mutex<()> = Mutex::new(());
+
+fn registry() -> &'static ArcSwap<Registry> {
+    REGISTRY.get_or_init(Default::default)
+}
+
+/// Returns a reroute of the given name.
+///
+/// The reroute is created on first use and lives until the end of the program. The empty name is
+/// the global [`REROUTE`] instance.
+///
+/// Unlike the global one, the named reroutes are not installed in the [`log`] facade. They are
+/// independent swap points to be plugged into the logging pipeline (eg. as the slave of something
+/// routing the records by their target).
+///
+/// Looking up an already existing reroute doesn't lock.
+///
+/// ```rust
+/// use log_reroute::Dummy;
+///
+/// let audit = log_reroute::named("audit");
+/// audit.reroute(Dummy);
+/// assert!(std::ptr::eq(audit, log_reroute::named("audit")));
+/// assert_eq!(vec!["audit"], log_reroute::names());
+/// ```
+pub fn named(name: &str) -> &'static Reroute {
+    if name.is_empty() {
+        return &REROUTE;
+    }
+    if let Some(reroute) = registry().load().get(name) {
+        return reroute;
+    }
+    let _write = WRITE.lock().unwrap_or_else(PoisonError::into_inner);
+    // Someone might have been faster.
+    let current = registry().load_full();
+    if let Some(reroute) = current.get(name) {
+        return reroute;
+    }
+    let reroute: &'static Reroute = Box::leak(Box::new(Reroute::new()));
+    let mut updated = Registry::clone(&current);
+    updated.insert(name.to_owned(), reroute);
+    registry().store(updated.into());
+    reroute
+}
+
+/// Lists the names of the existing [named][named] reroutes, sorted.
+///
+/// The global one (with the empty name) is not included.
+pub fn names() -> Vec<String> {
+    let mut names = registry().load().keys().cloned().collect::<Vec<_>>();
+    names.sort();
+    names
+}
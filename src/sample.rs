@@ -0,0 +1,203 @@
+//! Keeping only some of the records.
+
+use std::cell::Cell;
+use std::collections::hash_map::RandomState;
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[cfg(feature = "kv")]
+use log::kv::Source;
+use log::{Level, Log, Metadata, Record};
+
+/// A random number, from a per-thread generator.
+fn random() -> u64 {
+    thread_local! {
+        // Randomly seeded by the standard library.
+        static STATE: Cell<u64> = Cell::new(RandomState::new().build_hasher().finish() | 1);
+    }
+    STATE.with(|state| {
+        // xorshift64*
+        let mut x = state.get();
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        state.set(x);
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    })
+}
+
+/// A wrapper keeping only one in N records of each level.
+///
+/// The rate is set for each level separately by [`one_in`][Sample::one_in], all the records
+/// are kept by default. The kept records are passed on untouched. By default, the choice is
+/// deterministic (exactly every N-th record of the level, starting with the first), for
+/// reproducible tests; with [`with_random`][Sample::with_random], each record is kept with the
+/// probability of 1/N.
+///
+/// The [`enabled`][Log::enabled] is that of the wrapped logger, so the code guarded by
+/// `log_enabled!` is not skewed by the sampling.
+///
+/// With the `kv` feature, the kept records of the sampled levels can be tagged by a
+/// `sampled = "1/N"` key-value (see `with_tag`), so the analysis can re-weight them.
+///
+/// ```rust
+/// use std::sync::{Arc, Mutex};
+///
+/// use log::{Level, Log, Metadata, Record};
+/// use log_reroute::Sample;
+///
+/// #[derive(Clone, Default)]
+/// struct Messages(Arc<Mutex<Vec<String>>>);
+///
+/// impl Log for Messages {
+///     fn enabled(&self, _: &Metadata) -> bool {
+///         true
+///     }
+///     fn log(&self, record: &Record) {
+///         self.0.lock().unwrap().push(record.args().to_string());
+///     }
+///     fn flush(&self) {}
+/// }
+///
+/// let messages = Messages::default();
+/// let sample = Sample::new(messages.clone())
+///     .one_in(Level::Info, 10)
+///     .one_in(Level::Trace, 1000);
+/// for i in 0..25 {
+///     for level in [Level::Warn, Level::Info] {
+///         sample.log(
+///             &Record::builder()
+///                 .level(level)
+///                 .args(format_args!("{} {}", level, i))
+///                 .build(),
+///         );
+///     }
+/// }
+/// let messages = messages.0.lock().unwrap();
+/// assert_eq!(25, messages.iter().filter(|m| m.starts_with("WARN")).count());
+/// assert_eq!(
+///     vec!["INFO 0", "INFO 10", "INFO 20"],
+///     messages.iter().filter(|m| m.starts_with("INFO")).collect::<Vec<_>>(),
+/// );
+/// ```
+///
+/// With the tag:
+///
+/// ```rust
+/// # #![cfg_attr(not(feature = "kv"), allow(unused))]
+/// # #[cfg(feature = "kv")]
+/// # fn main() {
+/// use log::kv::Key;
+/// use log::{Level, LevelFilter};
+/// use log_reroute::{FnLog, Sample};
+///
+/// let sample = Sample::new(FnLog::new(|record| {
+///     let sampled = record.key_values().get(Key::from_str("sampled")).unwrap();
+///     assert_eq!("1/10", sampled.to_string());
+///     assert_eq!(Some(42), record.key_values().get(Key::from_str("id")).unwrap().to_u64());
+/// }))
+/// .one_in(Level::Info, 10)
+/// .with_tag();
+/// log::set_max_level(LevelFilter::Info);
+/// log::info!(logger: &sample, id = 42; "Sampled");
+/// # }
+/// # #[cfg(not(feature = "kv"))]
+/// # fn main() {}
+/// ```
+pub struct Sample<L> {
+    inner: L,
+    rates: [u64; 5],
+    counters: [AtomicU64; 5],
+    random: bool,
+    #[cfg(feature = "kv")]
+    tag: bool,
+}
+
+impl<L: Log> Sample<L> {
+    /// Wraps the logger, keeping all the records.
+    pub fn new(inner: L) -> Self {
+        Self {
+            inner,
+            rates: [1; 5],
+            counters: Default::default(),
+            random: false,
+            #[cfg(feature = "kv")]
+            tag: false,
+        }
+    }
+
+    /// Keeps only one in `n` records of the level.
+    ///
+    /// The `0` is taken as `1` (keeping everything).
+    pub fn one_in(mut self, level: Level, n: u64) -> Self {
+        self.rates[level as usize - 1] = n.max(1);
+        self
+    }
+
+    /// Chooses the kept records randomly instead of taking every N-th one.
+    pub fn with_random(mut self) -> Self {
+        self.random = true;
+        self
+    }
+
+    /// Tags the kept records of the sampled levels with the `sampled = "1/N"` key-value.
+    #[cfg(feature = "kv")]
+    pub fn with_tag(mut self) -> Self {
+        self.tag = true;
+        self
+    }
+
+    /// The wrapped logger.
+    pub fn inner(&self) -> &L {
+        &self.inner
+    }
+
+    fn keep(&self, level: Level) -> bool {
+        let index = level as usize - 1;
+        let rate = self.rates[index];
+        if rate == 1 {
+            true
+        } else if self.random {
+            random() % rate == 0
+        } else {
+            self.counters[index].fetch_add(1, Ordering::Relaxed) % rate == 0
+        }
+    }
+}
+
+impl<L> Debug for Sample<L> {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_struct("Sample")
+            .field("rates", &self.rates)
+            .field("random", &self.random)
+            .finish()
+    }
+}
+
+impl<L: Log> Log for Sample<L> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+    fn log(&self, record: &Record) {
+        if !self.keep(record.level()) {
+            return;
+        }
+        #[cfg(feature = "kv")]
+        {
+            let rate = self.rates[record.level() as usize - 1];
+            if self.tag && rate > 1 {
+                let tag = format!("1/{}", rate);
+                let sampled = ("sampled", tag.as_str());
+                let key_values: [&dyn Source; 2] = [record.key_values(), &sampled];
+                return self
+                    .inner
+                    .log(&record.to_builder().key_values(&key_values).build());
+            }
+        }
+        self.inner.log(record);
+    }
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
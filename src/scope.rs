@@ -0,0 +1,158 @@
+//! Support for [`Reroute::scope`][crate::Reroute::scope].
+//!
+//! The borrowed logger can't be put into the [`Reroute`] directly (it needs a `'static` one).
+//! Instead, it is lent to a scoped thread and a proxy is installed, sending all the calls to
+//! that thread. As the thread can't outlive the scope, neither can the borrow. At the end of the
+//! scope the proxy is detached from the thread (any copies still around become inert) and the
+//! thread terminates once the calls already sent are served, so nobody reaches the logger after
+//! the scope ends.
+
+use std::cell::Cell;
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+
+use log::{Level, Log, Metadata, Record};
+
+use crate::record::OwnedRecord;
+use crate::stderr::StderrLog;
+use crate::{Reroute, RerouteGuard, SlaveInfo};
+
+thread_local! {
+    /// Is this the thread serving a borrowed logger?
+    static SERVING: Cell<bool> = const { Cell::new(false) };
+}
+
+enum Request {
+    Enabled {
+        level: Level,
+        target: String,
+        reply: SyncSender<bool>,
+    },
+    Log {
+        record: OwnedRecord,
+        done: SyncSender<()>,
+    },
+    Flush {
+        done: SyncSender<()>,
+    },
+}
+
+/// The connection to the serving thread, cut at the end of the scope.
+type Connection = Arc<Mutex<Option<Sender<Request>>>>;
+
+/// The `'static` stand-in for the borrowed logger.
+struct Proxy {
+    requests: Connection,
+}
+
+impl Proxy {
+    fn call<R>(&self, request: impl FnOnce(SyncSender<R>) -> Request) -> Option<R> {
+        // If the borrowed logger itself logs through the reroute, it would wait for itself.
+        if SERVING.with(Cell::get) {
+            return None;
+        }
+        // Only a copy, not to block the others (or the detaching) while waiting.
+        let requests = self
+            .requests
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()?;
+        let (reply, result) = mpsc::sync_channel(1);
+        requests.send(request(reply)).ok()?;
+        drop(requests);
+        result.recv().ok()
+    }
+}
+
+/// Cuts the proxy off the serving thread when dropped.
+struct Detach(Connection);
+
+impl Drop for Detach {
+    fn drop(&mut self) {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).take();
+    }
+}
+
+impl Log for Proxy {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.call(|reply| Request::Enabled {
+            level: metadata.level(),
+            target: metadata.target().to_owned(),
+            reply,
+        })
+        .unwrap_or(false)
+    }
+    fn log(&self, record: &Record) {
+        let record = OwnedRecord::from_record(record);
+        self.call(|done| Request::Log { record, done });
+    }
+    fn flush(&self) {
+        self.call(|done| Request::Flush { done });
+    }
+}
+
+fn serve(logger: &dyn Log, requests: Receiver<Request>) {
+    SERVING.with(|s| s.set(true));
+    // Terminates once detached and the requests sent before are served.
+    for request in requests {
+        // The other side might have given up waiting (eg. by a panic), that's fine.
+        match request {
+            Request::Enabled {
+                level,
+                target,
+                reply,
+            } => {
+                let metadata = Metadata::builder().level(level).target(&target).build();
+                let _ = reply.send(logger.enabled(&metadata));
+            }
+            Request::Log { record, done } => {
+                record.emit_to(logger);
+                let _ = done.send(());
+            }
+            Request::Flush { done } => {
+                logger.flush();
+                let _ = done.send(());
+            }
+        }
+    }
+}
+
+pub(crate) fn run<R, F: FnOnce() -> R>(reroute: &Reroute, logger: &dyn Log, f: F) -> R {
+    thread::scope(|s| {
+        let (requests, received) = mpsc::channel();
+        let spawned = thread::Builder::new()
+            .name("log-reroute-scope".to_owned())
+            .spawn_scoped(s, move || serve(logger, received));
+        if let Err(e) = spawned {
+            StderrLog.log(
+                &Record::builder()
+                    .level(Level::Warn)
+                    .target("log_reroute")
+                    .args(format_args!(
+                        "Can't serve the scoped logger ({}), keeping the current one",
+                        e
+                    ))
+                    .build(),
+            );
+            return f();
+        }
+        let requests = Arc::new(Mutex::new(Some(requests)));
+        let proxy = Box::new(Proxy {
+            requests: Arc::clone(&requests),
+        }) as Box<dyn Log>;
+        let mut info = SlaveInfo::boxed();
+        info.type_name = "scoped";
+        let displaced = reroute.replace_unconditionally(Arc::new(proxy), info);
+        // Both happen even on panic, the original is restored first and then the proxy is
+        // detached. This needs to happen before the scope waits for the thread, otherwise it
+        // would wait for whoever still holds a copy of the proxy.
+        let _detach = Detach(requests);
+        let _guard = RerouteGuard {
+            reroute,
+            previous: Some((displaced.slave, displaced.info)),
+            level: None,
+        };
+        f()
+    })
+}
@@ -0,0 +1,173 @@
+//! Reacting to signals, for daemons.
+
+use std::io::Error;
+use std::os::raw::c_int;
+use std::thread::{self, JoinHandle};
+
+use log::{Level, LevelFilter, Log, Record};
+use signal_hook::consts::{SIGHUP, SIGUSR1, SIGUSR2};
+use signal_hook::iterator::backend::Handle;
+use signal_hook::iterator::Signals;
+
+use crate::Reroute;
+
+/// A registered reaction to signals.
+///
+/// Returned by [`Reroute::on_sighup`]. Dropping it keeps the reaction in place, it can be removed
+/// by [`unregister`][SignalHandle::unregister].
+#[derive(Debug)]
+pub struct SignalHandle {
+    handle: Handle,
+    thread: JoinHandle<()>,
+}
+
+impl SignalHandle {
+    /// Stops reacting to the signals.
+    ///
+    /// Waits for the reaction in progress, if any, to finish.
+    pub fn unregister(self) {
+        self.handle.close();
+        // A panic in the reaction was already reported by the thread.
+        let _ = self.thread.join();
+    }
+}
+
+/// Runs the action in a background thread each time one of the signals arrives.
+pub(crate) fn on_signals<F>(signals: &[c_int], mut action: F) -> Result<SignalHandle, Error>
+where
+    F: FnMut(c_int) + Send + 'static,
+{
+    let mut signals = Signals::new(signals)?;
+    let handle = signals.handle();
+    let thread = thread::Builder::new()
+        .name("log-reroute-signal".to_owned())
+        .spawn(move || {
+            for signal in signals.forever() {
+                action(signal);
+            }
+        })?;
+    Ok(SignalHandle { handle, thread })
+}
+
+/// The levels the verbosity signals cycle through, from the least verbose.
+const LEVELS: [LevelFilter; 5] = [
+    LevelFilter::Error,
+    LevelFilter::Warn,
+    LevelFilter::Info,
+    LevelFilter::Debug,
+    LevelFilter::Trace,
+];
+
+/// Moves the level by one step in the cycle.
+fn step(level: LevelFilter, up: bool) -> LevelFilter {
+    let pos = LEVELS.iter().position(|l| *l == level);
+    let pos = match (pos, up) {
+        // Off is below Error
+        (None, true) => 0,
+        (None, false) => LEVELS.len() - 1,
+        (Some(pos), true) => (pos + 1) % LEVELS.len(),
+        (Some(pos), false) => (pos + LEVELS.len() - 1) % LEVELS.len(),
+    };
+    LEVELS[pos]
+}
+
+/// The current verbosity, as set by the signals.
+///
+/// This is the [`log::max_level`], the gate of the [`log`] macros.
+pub fn current_level() -> LevelFilter {
+    log::max_level()
+}
+
+impl Reroute {
+    /// Replaces the slave by a fresh one each time `SIGHUP` arrives.
+    ///
+    /// This is the classic reaction of daemons to rotation of the log files: on each `SIGHUP`, the
+    /// closure creates a new logger (eg. opening the log file again) and it is installed by
+    /// [`reroute_boxed`][Reroute::reroute_boxed] (the old one is flushed as usual).
+    ///
+    /// Nothing is done about the signals until this is called. Each call registers another
+    /// reaction, served by its own background thread.
+    ///
+    /// ```rust
+    /// use log_reroute::{Dummy, Reroute};
+    /// use signal_hook::consts::SIGHUP;
+    ///
+    /// static REROUTE: Reroute = Reroute::const_new();
+    ///
+    /// let handle = REROUTE.on_sighup(|| Box::new(Dummy)).unwrap();
+    /// signal_hook::low_level::raise(SIGHUP).unwrap();
+    /// while REROUTE.generation() == 0 {
+    ///     std::thread::yield_now();
+    /// }
+    /// handle.unregister();
+    /// ```
+    pub fn on_sighup<F>(&'static self, f: F) -> Result<SignalHandle, Error>
+    where
+        F: Fn() -> Box<dyn Log> + Send + 'static,
+    {
+        on_signals(&[SIGHUP], move |_| self.reroute_boxed(f()))
+    }
+
+    /// Allows changing the verbosity by signals.
+    ///
+    /// Each `SIGUSR1` makes the logging more verbose by one level, each `SIGUSR2` less verbose,
+    /// cycling through `Error` to `Trace` (therefore, `SIGUSR1` on `Trace` gets to `Error`). The
+    /// level is the [`log::max_level`], see [`current_level`], and it is mirrored into the
+    /// [gate][Reroute::set_gate] of this reroute. Each change is announced by an `Info` record
+    /// sent directly to the slave (bypassing the levels).
+    ///
+    /// This can be combined with [`on_sighup`][Reroute::on_sighup], each registration has its own
+    /// background thread.
+    ///
+    /// ```rust
+    /// use log::LevelFilter;
+    /// use log_reroute::Reroute;
+    /// use signal_hook::consts::SIGUSR1;
+    ///
+    /// static REROUTE: Reroute = Reroute::const_new();
+    ///
+    /// log::set_max_level(LevelFilter::Info);
+    /// let handle = REROUTE.verbosity_signals().unwrap();
+    /// signal_hook::low_level::raise(SIGUSR1).unwrap();
+    /// while log_reroute::current_level() == LevelFilter::Info {
+    ///     std::thread::yield_now();
+    /// }
+    /// assert_eq!(LevelFilter::Debug, log_reroute::current_level());
+    /// handle.unregister();
+    /// ```
+    pub fn verbosity_signals(&'static self) -> Result<SignalHandle, Error> {
+        on_signals(&[SIGUSR1, SIGUSR2], move |signal| {
+            let level = step(log::max_level(), signal == SIGUSR1);
+            log::set_max_level(level);
+            self.set_gate(level);
+            self.get().log(
+                &Record::builder()
+                    .level(Level::Info)
+                    .target("log_reroute")
+                    .module_path_static(Some(module_path!()))
+                    .args(format_args!("Log level changed to {}", level))
+                    .build(),
+            );
+        })
+    }
+}
+
+/// Replaces the slave of the global [`Reroute`] by a fresh one each time `SIGHUP` arrives.
+///
+/// See [`Reroute::on_sighup`].
+#[cfg(feature = "global")]
+pub fn on_sighup<F>(f: F) -> Result<SignalHandle, Error>
+where
+    F: Fn() -> Box<dyn Log> + Send + 'static,
+{
+    crate::REROUTE.on_sighup(f)
+}
+
+/// Allows changing the verbosity by signals, announcing the changes through the global
+/// [`Reroute`].
+///
+/// See [`Reroute::verbosity_signals`].
+#[cfg(feature = "global")]
+pub fn verbosity_signals() -> Result<SignalHandle, Error> {
+    crate::REROUTE.verbosity_signals()
+}
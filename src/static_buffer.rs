@@ -0,0 +1,342 @@
+//! Keeping the early records without allocating.
+
+use std::fmt::{Debug, Formatter, Result as FmtResult, Write};
+use std::str;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use log::{Level, Log, Metadata, Record};
+
+use crate::displace::OnDisplace;
+
+/// The longest record a [`StaticBuffer`] stores, including its header; longer ones are truncated.
+const MAX_ENTRY: usize = 512;
+
+/// The length (2 bytes) and the level (1 byte) of an entry.
+const HEADER: usize = 3;
+
+/// Separates the target from the message in an entry.
+const SEPARATOR: u8 = 0;
+
+const LEVELS: [Level; 5] = [
+    Level::Error,
+    Level::Warn,
+    Level::Info,
+    Level::Debug,
+    Level::Trace,
+];
+
+/// A single record, formatted on the stack.
+struct Entry {
+    data: [u8; MAX_ENTRY],
+    len: usize,
+    truncated: bool,
+}
+
+impl Entry {
+    fn new(level: Level) -> Self {
+        let mut data = [0; MAX_ENTRY];
+        data[2] = level as u8;
+        Self {
+            data,
+            len: HEADER,
+            truncated: false,
+        }
+    }
+
+    fn format(record: &Record) -> Self {
+        let mut entry = Self::new(record.level());
+        let _ = entry.write_str(record.target());
+        let _ = entry.write_char(SEPARATOR as char);
+        let _ = entry.write_fmt(*record.args());
+        entry.set_len();
+        entry
+    }
+
+    /// Shortens the entry to fit, at a char boundary.
+    fn truncate(&mut self, max: usize) {
+        if self.len <= max {
+            return;
+        }
+        let mut len = max;
+        while len > HEADER && (self.data[len] & 0b1100_0000) == 0b1000_0000 {
+            len -= 1;
+        }
+        self.len = len;
+        self.truncated = true;
+        self.set_len();
+    }
+
+    fn set_len(&mut self) {
+        self.data[..2].copy_from_slice(&(self.len as u16).to_le_bytes());
+    }
+
+    fn emit_to(&self, log: &dyn Log) {
+        let level = LEVELS
+            .get(usize::from(self.data[2]).wrapping_sub(1))
+            .copied()
+            .unwrap_or(Level::Info);
+        let body = &self.data[HEADER..self.len];
+        let (target, message) = match body.iter().position(|b| *b == SEPARATOR) {
+            Some(pos) => (&body[..pos], &body[pos + 1..]),
+            None => (body, &[][..]),
+        };
+        // Truncated at char boundaries, so this doesn't fail.
+        let target = str::from_utf8(target).unwrap_or_default();
+        let message = str::from_utf8(message).unwrap_or_default();
+        log.log(
+            &Record::builder()
+                .level(level)
+                .target(target)
+                .args(format_args!("{}", message))
+                .build(),
+        );
+    }
+}
+
+impl Write for Entry {
+    fn write_str(&mut self, s: &str) -> FmtResult {
+        let room = MAX_ENTRY - self.len;
+        let mut take = s.len().min(room);
+        while !s.is_char_boundary(take) {
+            take -= 1;
+        }
+        self.data[self.len..self.len + take].copy_from_slice(&s.as_bytes()[..take]);
+        self.len += take;
+        if take < s.len() {
+            self.truncated = true;
+        }
+        Ok(())
+    }
+}
+
+struct Ring<const BYTES: usize> {
+    data: [u8; BYTES],
+    start: usize,
+    used: usize,
+    entries: usize,
+    truncated: bool,
+    overwritten: u64,
+}
+
+impl<const BYTES: usize> Ring<BYTES> {
+    fn byte(&self, offset: usize) -> u8 {
+        self.data[(self.start + offset) % BYTES]
+    }
+
+    fn drop_oldest(&mut self) -> Option<usize> {
+        if self.entries == 0 {
+            return None;
+        }
+        let len = usize::from(u16::from_le_bytes([self.byte(0), self.byte(1)]));
+        self.start = (self.start + len) % BYTES;
+        self.used -= len;
+        self.entries -= 1;
+        Some(len)
+    }
+
+    fn push(&mut self, mut entry: Entry) {
+        entry.truncate(BYTES.min(MAX_ENTRY));
+        if entry.len <= HEADER {
+            // Doesn't fit at all.
+            self.overwritten += 1;
+            return;
+        }
+        while BYTES - self.used < entry.len {
+            self.drop_oldest();
+            self.overwritten += 1;
+        }
+        for (i, b) in entry.data[..entry.len].iter().enumerate() {
+            self.data[(self.start + self.used + i) % BYTES] = *b;
+        }
+        self.used += entry.len;
+        self.entries += 1;
+        self.truncated |= entry.truncated;
+    }
+
+    fn pop(&mut self) -> Option<Entry> {
+        let mut entry = Entry::new(Level::Info);
+        let start = self.start;
+        let len = self.drop_oldest()?;
+        for (i, b) in entry.data[..len].iter_mut().enumerate() {
+            *b = self.data[(start + i) % BYTES];
+        }
+        entry.len = len;
+        Some(entry)
+    }
+}
+
+/// A buffer of the early records that doesn't allocate.
+///
+/// This is an alternative to the [`Buffer`][crate::Buffer] for when allocation is not possible
+/// (yet). It formats the records into a fixed ring of `BYTES` bytes, overwriting the oldest ones
+/// when it's full. The records longer than 512 bytes (or than the whole buffer) are truncated.
+/// Only the level, the target and the message are kept.
+///
+/// It can be created in a `static` and used as the initial slave of a const-constructed
+/// [`Reroute`][crate::Reroute] by
+/// [`Reroute::const_with_buffer`][crate::Reroute::const_with_buffer]. When the first real slave
+/// replaces it, it gets all the stored records. Alternatively, they can be sent anywhere by
+/// [`replay_into`][StaticBuffer::replay_into].
+///
+/// ```rust
+/// use std::sync::{Arc, Mutex};
+///
+/// use log::{Level, Log, Metadata, Record};
+/// use log_reroute::{Reroute, StaticBuffer};
+///
+/// static EARLY: StaticBuffer<4096> = StaticBuffer::new();
+/// static REROUTE: Reroute = Reroute::const_with_buffer(&EARLY);
+///
+/// #[derive(Clone, Default)]
+/// struct Lines(Arc<Mutex<Vec<String>>>);
+///
+/// impl Log for Lines {
+///     fn enabled(&self, _: &Metadata) -> bool {
+///         true
+///     }
+///     fn log(&self, record: &Record) {
+///         let line = format!("{} {}: {}", record.level(), record.target(), record.args());
+///         self.0.lock().unwrap().push(line);
+///     }
+///     fn flush(&self) {}
+/// }
+///
+/// REROUTE.log(
+///     &Record::builder()
+///         .level(Level::Warn)
+///         .target("boot")
+///         .args(format_args!("Booting {}", 1))
+///         .build(),
+/// );
+/// assert_eq!(1, EARLY.len());
+///
+/// let lines = Lines::default();
+/// REROUTE.reroute(lines.clone());
+/// assert_eq!(vec!["WARN boot: Booting 1"], *lines.0.lock().unwrap());
+/// assert!(EARLY.is_empty());
+/// ```
+pub struct StaticBuffer<const BYTES: usize> {
+    ring: Mutex<Ring<BYTES>>,
+}
+
+impl<const BYTES: usize> StaticBuffer<BYTES> {
+    /// Creates an empty buffer.
+    pub const fn new() -> Self {
+        Self {
+            ring: Mutex::new(Ring {
+                data: [0; BYTES],
+                start: 0,
+                used: 0,
+                entries: 0,
+                truncated: false,
+                overwritten: 0,
+            }),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Ring<BYTES>> {
+        self.ring.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// How many records are stored.
+    pub fn len(&self) -> usize {
+        self.lock().entries
+    }
+
+    /// Is the buffer empty?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Was any of the records truncated?
+    ///
+    /// ```rust
+    /// use log::{Level, Log, Record};
+    /// use log_reroute::StaticBuffer;
+    ///
+    /// fn log(buffer: &dyn Log, msg: &str) {
+    ///     buffer.log(
+    ///         &Record::builder()
+    ///             .level(Level::Info)
+    ///             .target("t")
+    ///             .args(format_args!("{}", msg))
+    ///             .build(),
+    ///     );
+    /// }
+    ///
+    /// // Each entry has 5 bytes of overhead
+    /// let buffer = StaticBuffer::<16>::new();
+    /// log(&buffer, "12345");
+    /// assert!(!buffer.truncated());
+    /// // Overwrites the previous one
+    /// log(&buffer, "abcdef");
+    /// assert_eq!((1, 1), (buffer.len(), buffer.overwritten()));
+    /// // Too long
+    /// log(&buffer, "This does not fit");
+    /// assert!(buffer.truncated());
+    /// assert_eq!((1, 2), (buffer.len(), buffer.overwritten()));
+    /// ```
+    pub fn truncated(&self) -> bool {
+        self.lock().truncated
+    }
+
+    /// How many records were overwritten (or didn't fit at all).
+    pub fn overwritten(&self) -> u64 {
+        self.lock().overwritten
+    }
+
+    /// Sends the stored records into the logger, in order, removing them from the buffer.
+    pub fn replay_into(&self, log: &dyn Log) {
+        // Not holding the lock while logging, the logger might log into us.
+        while let Some(entry) = self.lock().pop() {
+            entry.emit_to(log);
+        }
+    }
+}
+
+impl<const BYTES: usize> Default for StaticBuffer<BYTES> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const BYTES: usize> Debug for StaticBuffer<BYTES> {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        let ring = self.lock();
+        fmt.debug_struct("StaticBuffer")
+            .field("bytes", &BYTES)
+            .field("len", &ring.entries)
+            .field("truncated", &ring.truncated)
+            .field("overwritten", &ring.overwritten)
+            .finish()
+    }
+}
+
+impl<const BYTES: usize> Log for StaticBuffer<BYTES> {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+    fn log(&self, record: &Record) {
+        // Formatting into the entry may log again, the ring can't be locked yet.
+        let entry = Entry::format(record);
+        self.lock().push(entry);
+    }
+    fn flush(&self) {}
+}
+
+impl<const BYTES: usize> OnDisplace for StaticBuffer<BYTES> {
+    fn on_displace(&self, next: &dyn Log) {
+        self.replay_into(next);
+    }
+}
+
+/// The static initial slave of a [`Reroute`][crate::Reroute].
+pub(crate) trait Early: OnDisplace {
+    fn as_log(&self) -> &dyn Log;
+}
+
+impl<const BYTES: usize> Early for StaticBuffer<BYTES> {
+    fn as_log(&self) -> &dyn Log {
+        self
+    }
+}
@@ -0,0 +1,82 @@
+//! Counting the records going through a reroute.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use log::Level;
+
+/// The numbers of records that went through a [`Reroute`][crate::Reroute].
+///
+/// Returned by [`Reroute::stats`][crate::Reroute::stats].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct Stats {
+    /// The records of the `Error` level.
+    pub error: u64,
+    /// The records of the `Warn` level.
+    pub warn: u64,
+    /// The records of the `Info` level.
+    pub info: u64,
+    /// The records of the `Debug` level.
+    pub debug: u64,
+    /// The records of the `Trace` level.
+    pub trace: u64,
+    /// The records (of any level) that went to the [`Dummy`][crate::Dummy], so they were lost.
+    pub discarded: u64,
+}
+
+impl Stats {
+    /// The records of the level.
+    pub fn level(&self, level: Level) -> u64 {
+        match level {
+            Level::Error => self.error,
+            Level::Warn => self.warn,
+            Level::Info => self.info,
+            Level::Debug => self.debug,
+            Level::Trace => self.trace,
+        }
+    }
+
+    /// All the records.
+    pub fn total(&self) -> u64 {
+        self.error + self.warn + self.info + self.debug + self.trace
+    }
+}
+
+pub(crate) struct Counters {
+    levels: [AtomicU64; 5],
+    discarded: AtomicU64,
+}
+
+impl Counters {
+    pub(crate) const fn new() -> Self {
+        Self {
+            levels: [const { AtomicU64::new(0) }; 5],
+            discarded: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn count(&self, level: Level, dummy: bool) {
+        self.levels[level as usize - 1].fetch_add(1, Ordering::Relaxed);
+        if dummy {
+            self.discarded.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn snapshot(&self) -> Stats {
+        let level = |level: Level| self.levels[level as usize - 1].load(Ordering::Relaxed);
+        Stats {
+            error: level(Level::Error),
+            warn: level(Level::Warn),
+            info: level(Level::Info),
+            debug: level(Level::Debug),
+            trace: level(Level::Trace),
+            discarded: self.discarded.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn reset(&self) {
+        for counter in self.levels.iter().chain([&self.discarded]) {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+}
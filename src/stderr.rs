@@ -0,0 +1,119 @@
+//! A minimal logger writing to stderr.
+//!
+//! This is not a replacement of a full-featured logger, only a bootstrap that needs no other
+//! dependencies.
+
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::io::{self, IsTerminal, Write};
+use std::sync::OnceLock;
+use std::time::SystemTime;
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+use crate::format::{ColorChoice, Format, Plain};
+use crate::WriteLog;
+
+const FORMAT: Plain = Plain::new().with_color(ColorChoice::Auto);
+
+/// Formats the record and writes it with stderr locked, as a single line.
+///
+/// Formatting into the locked stderr directly could split the line into several writes,
+/// interleaved with the other threads.
+pub(crate) fn write_line<F: Format + ?Sized>(format: &F, record: &Record, terminal: bool) {
+    let mut line = Vec::new();
+    // Nowhere to report the failure to write the log.
+    if format
+        .format_for(&mut line, record, SystemTime::now(), terminal)
+        .is_ok()
+    {
+        let _ = io::stderr().lock().write_all(&line);
+    }
+}
+
+/// A simple logger writing to stderr, with the levels colored on a terminal.
+///
+/// The internal fallback, with no configuration; the public one is [`Stderr`].
+pub(crate) struct StderrLog;
+
+impl Log for StderrLog {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+    fn log(&self, record: &Record) {
+        static TERMINAL: OnceLock<bool> = OnceLock::new();
+        let terminal = *TERMINAL.get_or_init(|| io::stderr().is_terminal());
+        write_line(&FORMAT, record, terminal);
+    }
+    fn flush(&self) {
+        let _ = io::stderr().flush();
+    }
+}
+
+/// A simple logger writing the records to stderr.
+///
+/// Each record is a line in the [`Plain`] `LEVEL target: message` format (or another
+/// [format][Stderr::with_format]), with the levels colored if stderr is a terminal (see
+/// [`ColorChoice::Auto`]). The line is formatted first and then written with stderr locked, so
+/// it doesn't interleave with the output of `eprintln!` or of other threads. This is a
+/// [`WriteLog`] writing to [`io::stderr`], so it reports the failed writes the same way.
+///
+/// The records more verbose than the level are ignored.
+///
+/// ```rust
+/// use log::{Level, LevelFilter, Log, Metadata};
+/// use log_reroute::Stderr;
+///
+/// let stderr = Stderr::new(LevelFilter::Info);
+/// let metadata = |level| Metadata::builder().level(level).build();
+/// assert!(stderr.enabled(&metadata(Level::Warn)));
+/// assert!(!stderr.enabled(&metadata(Level::Debug)));
+/// ```
+pub struct Stderr<F = Plain>(WriteLog<io::Stderr, F>);
+
+impl Stderr {
+    /// Creates the logger, writing the records of the level and more severe.
+    pub fn new(level: LevelFilter) -> Self {
+        Self(
+            WriteLog::with_level(io::stderr(), level)
+                .with_format(FORMAT)
+                .detect_terminal(),
+        )
+    }
+}
+
+impl<F: Format> Stderr<F> {
+    /// Sets the format of the lines.
+    pub fn with_format<G: Format>(self, format: G) -> Stderr<G> {
+        Stderr(self.0.with_format(format))
+    }
+
+    /// How many records failed to be written.
+    pub fn errors(&self) -> u64 {
+        self.0.errors()
+    }
+}
+
+impl Default for Stderr {
+    /// Writes all the records.
+    fn default() -> Self {
+        Self::new(LevelFilter::Trace)
+    }
+}
+
+impl<F> Debug for Stderr<F> {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_tuple("Stderr").field(&self.0).finish()
+    }
+}
+
+impl<F: Format> Log for Stderr<F> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.0.enabled(metadata)
+    }
+    fn log(&self, record: &Record) {
+        self.0.log(record)
+    }
+    fn flush(&self) {
+        self.0.flush()
+    }
+}
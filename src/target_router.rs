@@ -0,0 +1,184 @@
+//! Sending the records to different loggers by their target.
+
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+use log::{Log, Metadata, Record};
+
+use crate::filter::lookup;
+
+/// A logger shared between routing tables.
+pub type SharedLog = Arc<Box<dyn Log>>;
+
+/// The routing table of a [`TargetRouter`].
+///
+/// Maps the target prefixes to loggers. A prefix matches the target equal to it and its
+/// submodules (so `hyper` matches `hyper` and `hyper::client`, but not `hyperx`); the most
+/// specific (longest) matching prefix wins. The empty prefix matches only the empty target. The
+/// records not matching any prefix go to the default logger.
+///
+/// The loggers are shared, so a new table can be built from the same loggers.
+#[derive(Clone)]
+pub struct TargetRoutes {
+    routes: Vec<(String, SharedLog)>,
+    default: SharedLog,
+}
+
+impl TargetRoutes {
+    /// Creates a table sending everything to the default logger.
+    pub fn new(default: SharedLog) -> Self {
+        Self {
+            routes: Vec::new(),
+            default,
+        }
+    }
+
+    /// Adds a route for the prefix.
+    ///
+    /// Replaces the previous route of the same prefix, if any.
+    pub fn route<P: Into<String>>(mut self, prefix: P, log: SharedLog) -> Self {
+        let prefix = prefix.into();
+        self.routes.retain(|(existing, _)| *existing != prefix);
+        self.routes.push((prefix, log));
+        self
+    }
+
+    /// The logger for the target.
+    fn resolve(&self, target: &str) -> &dyn Log {
+        &***lookup(&self.routes, target).unwrap_or(&self.default)
+    }
+}
+
+impl Debug for TargetRoutes {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        let prefixes = self
+            .routes
+            .iter()
+            .map(|(prefix, _)| prefix)
+            .collect::<Vec<_>>();
+        fmt.debug_struct("TargetRoutes")
+            .field("prefixes", &prefixes)
+            .finish()
+    }
+}
+
+/// A logger sending the records to different loggers by their target.
+///
+/// The routes are described by [`TargetRoutes`]. The table can be replaced at runtime by
+/// [`set_routes`][TargetRouter::set_routes], atomically and without locking the logging. This is
+/// a handle; the clones share the same table, so one can be installed in a
+/// [`Reroute`][crate::Reroute] while another is kept to change the routes.
+///
+/// Whether a record is [enabled][Log::enabled] is decided by the logger its target resolves
+/// to.
+///
+/// ```rust
+/// use std::sync::{Arc, Mutex};
+///
+/// use log::{Level, Log, Metadata, Record};
+/// use log_reroute::{Reroute, SharedLog, TargetRouter, TargetRoutes};
+///
+/// #[derive(Clone, Default)]
+/// struct Targets(Arc<Mutex<Vec<String>>>);
+///
+/// impl Log for Targets {
+///     fn enabled(&self, _: &Metadata) -> bool {
+///         true
+///     }
+///     fn log(&self, record: &Record) {
+///         self.0.lock().unwrap().push(record.target().to_owned());
+///     }
+///     fn flush(&self) {}
+/// }
+///
+/// fn info(reroute: &Reroute, target: &str) {
+///     reroute.log(
+///         &Record::builder()
+///             .level(Level::Info)
+///             .target(target)
+///             .args(format_args!("Hello"))
+///             .build(),
+///     );
+/// }
+///
+/// let main = Targets::default();
+/// let gpu = Targets::default();
+/// let audit = Targets::default();
+/// let main_log: SharedLog = Arc::new(Box::new(main.clone()));
+/// let gpu_log: SharedLog = Arc::new(Box::new(gpu.clone()));
+/// let audit_log: SharedLog = Arc::new(Box::new(audit.clone()));
+///
+/// let router = TargetRouter::new(
+///     TargetRoutes::new(Arc::clone(&main_log))
+///         .route("wgpu", Arc::clone(&gpu_log))
+///         .route("my_app::audit", audit_log),
+/// );
+/// let reroute = Reroute::new();
+/// reroute.reroute(router.clone());
+/// for target in ["wgpu", "wgpu::device", "wgpux", "my_app", "my_app::audit::login", ""] {
+///     info(&reroute, target);
+/// }
+/// assert_eq!(vec!["wgpu", "wgpu::device"], *gpu.0.lock().unwrap());
+/// assert_eq!(vec!["my_app::audit::login"], *audit.0.lock().unwrap());
+/// assert_eq!(vec!["wgpux", "my_app", ""], *main.0.lock().unwrap());
+///
+/// // Stop separating the audit log
+/// router.set_routes(TargetRoutes::new(main_log).route("wgpu", gpu_log));
+/// info(&reroute, "my_app::audit");
+/// assert_eq!(1, audit.0.lock().unwrap().len());
+/// assert_eq!(4, main.0.lock().unwrap().len());
+/// ```
+#[derive(Clone)]
+pub struct TargetRouter {
+    routes: Arc<ArcSwap<TargetRoutes>>,
+}
+
+impl TargetRouter {
+    /// Creates a router with the routing table.
+    pub fn new(routes: TargetRoutes) -> Self {
+        Self {
+            routes: Arc::new(ArcSwap::from_pointee(routes)),
+        }
+    }
+
+    /// The current routing table.
+    pub fn routes(&self) -> Arc<TargetRoutes> {
+        self.routes.load_full()
+    }
+
+    /// Replaces the routing table.
+    ///
+    /// The loggers not present in the new table are not flushed or dropped until nothing else
+    /// uses them (eg. the previous table).
+    pub fn set_routes(&self, routes: TargetRoutes) {
+        self.routes.store(Arc::new(routes));
+    }
+}
+
+impl Debug for TargetRouter {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_struct("TargetRouter")
+            .field("routes", &self.routes.load())
+            .finish()
+    }
+}
+
+impl Log for TargetRouter {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.routes
+            .load()
+            .resolve(metadata.target())
+            .enabled(metadata)
+    }
+    fn log(&self, record: &Record) {
+        self.routes.load().resolve(record.target()).log(record)
+    }
+    fn flush(&self) {
+        let routes = self.routes.load();
+        for (_, log) in &routes.routes {
+            log.flush();
+        }
+        routes.default.flush();
+    }
+}
@@ -0,0 +1,156 @@
+//! Support for async code running on tokio.
+//!
+//! Contains task-local overrides of the slave and flushing without blocking the runtime.
+
+use std::future::Future;
+use std::panic;
+
+use log::Log;
+use tokio::task::{self, JoinError};
+
+use crate::{typed, wait_released, Dummy, Reroute, Slave};
+
+tokio::task_local! {
+    /// The override, with the ID of the reroute it belongs to.
+    static OVERRIDE: (u64, Slave);
+}
+
+pub(crate) fn get(id: u64) -> Option<Slave> {
+    OVERRIDE
+        .try_with(|(o, slave)| {
+            if *o == id {
+                Some(Slave::clone(slave))
+            } else {
+                None
+            }
+        })
+        .ok()
+        .flatten()
+}
+
+impl Reroute {
+    /// Wraps a future so the records logged while polling it go to a different slave.
+    ///
+    /// This is like [`set_thread_override`][Reroute::set_thread_override], but for a logical
+    /// tokio task that may move between threads. Anything logged from within the future
+    /// (including the futures it awaits, but not separately spawned tasks) goes to the provided
+    /// slave instead of the shared one.
+    ///
+    /// The override takes precedence over the thread-local one. Nesting these (even for
+    /// different reroutes) makes the innermost one win.
+    ///
+    /// ```rust
+    /// use log_reroute::{Dummy, Reroute};
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let reroute = Reroute::new();
+    /// reroute
+    ///     .with_task_logger(Dummy, async {
+    ///         // Logs from here go to the Dummy
+    ///     })
+    ///     .await;
+    /// # }
+    /// ```
+    pub fn with_task_logger<L, F>(&self, log: L, fut: F) -> impl Future<Output = F::Output>
+    where
+        L: Log + 'static,
+        F: Future,
+    {
+        let (slave, _) = typed(log);
+        OVERRIDE.scope((self.local_id(), slave), fut)
+    }
+
+    /// Flushes the slave without blocking the async runtime.
+    ///
+    /// Flushing a slave (eg. a buffered file) may take a long time. This one runs the flush on
+    /// the blocking thread pool of tokio (see [`spawn_blocking`][task::spawn_blocking]) and waits
+    /// for it asynchronously. It flushes the [task override][Reroute::with_task_logger], if there
+    /// is one, the shared slave otherwise.
+    ///
+    /// Must be called from within a tokio runtime.
+    ///
+    /// ```rust
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    /// use std::sync::Arc;
+    /// use std::thread;
+    /// use std::time::Duration;
+    ///
+    /// use log::{Log, Metadata, Record};
+    /// use log_reroute::Reroute;
+    ///
+    /// struct Slow(Arc<AtomicBool>);
+    ///
+    /// impl Log for Slow {
+    ///     fn enabled(&self, _: &Metadata) -> bool {
+    ///         true
+    ///     }
+    ///     fn log(&self, _: &Record) {}
+    ///     fn flush(&self) {
+    ///         thread::sleep(Duration::from_millis(100));
+    ///         self.0.store(true, Ordering::Relaxed);
+    ///     }
+    /// }
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let flushed = Arc::new(AtomicBool::new(false));
+    /// let reroute = Reroute::with(Slow(Arc::clone(&flushed)));
+    /// // The other future gets to run while the flush is still in progress.
+    /// let ((), during) = tokio::join!(reroute.flush_async(), async {
+    ///     flushed.load(Ordering::Relaxed)
+    /// });
+    /// assert!(!during);
+    /// assert!(flushed.load(Ordering::Relaxed));
+    /// # }
+    /// ```
+    pub async fn flush_async(&self) {
+        let slave = get(self.local_id()).unwrap_or_else(|| self.get());
+        check(task::spawn_blocking(move || slave.flush()).await);
+    }
+
+    /// Shuts the logging down without blocking the async runtime.
+    ///
+    /// This [flushes][Reroute::flush_async] the slave, replaces it with the [`Dummy`] and waits
+    /// for everyone else to release the old slave (see [`swap_and_wait`][Reroute::swap_and_wait]).
+    /// All the blocking parts run on the blocking thread pool.
+    ///
+    /// Must be called from within a tokio runtime.
+    pub async fn shutdown_async(&self) {
+        self.flush_async().await;
+        let (dummy, info) = typed(Dummy);
+        let old = self.replace_unconditionally(dummy, info).slave;
+        // Without a deadline, it waits until it succeeds.
+        check(task::spawn_blocking(move || drop(wait_released(old, None))).await);
+    }
+}
+
+/// Propagates a panic from a blocking task.
+fn check(result: Result<(), JoinError>) {
+    if let Err(e) = result {
+        if e.is_panic() {
+            panic::resume_unwind(e.into_panic());
+        }
+    }
+}
+
+/// Wraps a future so the records logged while polling it go to a different slave.
+///
+/// This is the same as [`Reroute::with_task_logger`] on the global [`REROUTE`][crate::REROUTE].
+#[cfg(feature = "global")]
+pub fn with_task_logger<L, F>(log: L, fut: F) -> impl Future<Output = F::Output>
+where
+    L: Log + 'static,
+    F: Future,
+{
+    crate::REROUTE.with_task_logger(log, fut)
+}
+
+/// Shuts down the logging of the global [`REROUTE`][crate::REROUTE] without blocking the async
+/// runtime.
+///
+/// See [`Reroute::shutdown_async`].
+#[cfg(feature = "global")]
+pub async fn shutdown_async() {
+    crate::REROUTE.shutdown_async().await
+}
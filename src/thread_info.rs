@@ -0,0 +1,137 @@
+//! The thread and process of the records.
+
+use std::thread;
+
+/// Calls the closure with the name of the current thread.
+///
+/// The unnamed threads are called by their ID, like `ThreadId(12)`. The name is cached for each
+/// thread.
+pub(crate) fn with_thread_name<R, F: FnOnce(&str) -> R>(f: F) -> R {
+    thread_local! {
+        static NAME: String = {
+            let current = thread::current();
+            match current.name() {
+                Some(name) => name.to_owned(),
+                None => format!("{:?}", current.id()),
+            }
+        };
+    }
+    NAME.with(|name| f(name))
+}
+
+#[cfg(feature = "kv")]
+pub use self::wrapper::ThreadInfo;
+
+#[cfg(feature = "kv")]
+mod wrapper {
+    use std::fmt::{Debug, Formatter, Result as FmtResult};
+    use std::process;
+
+    use log::kv::{Error, Key, Source, Value, VisitSource};
+    use log::{Log, Metadata, Record};
+
+    use super::with_thread_name;
+    use crate::transform::rebuild;
+
+    struct Pairs<'a> {
+        thread: &'a str,
+        pid: Option<u32>,
+    }
+
+    impl Source for Pairs<'_> {
+        fn visit<'kvs>(&'kvs self, visitor: &mut dyn VisitSource<'kvs>) -> Result<(), Error> {
+            visitor.visit_pair(Key::from_str("thread"), Value::from(self.thread))?;
+            if let Some(pid) = self.pid {
+                visitor.visit_pair(Key::from_str("pid"), Value::from(pid))?;
+            }
+            Ok(())
+        }
+    }
+
+    /// A wrapper adding the thread (and optionally the process) to the key-values of each record.
+    ///
+    /// The key `thread` is the name of the thread logging the record, or its ID for the unnamed
+    /// threads (like `ThreadId(12)`). With [`with_pid`][ThreadInfo::with_pid], there's also the
+    /// `pid` key with the process ID.
+    ///
+    /// Without the key-values, the same can be put into the lines by the `{thread}` and `{pid}`
+    /// placeholders of the [`Pattern`][crate::Pattern].
+    ///
+    /// Requires the `kv` feature.
+    ///
+    /// ```rust
+    /// use std::process;
+    /// use std::thread;
+    ///
+    /// use log::kv::Key;
+    /// use log::LevelFilter;
+    /// use log_reroute::{FnLog, ThreadInfo};
+    ///
+    /// let log = ThreadInfo::new(FnLog::new(|record| {
+    ///     let kvs = record.key_values();
+    ///     let thread = kvs.get(Key::from_str("thread")).unwrap().to_string();
+    ///     assert_eq!("worker", thread);
+    ///     let pid = kvs.get(Key::from_str("pid")).unwrap().to_u64();
+    ///     assert_eq!(Some(u64::from(process::id())), pid);
+    /// }))
+    /// .with_pid();
+    ///
+    /// log::set_max_level(LevelFilter::Info);
+    /// thread::Builder::new()
+    ///     .name("worker".to_owned())
+    ///     .spawn(move || log::info!(logger: &log, "Working"))
+    ///     .unwrap()
+    ///     .join()
+    ///     .unwrap();
+    /// ```
+    pub struct ThreadInfo<L> {
+        inner: L,
+        pid: bool,
+    }
+
+    impl<L: Log> ThreadInfo<L> {
+        /// Wraps the logger, adding the thread.
+        pub fn new(inner: L) -> Self {
+            Self { inner, pid: false }
+        }
+
+        /// Adds the process ID too.
+        pub fn with_pid(mut self) -> Self {
+            self.pid = true;
+            self
+        }
+
+        /// The wrapped logger.
+        pub fn inner(&self) -> &L {
+            &self.inner
+        }
+    }
+
+    impl<L> Debug for ThreadInfo<L> {
+        fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+            fmt.debug_struct("ThreadInfo")
+                .field("pid", &self.pid)
+                .finish()
+        }
+    }
+
+    impl<L: Log> Log for ThreadInfo<L> {
+        fn enabled(&self, metadata: &Metadata) -> bool {
+            self.inner.enabled(metadata)
+        }
+        fn log(&self, record: &Record) {
+            with_thread_name(|thread| {
+                let pairs = Pairs {
+                    thread,
+                    pid: self.pid.then(process::id),
+                };
+                let key_values: [&dyn Source; 2] = [record.key_values(), &pairs];
+                self.inner
+                    .log(&rebuild(record).key_values(&key_values).build());
+            })
+        }
+        fn flush(&self) {
+            self.inner.flush();
+        }
+    }
+}
@@ -0,0 +1,161 @@
+//! Rewriting the records.
+
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+
+use log::{Log, Metadata, Record, RecordBuilder};
+
+type Prefix = Box<dyn Fn(&Record) -> String + Send + Sync>;
+type Target = Box<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+/// A builder of a copy of the record, keeping everything.
+pub(crate) fn rebuild<'a>(record: &'a Record) -> RecordBuilder<'a> {
+    #[cfg(feature = "kv")]
+    {
+        record.to_builder()
+    }
+    #[cfg(not(feature = "kv"))]
+    {
+        let mut builder = Record::builder();
+        builder
+            .level(record.level())
+            .target(record.target())
+            .args(*record.args())
+            .line(record.line());
+        match record.module_path_static() {
+            Some(path) => builder.module_path_static(Some(path)),
+            None => builder.module_path(record.module_path()),
+        };
+        match record.file_static() {
+            Some(file) => builder.file_static(Some(file)),
+            None => builder.file(record.file()),
+        };
+        builder
+    }
+}
+
+/// A wrapper rewriting the records before passing them on.
+///
+/// It can prefix the message (eg. by `[worker-3] `) and map the target. Everything else (the
+/// level, module path, file, line) is passed unchanged. The wrapped logger gets an ordinary
+/// [`Record`].
+///
+/// ```rust
+/// use std::sync::{Arc, Mutex};
+///
+/// use log::{Level, Log, Record};
+/// use log_reroute::{FnLog, Transform};
+///
+/// let lines = Arc::new(Mutex::new(Vec::new()));
+/// let collect = Arc::clone(&lines);
+/// let inner = FnLog::new(move |record| {
+///     let line = format!("{} {}: {}", record.level(), record.target(), record.args());
+///     collect.lock().unwrap().push(line);
+/// });
+/// let transform = Transform::new(inner)
+///     .with_prefix(|_| "[worker-3] ".to_owned())
+///     .with_target(|target| target.strip_prefix("engine::").map(str::to_owned));
+///
+/// for target in ["engine::db", "app"] {
+///     transform.log(
+///         &Record::builder()
+///             .level(Level::Warn)
+///             .target(target)
+///             .args(format_args!("Slow"))
+///             .build(),
+///     );
+/// }
+/// assert_eq!(
+///     vec!["WARN db: [worker-3] Slow", "WARN app: [worker-3] Slow"],
+///     *lines.lock().unwrap(),
+/// );
+/// ```
+pub struct Transform<L> {
+    inner: L,
+    prefix: Option<Prefix>,
+    target: Option<Target>,
+}
+
+impl<L: Log> Transform<L> {
+    /// Wraps the logger, not changing anything yet.
+    pub fn new(inner: L) -> Self {
+        Self {
+            inner,
+            prefix: None,
+            target: None,
+        }
+    }
+
+    /// Prefixes the message of each record by what the closure returns.
+    pub fn with_prefix<F>(mut self, prefix: F) -> Self
+    where
+        F: Fn(&Record) -> String + Send + Sync + 'static,
+    {
+        self.prefix = Some(Box::new(prefix));
+        self
+    }
+
+    /// Maps the targets; the closure returns the new target or `None` to keep it.
+    ///
+    /// The targets of the [metadata][Log::enabled] are mapped too.
+    pub fn with_target<F>(mut self, target: F) -> Self
+    where
+        F: Fn(&str) -> Option<String> + Send + Sync + 'static,
+    {
+        self.target = Some(Box::new(target));
+        self
+    }
+
+    /// The wrapped logger.
+    pub fn inner(&self) -> &L {
+        &self.inner
+    }
+
+    fn map_target(&self, target: &str) -> Option<String> {
+        self.target.as_ref().and_then(|map| map(target))
+    }
+}
+
+impl<L> Debug for Transform<L> {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_struct("Transform")
+            .field("prefix", &self.prefix.is_some())
+            .field("target", &self.target.is_some())
+            .finish()
+    }
+}
+
+impl<L: Log> Log for Transform<L> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        match self.map_target(metadata.target()) {
+            Some(target) => self.inner.enabled(
+                &Metadata::builder()
+                    .level(metadata.level())
+                    .target(&target)
+                    .build(),
+            ),
+            None => self.inner.enabled(metadata),
+        }
+    }
+    fn log(&self, record: &Record) {
+        let target = self.map_target(record.target());
+        let prefix = self.prefix.as_ref().map(|prefix| prefix(record));
+        if target.is_none() && prefix.is_none() {
+            return self.inner.log(record);
+        }
+        let mut builder = rebuild(record);
+        if let Some(target) = &target {
+            builder.target(target);
+        }
+        match &prefix {
+            Some(prefix) => self.inner.log(
+                &builder
+                    .args(format_args!("{}{}", prefix, record.args()))
+                    .build(),
+            ),
+            None => self.inner.log(&builder.build()),
+        }
+    }
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
@@ -0,0 +1,190 @@
+//! Cutting the too long messages.
+
+use std::fmt::{Debug, Formatter, Result as FmtResult, Write};
+
+#[cfg(feature = "kv")]
+use log::kv::Source;
+use log::{LevelFilter, Log, Metadata, Record};
+
+use crate::transform::rebuild;
+
+/// Counts the length of the formatted message, without storing it.
+struct Count(usize);
+
+impl Write for Count {
+    fn write_str(&mut self, s: &str) -> FmtResult {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+/// Keeps the beginning of the formatted message, up to the limit.
+struct Prefix {
+    kept: String,
+    limit: usize,
+}
+
+impl Write for Prefix {
+    fn write_str(&mut self, s: &str) -> FmtResult {
+        let room = self.limit - self.kept.len();
+        let mut take = s.len().min(room);
+        while !s.is_char_boundary(take) {
+            take -= 1;
+        }
+        self.kept.push_str(&s[..take]);
+        if take < s.len() {
+            // Stop formatting the rest.
+            self.limit = self.kept.len();
+            return Err(std::fmt::Error);
+        }
+        Ok(())
+    }
+}
+
+/// A wrapper cutting the messages longer than a limit.
+///
+/// The message of a longer record is cut at a char boundary (so it's at most `limit` bytes) and
+/// `… [truncated N bytes]` is appended. With the `kv` feature, the record also gets the
+/// `truncated = true` key-value. The messages within the limit are passed on untouched (and
+/// checking them doesn't allocate).
+///
+/// The severe records (eg. the panics with their backtraces) can be [exempt][Truncate::exempt].
+///
+/// ```rust
+/// use std::sync::{Arc, Mutex};
+///
+/// use log::{Level, LevelFilter, Log, Record};
+/// use log_reroute::{FnLog, Truncate};
+///
+/// let messages = Arc::new(Mutex::new(Vec::new()));
+/// let collect = Arc::clone(&messages);
+/// let truncate = Truncate::new(
+///     FnLog::new(move |record| collect.lock().unwrap().push(record.args().to_string())),
+///     6,
+/// )
+/// .exempt(LevelFilter::Error);
+///
+/// for (level, msg) in [
+///     (Level::Info, "short"),
+///     (Level::Info, "Too long message"),
+///     // "ž" takes 2 bytes, it doesn't fit after the first 5 ones
+///     (Level::Info, "abcdežf"),
+///     (Level::Error, "Exempt from truncation"),
+/// ] {
+///     truncate.log(
+///         &Record::builder()
+///             .level(level)
+///             .args(format_args!("{}", msg))
+///             .build(),
+///     );
+/// }
+/// assert_eq!(
+///     vec![
+///         "short",
+///         "Too lo… [truncated 10 bytes]",
+///         "abcde… [truncated 3 bytes]",
+///         "Exempt from truncation",
+///     ],
+///     *messages.lock().unwrap(),
+/// );
+/// ```
+///
+/// With the `kv` feature, the truncated records are marked:
+///
+/// ```rust
+/// # #![cfg_attr(not(feature = "kv"), allow(unused))]
+/// # #[cfg(feature = "kv")]
+/// # fn main() {
+/// use log::kv::Key;
+/// use log::{LevelFilter, Record};
+/// use log_reroute::{FnLog, Truncate};
+///
+/// log::set_max_level(LevelFilter::Info);
+/// let truncate = Truncate::new(
+///     FnLog::new(|record: &Record| {
+///         let truncated = record.key_values().get(Key::from_str("truncated"));
+///         let long = record.args().to_string().contains("[truncated");
+///         assert_eq!(long, truncated.and_then(|t| t.to_bool()).unwrap_or(false));
+///         assert!(record.key_values().get(Key::from_str("user")).is_some());
+///     }),
+///     8,
+/// );
+/// log::info!(logger: &truncate, user = "joe"; "short");
+/// log::info!(logger: &truncate, user = "joe"; "A bit too long");
+/// # }
+/// # #[cfg(not(feature = "kv"))]
+/// # fn main() {}
+/// ```
+pub struct Truncate<L> {
+    inner: L,
+    limit: usize,
+    exempt: LevelFilter,
+}
+
+impl<L: Log> Truncate<L> {
+    /// Wraps the logger, cutting the messages longer than `limit` bytes.
+    pub fn new(inner: L, limit: usize) -> Self {
+        Self {
+            inner,
+            limit,
+            exempt: LevelFilter::Off,
+        }
+    }
+
+    /// Doesn't cut the records of this level and more severe.
+    pub fn exempt(mut self, level: LevelFilter) -> Self {
+        self.exempt = level;
+        self
+    }
+
+    /// The wrapped logger.
+    pub fn inner(&self) -> &L {
+        &self.inner
+    }
+}
+
+impl<L> Debug for Truncate<L> {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_struct("Truncate")
+            .field("limit", &self.limit)
+            .field("exempt", &self.exempt)
+            .finish()
+    }
+}
+
+impl<L: Log> Log for Truncate<L> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+    fn log(&self, record: &Record) {
+        if record.level() <= self.exempt {
+            return self.inner.log(record);
+        }
+        let mut count = Count(0);
+        let _ = count.write_fmt(*record.args());
+        if count.0 <= self.limit {
+            return self.inner.log(record);
+        }
+        let mut prefix = Prefix {
+            kept: String::with_capacity(self.limit),
+            limit: self.limit,
+        };
+        let _ = prefix.write_fmt(*record.args());
+        let cut = count.0 - prefix.kept.len();
+        let mut builder = rebuild(record);
+        #[cfg(feature = "kv")]
+        let truncated = ("truncated", true);
+        #[cfg(feature = "kv")]
+        let key_values: [&dyn Source; 2] = [record.key_values(), &truncated];
+        #[cfg(feature = "kv")]
+        builder.key_values(&key_values);
+        self.inner.log(
+            &builder
+                .args(format_args!("{}… [truncated {} bytes]", prefix.kept, cut))
+                .build(),
+        );
+    }
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
@@ -0,0 +1,114 @@
+//! The [`TypedReroute`] (statically typed variant of [`Reroute`][crate::Reroute]).
+
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::sync::Arc;
+
+use arc_swap::ArcSwapOption;
+use log::{Log, Metadata, Record};
+
+/// A logging proxy switching between slaves of the same type.
+///
+/// This is like [`Reroute`][crate::Reroute], but all the slaves are of the type `L`, known at
+/// compile time. That saves the `Box` indirection and the dynamic dispatch on each log call, at
+/// the cost of flexibility.
+///
+/// Instead of a [`Dummy`][crate::Dummy], the proxy can be cleared, in which case it throws all
+/// the logs away.
+///
+/// ```rust
+/// use fern::Dispatch;
+/// use log_reroute::TypedReroute;
+///
+/// let reroute = TypedReroute::new();
+/// reroute.reroute(Dispatch::new().chain(std::io::stderr()).into_log().1);
+/// reroute.reroute(Dispatch::new().chain(std::io::stdout()).into_log().1);
+/// reroute.clear();
+/// ```
+pub struct TypedReroute<L> {
+    inner: ArcSwapOption<L>,
+}
+
+impl<L: Log> TypedReroute<L> {
+    /// Creates a new cleared [`TypedReroute`].
+    pub fn new() -> Self {
+        Self {
+            inner: ArcSwapOption::empty(),
+        }
+    }
+
+    /// Creates a new [`TypedReroute`] with a slave already set.
+    pub fn with(log: L) -> Self {
+        Self {
+            inner: ArcSwapOption::from_pointee(log),
+        }
+    }
+
+    /// Sets a new slave logger.
+    ///
+    /// The old one (if any) is flushed, as with [`Reroute::reroute`][crate::Reroute::reroute].
+    pub fn reroute(&self, log: L) {
+        self.reroute_arc(Arc::new(log));
+    }
+
+    /// Sets a new slave logger, in its inner representation.
+    pub fn reroute_arc(&self, log: Arc<L>) {
+        if let Some(old) = self.inner.swap(Some(log)) {
+            old.flush();
+        }
+    }
+
+    /// Sets a new slave logger and returns the previous one (without flushing it).
+    pub fn swap(&self, log: L) -> Option<Arc<L>> {
+        self.inner.swap(Some(Arc::new(log)))
+    }
+
+    /// Stubs out the logger.
+    ///
+    /// The old slave is flushed and from now on, all the logs are thrown away.
+    pub fn clear(&self) {
+        if let Some(old) = self.inner.swap(None) {
+            old.flush();
+        }
+    }
+
+    /// Gives access to the inner logger.
+    ///
+    /// Returns `None` if cleared. See [`Reroute::get`][crate::Reroute::get].
+    pub fn get(&self) -> Option<Arc<L>> {
+        self.inner.load_full()
+    }
+}
+
+impl<L: Log> Log for TypedReroute<L> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        match &*self.inner.load() {
+            Some(log) => log.enabled(metadata),
+            None => false,
+        }
+    }
+    fn log(&self, record: &Record) {
+        if let Some(log) = &*self.inner.load() {
+            log.log(record)
+        }
+    }
+    fn flush(&self) {
+        if let Some(log) = &*self.inner.load() {
+            log.flush()
+        }
+    }
+}
+
+impl<L: Log> Default for TypedReroute<L> {
+    /// Creates a cleared [`TypedReroute`].
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<L> Debug for TypedReroute<L> {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_struct("TypedReroute")
+            .field("cleared", &self.inner.load().is_none())
+            .finish()
+    }
+}
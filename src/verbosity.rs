@@ -0,0 +1,136 @@
+//! Mapping of the `-v`/`-q` command line flags to a level.
+
+use log::LevelFilter;
+
+use crate::Reroute;
+
+/// The verbosity of a program, as usually set by the `-v` and `-q` command line flags.
+///
+/// The verbosity is the number of `-v` flags minus the number of `-q`s. It maps to the levels
+/// like this (the mapping is stable):
+///
+/// | verbosity | level   |
+/// |-----------|---------|
+/// | -2 and less | `Off` |
+/// | -1        | `Error` |
+/// | 0         | `Warn`  |
+/// | 1         | `Info`  |
+/// | 2         | `Debug` |
+/// | 3 and more | `Trace` |
+///
+/// With the `clap` feature, this can be [flattened] into a clap parser, providing the
+/// `-v`/`--verbose` and `-q`/`--quiet` flags:
+///
+/// ```rust
+/// # #![cfg_attr(not(feature = "clap"), allow(unused))]
+/// # #[cfg(feature = "clap")]
+/// # fn main() {
+/// use clap::Parser;
+/// use log::LevelFilter;
+/// use log_reroute::Verbosity;
+///
+/// #[derive(Parser)]
+/// struct Cli {
+///     #[command(flatten)]
+///     verbosity: Verbosity,
+/// }
+///
+/// let cli = Cli::parse_from(["prog", "-vv"]);
+/// assert_eq!(LevelFilter::Debug, cli.verbosity.level());
+/// # }
+/// # #[cfg(not(feature = "clap"))]
+/// # fn main() {}
+/// ```
+///
+/// [flattened]: https://docs.rs/clap/latest/clap/_derive/index.html#flattening
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "clap", derive(clap::Args))]
+pub struct Verbosity {
+    /// Log more (can be repeated).
+    #[cfg_attr(feature = "clap", arg(short, long, action = clap::ArgAction::Count, global = true))]
+    verbose: u8,
+    /// Log less (can be repeated).
+    #[cfg_attr(
+        feature = "clap",
+        arg(short, long, action = clap::ArgAction::Count, global = true, conflicts_with = "verbose")
+    )]
+    quiet: u8,
+}
+
+impl Verbosity {
+    /// Creates the verbosity from the signed count.
+    pub const fn new(verbosity: i8) -> Self {
+        if verbosity >= 0 {
+            Verbosity {
+                verbose: verbosity as u8,
+                quiet: 0,
+            }
+        } else {
+            Verbosity {
+                verbose: 0,
+                quiet: verbosity.unsigned_abs(),
+            }
+        }
+    }
+
+    /// Creates the verbosity from the number of the `-v` and `-q` flags.
+    pub const fn from_flags(verbose: u8, quiet: u8) -> Self {
+        Verbosity { verbose, quiet }
+    }
+
+    /// The signed count (the number of `-v` minus the number of `-q`).
+    pub fn count(&self) -> i16 {
+        i16::from(self.verbose) - i16::from(self.quiet)
+    }
+
+    /// The level corresponding to the verbosity.
+    ///
+    /// ```rust
+    /// use log::LevelFilter;
+    /// use log_reroute::Verbosity;
+    ///
+    /// assert_eq!(LevelFilter::Off, Verbosity::new(-5).level());
+    /// assert_eq!(LevelFilter::Error, Verbosity::new(-1).level());
+    /// assert_eq!(LevelFilter::Warn, Verbosity::default().level());
+    /// assert_eq!(LevelFilter::Info, Verbosity::from_flags(2, 1).level());
+    /// assert_eq!(LevelFilter::Trace, Verbosity::new(3).level());
+    /// assert_eq!(LevelFilter::Trace, Verbosity::new(100).level());
+    /// ```
+    pub fn level(&self) -> LevelFilter {
+        match self.count() {
+            i16::MIN..=-2 => LevelFilter::Off,
+            -1 => LevelFilter::Error,
+            0 => LevelFilter::Warn,
+            1 => LevelFilter::Info,
+            2 => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+        }
+    }
+
+    /// Sets both the [gate][Reroute::set_gate] of the reroute and the [`log::max_level`] to the
+    /// [level][Verbosity::level].
+    ///
+    /// ```rust
+    /// use log::LevelFilter;
+    /// use log_reroute::{Reroute, Verbosity};
+    ///
+    /// let reroute = Reroute::new();
+    /// Verbosity::new(1).apply(&reroute);
+    /// assert_eq!(LevelFilter::Info, reroute.gate());
+    /// assert_eq!(LevelFilter::Info, log::max_level());
+    /// ```
+    pub fn apply(&self, reroute: &Reroute) {
+        let level = self.level();
+        reroute.set_gate(level);
+        log::set_max_level(level);
+    }
+}
+
+/// Applies the verbosity to the global [`Reroute`].
+///
+/// This is a shortcut for `Verbosity::new(verbosity).apply(&REROUTE)`, see [`Verbosity`] for the
+/// mapping.
+#[cfg(feature = "global")]
+pub fn verbosity(verbosity: i8) {
+    Verbosity::new(verbosity).apply(&crate::REROUTE);
+}
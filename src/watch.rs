@@ -0,0 +1,178 @@
+//! Re-applying the configuration when its file changes.
+
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use log::{Level, Log, Record};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::{Config, Reroute};
+
+/// How long the file needs to stay untouched before it is read.
+///
+/// Editors tend to write the file in several steps.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+enum Message {
+    Event(notify::Result<Event>),
+    Stop,
+}
+
+/// A running watch of a configuration file.
+///
+/// Returned by [`Reroute::watch_config`]. Dropping it stops the watching (and waits for the
+/// background thread to terminate).
+#[derive(Debug)]
+pub struct WatchHandle {
+    stop: Sender<Message>,
+    thread: Option<JoinHandle<()>>,
+    watcher: Option<RecommendedWatcher>,
+}
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        let _ = self.stop.send(Message::Stop);
+        if let Some(thread) = self.thread.take() {
+            // A panic in the thread was already reported by it.
+            let _ = thread.join();
+        }
+        self.watcher.take();
+    }
+}
+
+/// Tells if the event concerns the file.
+fn concerns(event: &notify::Result<Event>, path: &Path) -> bool {
+    match event {
+        Ok(event) => event
+            .paths
+            .iter()
+            .any(|p| p.file_name() == path.file_name()),
+        // Better to look at the file needlessly than miss a change.
+        Err(_) => true,
+    }
+}
+
+impl Reroute {
+    fn warn_config(&self, path: &Path, error: &dyn Display) {
+        self.log(
+            &Record::builder()
+                .level(Level::Warn)
+                .target("log_reroute")
+                .args(format_args!(
+                    "Keeping the previous logging config, {} is broken: {}",
+                    path.display(),
+                    error
+                ))
+                .build(),
+        );
+    }
+
+    fn reload_config<F, E>(&self, path: &Path, parse: &F)
+    where
+        F: Fn(&str) -> Result<Config, E>,
+        E: Display,
+    {
+        let config = match fs::read_to_string(path) {
+            Ok(content) => parse(&content),
+            Err(e) => return self.warn_config(path, &e),
+        };
+        match config {
+            Ok(config) => {
+                if let Err(e) = config.apply(self) {
+                    self.warn_config(path, &e);
+                }
+            }
+            Err(e) => self.warn_config(path, &e),
+        }
+    }
+
+    /// Watches the configuration file and [applies][Config::apply] it each time it changes.
+    ///
+    /// The closure extracts the logging configuration from the content of the file (which may
+    /// contain other things too). The file is watched in a background thread, a burst of changes
+    /// is handled as a single one. The file is not applied until the first change.
+    ///
+    /// If the file can't be read, parsed or applied, the previous configuration stays and a
+    /// single warning is logged about it.
+    ///
+    /// ```rust
+    /// use std::fs;
+    /// use std::thread;
+    /// use std::time::Duration;
+    ///
+    /// use log::LevelFilter;
+    /// use log_reroute::{Config, Reroute};
+    ///
+    /// static REROUTE: Reroute = Reroute::const_new();
+    ///
+    /// let dir = tempfile::tempdir().unwrap();
+    /// let path = dir.path().join("config.json");
+    /// fs::write(&path, "{}").unwrap();
+    ///
+    /// let _watch = REROUTE
+    ///     .watch_config(&path, |content| serde_json::from_str::<Config>(content))
+    ///     .unwrap();
+    /// fs::write(&path, r#"{ "level": "trace", "destination": { "kind": "null" } }"#).unwrap();
+    /// while log::max_level() != LevelFilter::Trace {
+    ///     thread::sleep(Duration::from_millis(10));
+    /// }
+    /// ```
+    pub fn watch_config<P, F, E>(&'static self, path: P, parse: F) -> notify::Result<WatchHandle>
+    where
+        P: Into<PathBuf>,
+        F: Fn(&str) -> Result<Config, E> + Send + 'static,
+        E: Display,
+    {
+        let path = path.into();
+        let (sender, receiver) = mpsc::channel();
+        let events = sender.clone();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = events.send(Message::Event(event));
+        })?;
+        // Editors often replace the file by a new one, so the directory needs watching.
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        let thread = thread::Builder::new()
+            .name("log-reroute-watch".to_owned())
+            .spawn(move || {
+                let mut changed = false;
+                loop {
+                    let timeout = if changed { DEBOUNCE } else { Duration::MAX };
+                    match receiver.recv_timeout(timeout) {
+                        Ok(Message::Event(event)) => changed |= concerns(&event, &path),
+                        Ok(Message::Stop) | Err(RecvTimeoutError::Disconnected) => break,
+                        Err(RecvTimeoutError::Timeout) => {
+                            changed = false;
+                            self.reload_config(&path, &parse);
+                        }
+                    }
+                }
+            })
+            .map_err(notify::Error::io)?;
+        Ok(WatchHandle {
+            stop: sender,
+            thread: Some(thread),
+            watcher: Some(watcher),
+        })
+    }
+}
+
+/// Watches the configuration file, applying it to the global [`Reroute`].
+///
+/// See [`Reroute::watch_config`].
+#[cfg(feature = "global")]
+pub fn watch_config<P, F, E>(path: P, parse: F) -> notify::Result<WatchHandle>
+where
+    P: Into<PathBuf>,
+    F: Fn(&str) -> Result<Config, E> + Send + 'static,
+    E: Display,
+{
+    crate::REROUTE.watch_config(path, parse)
+}
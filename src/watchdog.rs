@@ -0,0 +1,191 @@
+//! Noticing a slow logger.
+
+use std::convert::TryFrom;
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use log::{Level, Log, Metadata, Record};
+
+use crate::stderr::StderrLog;
+
+/// The slow calls seen by a [`Watchdog`].
+///
+/// Returned by [`Watchdog::stats`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct WatchdogStats {
+    /// How many calls took longer than the threshold.
+    pub slow: u64,
+    /// The longest of the slow calls (zero if there was none).
+    pub max: Duration,
+}
+
+/// A wrapper measuring how long the logger takes.
+///
+/// Each [`log`][Log::log] and [`flush`][Log::flush] call into the inner logger is timed (the cost
+/// is reading the clock twice). The calls longer than the threshold are counted, see
+/// [`stats`][Watchdog::stats]. With [`with_warnings`][Watchdog::with_warnings], they are also
+/// reported by a warning to stderr (or to the logger set by
+/// [`with_fallback`][Watchdog::with_fallback]), never to the slow logger itself. The warnings are
+/// rate limited, so a logger that stays slow doesn't flood the fallback.
+///
+/// This doesn't make the slow logger any faster, the logging threads still wait for it. It only
+/// tells why the application stalls.
+///
+/// ```rust
+/// use std::sync::{Arc, Mutex};
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// use log::{Level, Log, Record};
+/// use log_reroute::{FnLog, Watchdog};
+///
+/// let warnings = Arc::new(Mutex::new(Vec::new()));
+/// let collect = Arc::clone(&warnings);
+/// let watchdog = Watchdog::new(
+///     FnLog::new(|record: &Record| {
+///         if record.level() == Level::Error {
+///             thread::sleep(Duration::from_millis(20));
+///         }
+///     }),
+///     Duration::from_millis(10),
+/// )
+/// .with_warnings(Duration::from_secs(3600))
+/// .with_fallback(FnLog::new(move |record: &Record| {
+///     collect.lock().unwrap().push(record.args().to_string());
+/// }));
+///
+/// watchdog.log(&Record::builder().level(Level::Info).build());
+/// assert_eq!(0, watchdog.stats().slow);
+/// for _ in 0..3 {
+///     watchdog.log(&Record::builder().level(Level::Error).build());
+/// }
+/// let stats = watchdog.stats();
+/// assert_eq!(3, stats.slow);
+/// assert!(stats.max >= Duration::from_millis(20));
+/// // Warned only once in the interval
+/// let warnings = warnings.lock().unwrap();
+/// assert_eq!(1, warnings.len());
+/// assert!(warnings[0].starts_with("Logging took "), "{}", warnings[0]);
+/// ```
+pub struct Watchdog<L> {
+    inner: L,
+    threshold: Duration,
+    warn_interval: Option<Duration>,
+    last_warning: Mutex<Option<Instant>>,
+    fallback: Box<dyn Log>,
+    slow: AtomicU64,
+    max_nanos: AtomicU64,
+}
+
+impl<L: Log> Watchdog<L> {
+    /// Wraps the logger, counting the calls longer than the threshold.
+    pub fn new(inner: L, threshold: Duration) -> Self {
+        Self {
+            inner,
+            threshold,
+            warn_interval: None,
+            last_warning: Mutex::new(None),
+            fallback: Box::new(StderrLog),
+            slow: AtomicU64::new(0),
+            max_nanos: AtomicU64::new(0),
+        }
+    }
+
+    /// Warns about the slow calls, at most once per the interval.
+    ///
+    /// The warnings go to stderr, unless [`with_fallback`][Watchdog::with_fallback] is used.
+    pub fn with_warnings(mut self, interval: Duration) -> Self {
+        self.warn_interval = Some(interval);
+        self
+    }
+
+    /// Sends the warnings to this logger instead of stderr.
+    ///
+    /// This alone doesn't turn the warnings on.
+    pub fn with_fallback<F: Log + 'static>(mut self, fallback: F) -> Self {
+        self.fallback = Box::new(fallback);
+        self
+    }
+
+    /// The slow calls seen so far.
+    pub fn stats(&self) -> WatchdogStats {
+        WatchdogStats {
+            slow: self.slow.load(Ordering::Relaxed),
+            max: Duration::from_nanos(self.max_nanos.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// The wrapped logger.
+    pub fn inner(&self) -> &L {
+        &self.inner
+    }
+
+    fn timed<F: FnOnce(&L)>(&self, f: F) {
+        let start = Instant::now();
+        f(&self.inner);
+        let took = start.elapsed();
+        if took > self.threshold {
+            self.slow_call(took);
+        }
+    }
+
+    #[cold]
+    fn slow_call(&self, took: Duration) {
+        let slow = self.slow.fetch_add(1, Ordering::Relaxed) + 1;
+        let nanos = u64::try_from(took.as_nanos()).unwrap_or(u64::MAX);
+        self.max_nanos.fetch_max(nanos, Ordering::Relaxed);
+        let interval = match self.warn_interval {
+            Some(interval) => interval,
+            None => return,
+        };
+        {
+            let mut last = self
+                .last_warning
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            let now = Instant::now();
+            if last.is_some_and(|last| now.duration_since(last) < interval) {
+                return;
+            }
+            *last = Some(now);
+        }
+        self.fallback.log(
+            &Record::builder()
+                .level(Level::Warn)
+                .target("log_reroute")
+                .args(format_args!(
+                    "Logging took {:?} (more than {:?}), {} slow calls so far",
+                    took, self.threshold, slow
+                ))
+                .build(),
+        );
+    }
+}
+
+impl<L> Debug for Watchdog<L> {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_struct("Watchdog")
+            .field("threshold", &self.threshold)
+            .field("warn_interval", &self.warn_interval)
+            .field("slow", &self.slow.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+impl<L: Log> Log for Watchdog<L> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+    fn log(&self, record: &Record) {
+        self.timed(|inner| inner.log(record));
+    }
+    fn flush(&self) {
+        self.timed(|inner| inner.flush());
+        if self.warn_interval.is_some() {
+            self.fallback.flush();
+        }
+    }
+}
@@ -0,0 +1,115 @@
+//! Slaves that don't keep their logger alive.
+
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
+
+use log::{Log, Metadata, Record};
+
+use crate::Reroute;
+
+/// A logger forwarding to a [`Weak`] reference.
+///
+/// It forwards to the target as long as it exists, but doesn't keep it alive. Once the target is
+/// gone, it behaves like the [`Dummy`][crate::Dummy] (and doesn't try to reach the target any
+/// more).
+///
+/// See [`Reroute::reroute_weak`].
+pub struct WeakLog {
+    target: Weak<dyn Log>,
+    gone: AtomicBool,
+}
+
+impl WeakLog {
+    /// Creates the logger, forwarding to the given target.
+    pub fn new(target: Weak<dyn Log>) -> Self {
+        Self {
+            target,
+            gone: AtomicBool::new(false),
+        }
+    }
+
+    /// Is the target gone already?
+    pub fn is_gone(&self) -> bool {
+        self.gone.load(Ordering::Relaxed) || self.target.strong_count() == 0
+    }
+
+    fn with_target<R, F: FnOnce(&dyn Log) -> R>(&self, default: R, f: F) -> R {
+        if self.gone.load(Ordering::Relaxed) {
+            return default;
+        }
+        match self.target.upgrade() {
+            Some(target) => f(&*target),
+            None => {
+                // Don't bother upgrading again next time.
+                self.gone.store(true, Ordering::Relaxed);
+                default
+            }
+        }
+    }
+}
+
+impl Log for WeakLog {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.with_target(false, |log| log.enabled(metadata))
+    }
+    fn log(&self, record: &Record) {
+        self.with_target((), |log| log.log(record))
+    }
+    fn flush(&self) {
+        self.with_target((), |log| log.flush())
+    }
+}
+
+impl Debug for WeakLog {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_struct("WeakLog")
+            .field("gone", &self.is_gone())
+            .finish()
+    }
+}
+
+impl From<&Arc<dyn Log>> for WeakLog {
+    fn from(target: &Arc<dyn Log>) -> Self {
+        Self::new(Arc::downgrade(target))
+    }
+}
+
+impl Reroute {
+    /// Sets a slave that isn't kept alive by the reroute.
+    ///
+    /// The records are sent to the target as long as someone else holds it. Once it is dropped,
+    /// the records are thrown away, as with the [`Dummy`][crate::Dummy]. This saves the owner of
+    /// the logger from having to [`clear`][Reroute::clear] the reroute when it's done with it.
+    ///
+    /// See [`WeakLog`].
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use log::{Level, Log, Metadata, Record};
+    /// use log_reroute::{Dummy, Reroute};
+    ///
+    /// struct Panel;
+    ///
+    /// impl Log for Panel {
+    ///     fn enabled(&self, _: &Metadata) -> bool {
+    ///         true
+    ///     }
+    ///     fn log(&self, _: &Record) {}
+    ///     fn flush(&self) {}
+    /// }
+    ///
+    /// let reroute = Reroute::with(Dummy);
+    /// let panel: Arc<dyn Log> = Arc::new(Panel);
+    /// reroute.reroute_weak(Arc::downgrade(&panel));
+    ///
+    /// let metadata = Metadata::builder().level(Level::Info).build();
+    /// assert!(reroute.enabled(&metadata));
+    /// drop(panel);
+    /// assert!(!reroute.enabled(&metadata));
+    /// ```
+    pub fn reroute_weak(&self, target: Weak<dyn Log>) {
+        self.reroute(WeakLog::new(target));
+    }
+}
@@ -0,0 +1,296 @@
+//! The background threads.
+
+use std::convert::TryFrom;
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError, Weak};
+use std::thread::{self, JoinHandle, ThreadId};
+use std::time::{Duration, Instant};
+
+use log::{Level, Log, Record};
+
+use crate::panic::message;
+use crate::stderr::StderrLog;
+
+/// How long [`shutdown`] (and the guards of the workers) wait by default.
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How many times in a row the body may panic before the worker gives up.
+const MAX_RESTARTS: u32 = 16;
+
+/// The configured shutdown timeout, in milliseconds.
+static SHUTDOWN_TIMEOUT: AtomicU64 = AtomicU64::new(DEFAULT_SHUTDOWN_TIMEOUT.as_millis() as u64);
+
+/// All the workers that may still run.
+static WORKERS: Mutex<Vec<Weak<Shared>>> = Mutex::new(Vec::new());
+
+fn workers() -> MutexGuard<'static, Vec<Weak<Shared>>> {
+    WORKERS.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+struct Shared {
+    stop: Box<dyn Fn() + Send + Sync>,
+    thread_id: Mutex<Option<ThreadId>>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+    running: AtomicBool,
+    panics: AtomicU64,
+    done: Mutex<bool>,
+    finished: Condvar,
+}
+
+impl Shared {
+    fn lock_done(&self) -> MutexGuard<'_, bool> {
+        self.done.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Asks the worker to stop and waits for it until the deadline.
+    fn stop(&self, deadline: Instant) -> bool {
+        (self.stop)();
+        let current = *self
+            .thread_id
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if current == Some(thread::current().id()) {
+            // Can't wait for ourselves.
+            return false;
+        }
+        let mut done = self.lock_done();
+        while !*done {
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            done = self
+                .finished
+                .wait_timeout(done, deadline - now)
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+        }
+        drop(done);
+        let thread = self
+            .thread
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        if let Some(thread) = thread {
+            let _ = thread.join();
+        }
+        true
+    }
+}
+
+/// Marks the worker as finished, even if its thread ends by a panic.
+struct Finish(Arc<Shared>);
+
+impl Drop for Finish {
+    fn drop(&mut self) {
+        self.0.running.store(false, Ordering::Release);
+        *self.0.lock_done() = true;
+        self.0.finished.notify_all();
+    }
+}
+
+fn run<B: FnMut() -> bool>(shared: Arc<Shared>, mut body: B) {
+    let _finish = Finish(Arc::clone(&shared));
+    let mut restarts = 0;
+    loop {
+        match panic::catch_unwind(AssertUnwindSafe(&mut body)) {
+            Ok(true) => restarts = 0,
+            Ok(false) => break,
+            Err(payload) => {
+                if shared.panics.fetch_add(1, Ordering::Relaxed) == 0 {
+                    let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+                        StderrLog.log(
+                            &Record::builder()
+                                .level(Level::Error)
+                                .target("log_reroute")
+                                .args(format_args!(
+                                    "A log-reroute worker panicked, restarting it: {}",
+                                    message(&*payload)
+                                ))
+                                .build(),
+                        )
+                    }));
+                }
+                let _ = panic::catch_unwind(AssertUnwindSafe(|| drop(payload)));
+                restarts += 1;
+                if restarts > MAX_RESTARTS {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// A background thread of this crate.
+///
+/// The worker thread (named `log-reroute/worker`) calls the body over and over, until it returns
+/// `false`. Each call is expected to do a bit of work (eg. to handle one message from a queue).
+/// If the body panics, the panic is reported to stderr (only the first one) and the body is
+/// called again, so whatever it keeps (like the rest of the queue) survives. A body that keeps
+/// panicking (more than 16 times in a row) is given up on.
+///
+/// The workers are used by [`Async`][crate::Async] and the other background parts of the crate,
+/// but can be used for custom ones too. Each worker can be [stopped][Worker::stop] on its own and
+/// all of them by [`shutdown`].
+///
+/// ```rust
+/// use std::sync::mpsc;
+///
+/// use log_reroute::Worker;
+///
+/// let (sender, receiver) = mpsc::channel::<Option<u32>>();
+/// let (results, collected) = mpsc::channel();
+/// let stop = sender.clone();
+/// let worker = Worker::spawn(
+///     move || match receiver.recv() {
+///         Ok(Some(n)) => {
+///             if n == 0 {
+///                 panic!("Can't handle zero");
+///             }
+///             results.send(n).unwrap();
+///             true
+///         }
+///         _ => false,
+///     },
+///     move || {
+///         let _ = stop.send(None);
+///     },
+/// )
+/// .unwrap();
+///
+/// for n in [1, 0, 2] {
+///     sender.send(Some(n)).unwrap();
+/// }
+/// assert_eq!(1, collected.recv().unwrap());
+/// // Survived the panic
+/// assert_eq!(2, collected.recv().unwrap());
+/// assert!(worker.is_healthy());
+/// assert_eq!(1, worker.panics());
+///
+/// assert!(worker.stop());
+/// assert!(!worker.is_healthy());
+/// ```
+#[derive(Clone)]
+pub struct Worker {
+    shared: Arc<Shared>,
+}
+
+impl Worker {
+    /// Starts a worker.
+    ///
+    /// The `stop` closure is called when the worker is asked to stop; it should make the body
+    /// return `false` soon (eg. by sending a message to it).
+    pub fn spawn<B, S>(body: B, stop: S) -> io::Result<Self>
+    where
+        B: FnMut() -> bool + Send + 'static,
+        S: Fn() + Send + Sync + 'static,
+    {
+        let shared = Arc::new(Shared {
+            stop: Box::new(stop),
+            thread_id: Mutex::new(None),
+            thread: Mutex::new(None),
+            running: AtomicBool::new(true),
+            panics: AtomicU64::new(0),
+            done: Mutex::new(false),
+            finished: Condvar::new(),
+        });
+        let worker_shared = Arc::clone(&shared);
+        let thread = thread::Builder::new()
+            .name("log-reroute/worker".to_owned())
+            .spawn(move || run(worker_shared, body))?;
+        *shared
+            .thread_id
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(thread.thread().id());
+        *shared.thread.lock().unwrap_or_else(PoisonError::into_inner) = Some(thread);
+        let mut workers = workers();
+        workers.retain(|w| w.strong_count() > 0);
+        workers.push(Arc::downgrade(&shared));
+        Ok(Self { shared })
+    }
+
+    /// Is the worker running?
+    ///
+    /// It is not once it was stopped, its body decided to end or it panicked too many times in a
+    /// row.
+    pub fn is_healthy(&self) -> bool {
+        self.shared.running.load(Ordering::Acquire)
+    }
+
+    /// How many times the body panicked.
+    pub fn panics(&self) -> u64 {
+        self.shared.panics.load(Ordering::Relaxed)
+    }
+
+    /// Stops the worker and waits for it.
+    ///
+    /// Waits at most for the [configured timeout][set_shutdown_timeout]. Returns if the worker
+    /// finished in time.
+    pub fn stop(&self) -> bool {
+        self.shared.stop(Instant::now() + shutdown_timeout())
+    }
+
+    /// Is the calling thread the one of this worker?
+    pub(crate) fn is_current(&self) -> bool {
+        *self
+            .shared
+            .thread_id
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            == Some(thread::current().id())
+    }
+}
+
+impl Debug for Worker {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_struct("Worker")
+            .field("healthy", &self.is_healthy())
+            .field("panics", &self.panics())
+            .finish()
+    }
+}
+
+fn shutdown_timeout() -> Duration {
+    Duration::from_millis(SHUTDOWN_TIMEOUT.load(Ordering::Relaxed))
+}
+
+/// Sets how long [`shutdown`] and [`Worker::stop`] wait for the workers.
+///
+/// The default is [`DEFAULT_SHUTDOWN_TIMEOUT`]. A worker that doesn't finish in time (eg. because
+/// it's stuck writing into a hung logger) is left running, so it doesn't prevent the process
+/// from exiting.
+pub fn set_shutdown_timeout(timeout: Duration) {
+    let millis = u64::try_from(timeout.as_millis()).unwrap_or(u64::MAX);
+    SHUTDOWN_TIMEOUT.store(millis, Ordering::Relaxed);
+}
+
+/// Stops all the [workers][Worker] and waits for them.
+///
+/// All of them together are waited for at most the [configured
+/// timeout][set_shutdown_timeout]. Returns if all finished in time.
+///
+/// ```rust
+/// use log::{Level, Log, Record};
+/// use log_reroute::{Async, Dummy};
+///
+/// let (log, _guard) = Async::new(Dummy);
+/// log.log(&Record::builder().level(Level::Info).build());
+/// // At the end of main
+/// assert!(log_reroute::shutdown());
+/// ```
+pub fn shutdown() -> bool {
+    let deadline = Instant::now() + shutdown_timeout();
+    let running = workers()
+        .iter()
+        .filter_map(Weak::upgrade)
+        .collect::<Vec<_>>();
+    let mut all = true;
+    for worker in running {
+        // Not short-circuiting, the rest of them are to be stopped too.
+        all &= worker.stop(deadline);
+    }
+    all
+}
@@ -0,0 +1,242 @@
+//! A logger writing lines into an [`io::Write`].
+
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::SystemTime;
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+use crate::format::{Format, Plain};
+use crate::Json;
+
+/// A logger writing the records as lines into any [`io::Write`].
+///
+/// Each record is a single line, in the [`Plain`] `LEVEL target: message` format unless
+/// [another one][WriteLog::with_format] is set. The line is formatted first and then written with
+/// the writer locked, so the lines from different threads don't interleave.
+///
+/// A failure to write (eg. a full disk) doesn't panic, there's nowhere to report it from inside
+/// the logger. The failed records are lost (a partially written line is not completed) and
+/// counted, see [`errors`][WriteLog::errors].
+///
+/// ```rust
+/// use std::io::{self, Write};
+///
+/// use log::{Level, LevelFilter, Log, Record};
+/// use log_reroute::WriteLog;
+///
+/// fn log(log: &dyn Log, level: Level) {
+///     log.log(
+///         &Record::builder()
+///             .level(level)
+///             .target("app")
+///             .args(format_args!("Hello"))
+///             .build(),
+///     );
+/// }
+///
+/// let out = WriteLog::with_level(Vec::new(), LevelFilter::Info);
+/// log(&out, Level::Info);
+/// log(&out, Level::Debug);
+/// assert_eq!(b"INFO app: Hello\n", &out.into_inner()[..]);
+///
+/// struct Full;
+///
+/// impl Write for Full {
+///     fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+///         Err(io::Error::new(io::ErrorKind::Other, "No space left"))
+///     }
+///     fn flush(&mut self) -> io::Result<()> {
+///         Ok(())
+///     }
+/// }
+///
+/// let full = WriteLog::new(Full);
+/// log(&full, Level::Error);
+/// log(&full, Level::Error);
+/// assert_eq!(2, full.errors());
+/// ```
+pub struct WriteLog<W, F = Plain> {
+    level: LevelFilter,
+    format: F,
+    terminal: bool,
+    errors: AtomicU64,
+    on_error: Option<ErrorHandler>,
+    writer: Mutex<W>,
+}
+
+type ErrorHandler = Box<dyn Fn(&io::Error) + Send + Sync>;
+
+impl<W: Write + Send> WriteLog<W> {
+    /// Creates the logger writing all the records.
+    pub fn new(writer: W) -> Self {
+        Self::with_level(writer, LevelFilter::Trace)
+    }
+
+    /// Creates the logger writing the records of the given level and more severe.
+    pub fn with_level(writer: W, level: LevelFilter) -> Self {
+        Self {
+            level,
+            format: Plain::new(),
+            terminal: false,
+            errors: AtomicU64::new(0),
+            on_error: None,
+            writer: Mutex::new(writer),
+        }
+    }
+}
+
+impl<W: Write + Send + IsTerminal, F: Format> WriteLog<W, F> {
+    /// Sets if the writer is a terminal by checking it.
+    ///
+    /// ```rust
+    /// use std::io;
+    ///
+    /// use log_reroute::{ColorChoice, Plain, WriteLog};
+    ///
+    /// let stderr = WriteLog::new(io::stderr())
+    ///     .with_format(Plain::new().with_color(ColorChoice::Auto))
+    ///     .detect_terminal();
+    /// # drop(stderr);
+    /// ```
+    pub fn detect_terminal(self) -> Self {
+        let terminal = self
+            .writer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_terminal();
+        self.with_terminal(terminal)
+    }
+}
+
+impl<W: Write + Send> WriteLog<W, Json> {
+    /// Creates the logger writing all the records as [`Json`] lines.
+    pub fn json(writer: W) -> Self {
+        WriteLog::new(writer).with_format(Json)
+    }
+}
+
+impl<W: Write + Send, F: Format> WriteLog<W, F> {
+    /// Sets the format of the lines.
+    pub fn with_format<G: Format>(self, format: G) -> WriteLog<W, G> {
+        WriteLog {
+            level: self.level,
+            format,
+            terminal: self.terminal,
+            errors: self.errors,
+            on_error: self.on_error,
+            writer: self.writer,
+        }
+    }
+
+    /// Sets if the writer is a terminal, for the formats using colors.
+    ///
+    /// It is assumed not to be by default.
+    pub fn with_terminal(mut self, terminal: bool) -> Self {
+        self.terminal = terminal;
+        self
+    }
+
+    /// Calls the handler each time writing or flushing fails.
+    ///
+    /// The handler runs in the logging thread. It must not log into this logger (a failure would
+    /// call it again), but it may eg. log somewhere else or set a flag for a health check.
+    ///
+    /// ```rust
+    /// use std::io::{self, Write};
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// use log::Log;
+    /// use log_reroute::WriteLog;
+    ///
+    /// struct Broken;
+    ///
+    /// impl Write for Broken {
+    ///     fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+    ///         Ok(0)
+    ///     }
+    ///     fn flush(&mut self) -> io::Result<()> {
+    ///         Err(io::Error::new(io::ErrorKind::Other, "Broken"))
+    ///     }
+    /// }
+    ///
+    /// let failed = Arc::new(AtomicBool::new(false));
+    /// let flag = Arc::clone(&failed);
+    /// let log = WriteLog::new(Broken).with_error_handler(move |_| {
+    ///     flag.store(true, Ordering::Relaxed);
+    /// });
+    /// log.flush();
+    /// assert!(failed.load(Ordering::Relaxed));
+    /// ```
+    pub fn with_error_handler<H>(mut self, handler: H) -> Self
+    where
+        H: Fn(&io::Error) + Send + Sync + 'static,
+    {
+        self.on_error = Some(Box::new(handler));
+        self
+    }
+
+    /// How many times writing or flushing failed.
+    pub fn errors(&self) -> u64 {
+        self.errors.load(Ordering::Relaxed)
+    }
+
+    /// Takes the writer out.
+    pub fn into_inner(self) -> W {
+        self.writer
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub(crate) fn writer(&self) -> MutexGuard<'_, W> {
+        self.writer.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Counts the error and calls the handler.
+    pub(crate) fn report(&self, error: &io::Error) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+        if let Some(handler) = &self.on_error {
+            handler(error);
+        }
+    }
+
+    fn count(&self, result: io::Result<()>) {
+        if let Err(error) = result {
+            self.report(&error);
+        }
+    }
+}
+
+impl<W, F> Debug for WriteLog<W, F> {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_struct("WriteLog")
+            .field("level", &self.level)
+            .field("errors", &self.errors)
+            .finish()
+    }
+}
+
+impl<W: Write + Send, F: Format> Log for WriteLog<W, F> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        // A record logged from the Display impls would deadlock on the writer.
+        let mut line = Vec::new();
+        let _ = self
+            .format
+            .format_for(&mut line, record, SystemTime::now(), self.terminal);
+        let result = self.writer().write_all(&line);
+        self.count(result);
+    }
+    fn flush(&self) {
+        let result = self.writer().flush();
+        self.count(result);
+    }
+}